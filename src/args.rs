@@ -0,0 +1,168 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+
+use crate::{
+    commands::Command,
+    platform::FanCurve,
+    sink::{InfluxTarget, MqttBroker},
+};
+
+/// CLI tool for getting temperature, fan, power and cooling fan info from Raspberry Pi 5
+#[derive(Parser)]
+#[command(version, about)]
+pub struct Args {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Update interval in milliseconds
+    #[arg(short, long, value_parser = clap::value_parser!(u64).range(1..), default_value_t = 1000)]
+    pub interval: u64,
+
+    /// Take over the fan control with the `TEMP:PWM` curve, e.g. `50:0,60:100,70:180,80:255`.
+    /// The kernel automatic control is restored on exit.
+    #[arg(long, value_name = "CURVE")]
+    pub fan_curve: Option<FanCurve>,
+
+    /// Degrees the temperature has to fall before the fan duty is lowered
+    #[arg(
+        long,
+        value_name = "DEGREES",
+        default_value_t = 3.0,
+        requires = "fan_curve"
+    )]
+    pub fan_hysteresis: f32,
+
+    /// Lowest non zero fan duty (0-255), lower duties are raised to it so the fan can spin up
+    #[arg(long, value_name = "PWM", default_value_t = 80, requires = "fan_curve")]
+    pub fan_min_duty: u8,
+
+    /// File the energy counters are kept in across restarts,
+    /// `$XDG_STATE_HOME/pi-stats/energy` or `~/.local/state/pi-stats/energy` by default
+    #[arg(long, value_name = "PATH")]
+    pub energy_state: Option<PathBuf>,
+
+    /// Watch the board of a remote `pi-stats serve` instance instead of the local one,
+    /// e.g. `pi5.local:9180` or `http://pi5.local:9180`
+    #[arg(
+        long,
+        value_name = "ADDRESS",
+        conflicts_with_all = ["fan_curve", "energy_state"]
+    )]
+    pub remote: Option<String>,
+
+    /// Sample without the terminal UI, e.g. as a service feeding the outputs below
+    #[arg(long)]
+    pub headless: bool,
+
+    /// Write every sample as InfluxDB line protocol to `-` (stdout, headless only), a file,
+    /// `udp://HOST:PORT` or an HTTP write URL, e.g. `http://localhost:8086?org=ORG&bucket=BUCKET`
+    #[arg(long, value_name = "TARGET")]
+    pub influx: Option<InfluxTarget>,
+
+    /// InfluxDB API token sent with the HTTP writes
+    #[arg(
+        long,
+        value_name = "TOKEN",
+        env = "INFLUX_TOKEN",
+        hide_env_values = true,
+        requires = "influx"
+    )]
+    pub influx_token: Option<String>,
+
+    /// Samples written to InfluxDB at once
+    #[arg(long, value_name = "SAMPLES", default_value_t = 10)]
+    pub influx_batch: usize,
+
+    /// Publish every metric to the MQTT broker `mqtt://[USER[:PASSWORD]@]HOST[:PORT]`,
+    /// `mqtts://` for TLS
+    #[arg(long, value_name = "URL")]
+    pub mqtt: Option<MqttBroker>,
+
+    /// MQTT username, instead of the one in the URL
+    #[arg(long, value_name = "USER", env = "MQTT_USERNAME", requires = "mqtt")]
+    pub mqtt_username: Option<String>,
+
+    /// MQTT password, instead of the one in the URL
+    #[arg(
+        long,
+        value_name = "PASSWORD",
+        env = "MQTT_PASSWORD",
+        hide_env_values = true,
+        requires = "mqtt"
+    )]
+    pub mqtt_password: Option<String>,
+
+    /// Topic prefix, `{hostname}`, `{serial}` and `{model}` are replaced by the board identity
+    #[arg(long, value_name = "TOPIC", default_value = "pi-stats/{hostname}")]
+    pub mqtt_topic: String,
+
+    /// Publish the values retained, so new subscribers get the latest ones right away
+    #[arg(long, requires = "mqtt")]
+    pub mqtt_retain: bool,
+
+    /// Send Home Assistant discovery messages under the prefix (`homeassistant` if not given)
+    #[arg(
+        long,
+        value_name = "PREFIX",
+        num_args = 0..=1,
+        default_missing_value = "homeassistant",
+        requires = "mqtt"
+    )]
+    pub mqtt_discovery: Option<String>,
+
+    /// CA certificate (PEM) of the broker, the system certificates are used by default
+    #[arg(long, value_name = "PATH", requires = "mqtt")]
+    pub mqtt_ca: Option<PathBuf>,
+
+    /// Send every metric as a StatsD gauge over UDP to `HOST:PORT`
+    #[arg(long, value_name = "HOST:PORT")]
+    pub statsd: Option<String>,
+
+    /// Send every metric in the Graphite plaintext format over TCP to `HOST:PORT`
+    #[arg(long, value_name = "HOST:PORT")]
+    pub graphite: Option<String>,
+
+    /// StatsD and Graphite metric prefix, `{hostname}`, `{serial}` and `{model}` are replaced
+    /// by the board identity
+    #[arg(long, value_name = "PREFIX", default_value = "pi.{hostname}")]
+    pub metric_prefix: String,
+
+    /// Export the metrics over OTLP/HTTP (protobuf) to the collector, e.g. `http://localhost:4318`
+    #[arg(
+        long,
+        value_name = "ENDPOINT",
+        env = "OTEL_EXPORTER_OTLP_METRICS_ENDPOINT"
+    )]
+    pub otlp: Option<String>,
+
+    /// Seconds between the OTLP exports
+    #[arg(long, value_name = "SECONDS", default_value_t = 10)]
+    pub otlp_interval: u64,
+
+    /// Header sent with the OTLP exports, e.g. `Authorization=Bearer TOKEN`, can be repeated
+    #[arg(long, value_name = "KEY=VALUE", value_parser = parse_header, requires = "otlp")]
+    pub otlp_header: Vec<(String, String)>,
+
+    /// Answer SNMP v2c requests for the `PI-STATS-MIB` objects on `ADDRESS`, e.g. `0.0.0.0:161`
+    #[arg(long, value_name = "ADDRESS")]
+    pub snmp: Option<String>,
+
+    /// Community the SNMP requests have to carry
+    #[arg(
+        long,
+        value_name = "COMMUNITY",
+        env = "SNMP_COMMUNITY",
+        default_value = "public",
+        hide_env_values = true,
+        requires = "snmp"
+    )]
+    pub snmp_community: String,
+}
+
+fn parse_header(header: &str) -> Result<(String, String), String> {
+    let (key, value) = header
+        .split_once('=')
+        .ok_or_else(|| format!("expected KEY=VALUE, got {header}"))?;
+    Ok((key.trim().to_owned(), value.trim().to_owned()))
+}
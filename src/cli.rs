@@ -1,4 +1,4 @@
-use std::{ops::Sub, time::Duration};
+use std::{ops::Sub, path::PathBuf, time::Duration};
 
 use crossterm::event::{Event, EventStream, KeyCode};
 use ratatui::{
@@ -6,20 +6,50 @@ use ratatui::{
     style::{Color, Style, Stylize},
     symbols,
     text::{Line, Span},
-    widgets::{Axis, Block, Chart, Dataset, Paragraph, Wrap},
+    widgets::{Axis, Block, Chart, Dataset, GraphType, Paragraph, Wrap},
     DefaultTerminal, Frame,
 };
-use tokio::time::{self, Interval};
+use tokio::{
+    signal::unix::{signal, SignalKind},
+    time::{self, Interval},
+};
 
-use crate::{Result, Rpi};
+use crate::{
+    platform::{
+        ChannelKind, InnerThrottleStatus, PsuAnalysis, PsuVerdict, Sensor, SensorState,
+        ThrottleStatus,
+    },
+    sink::Sinks,
+    systemd::{ErrorLog, Service},
+    EnergyState, FanController, Remote, Rpi,
+};
 use tokio_stream::StreamExt;
 
+/// Content of the chart block, switched with `Tab`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum View {
+    Power,
+    Temperature,
+    Load,
+    Soc,
+    FanCurve,
+}
+
 pub struct App {
     quit: bool,
     tick_interval: Interval,
     // ui_refresh_interval: Duration,
     platform: Rpi,
+    fan_control: Option<FanController>,
+    fan_control_error: Option<String>,
+    energy_state: Option<EnergyState>,
+    /// Instance the board state is streamed from, the local board without it
+    remote: Option<Remote>,
+    sinks: Sinks,
+    view: View,
     chart_data: Vec<f64>,
+    temp_chart_data: Vec<f64>,
+    load_chart_data: Vec<f64>,
     chart_bounds: [f64; 2],
 }
 
@@ -40,29 +70,71 @@ impl App {
             //     App::MICROS_IN_SECOND / App::FRAMES_PER_SECOND,
             // ),
             quit: false,
-            platform: Default::default(),
+            platform: Rpi::new(),
+            fan_control: None,
+            fan_control_error: None,
+            energy_state: None,
+            remote: None,
+            sinks: Sinks::default(),
+            view: View::Power,
             chart_data: vec![0.0f64; 200],
+            temp_chart_data: vec![0.0f64; 200],
+            load_chart_data: vec![0.0f64; 200],
             chart_bounds: [0f64, 200f64],
         }
     }
 
     pub fn with_tick_duration(mut self, duration: Duration) -> Self {
         self.tick_interval = time::interval(duration);
+        self.platform.energy.set_interval(duration);
+        self
+    }
+
+    /// Hands the fan over to the controller, applied on every tick
+    pub fn with_fan_control(mut self, controller: FanController) -> Self {
+        self.fan_control = Some(controller);
         self
     }
 
-    pub async fn run(&mut self, mut terminal: DefaultTerminal) -> color_eyre::Result<()> {
+    /// Restores the energy counters from the state file, saved periodically and on exit
+    pub fn with_energy_state(mut self, path: PathBuf) -> Self {
+        let (state, mut meter) = EnergyState::load(path);
+        meter.set_interval(self.tick_interval.period());
+        self.platform.energy = meter;
+        self.energy_state = Some(state);
+        self
+    }
+
+    /// Shows the board of the remote instance instead of the local one
+    pub fn with_remote(mut self, remote: Remote) -> Self {
+        self.platform = remote.latest().unwrap_or_default();
+        self.remote = Some(remote);
+        self
+    }
+
+    /// Hands every sample over to the outputs
+    pub fn with_sinks(mut self, sinks: Sinks) -> Self {
+        self.sinks = sinks;
+        self
+    }
+
+    pub async fn run(&mut self, terminal: &mut DefaultTerminal) -> crate::Result<()> {
         // let mut tick_interval = time::interval(self.tick_interval);
         // let mut ui_refresh_interval = time::interval(self.ui_refresh_interval);
         let mut events = EventStream::new();
+        let mut terminate = signal(SignalKind::terminate())?;
+        let mut hangup = signal(SignalKind::hangup())?;
 
         while !self.quit {
             tokio::select! {
                 Some(Ok(event)) = events.next() => {
                     self.handle_event(&event);
                 },
+                // Graceful exit, so the fan control is handed back to the kernel
+                _ = terminate.recv() => self.quit = true,
+                _ = hangup.recv() => self.quit = true,
                 _ = self.tick_interval.tick() => {
-                    self.on_tick()?;
+                    self.on_tick();
                     terminal.draw(|frame| self.render(frame))?;
                 },
                 // FIXME Not needed if it utilises too much resources
@@ -72,15 +144,95 @@ impl App {
                 // }
             }
         }
+        self.stop().await;
         Ok(())
     }
 
-    fn on_tick(&mut self) -> Result<()> {
-        self.platform.update()?;
-        self.chart_update();
+    /// Samples without the terminal UI until interrupted, the errors are logged.
+    /// Under systemd the readiness and the watchdog pings are sent from the sampling loop.
+    pub async fn run_headless(&mut self) -> crate::Result<()> {
+        let mut interrupt = signal(SignalKind::interrupt())?;
+        let mut terminate = signal(SignalKind::terminate())?;
+        let mut hangup = signal(SignalKind::hangup())?;
+        let mut service = Service::new(self.tick_interval.period());
+        let mut errors = ErrorLog::default();
+
+        while !self.quit {
+            tokio::select! {
+                _ = interrupt.recv() => self.quit = true,
+                _ = terminate.recv() => self.quit = true,
+                _ = hangup.recv() => self.quit = true,
+                _ = self.tick_interval.tick() => {
+                    self.on_tick();
+                    self.report_errors(&mut errors);
+                    service.sampled();
+                },
+            }
+        }
+        service.stopping();
+        self.stop().await;
+        self.report_errors(&mut errors);
         Ok(())
     }
 
+    /// Saves the energy counters and flushes the outputs
+    async fn stop(&mut self) {
+        if let Some(state) = self.energy_state.as_mut() {
+            state.save(&self.platform.energy);
+        }
+        std::mem::take(&mut self.sinks).shutdown().await;
+    }
+
+    /// Logs the errors of the board and of the outputs not reported yet
+    fn report_errors(&self, errors: &mut ErrorLog) {
+        let others = [
+            self.fan_control_error
+                .clone()
+                .map(|e| (String::from("fan control"), e)),
+            self.energy_state
+                .as_ref()
+                .and_then(|state| state.error())
+                .map(|e| (String::from("energy state"), e.to_owned())),
+            self.remote
+                .as_ref()
+                .and_then(|remote| remote.error().map(|e| (remote.address().to_owned(), e))),
+        ]
+        .into_iter()
+        .flatten()
+        .chain(
+            self.sinks
+                .status()
+                .into_iter()
+                .filter_map(|(name, error, _)| error.map(|e| (name.to_owned(), e))),
+        );
+        errors.report(&self.platform, others);
+    }
+
+    fn on_tick(&mut self) {
+        match &self.remote {
+            // Nothing to add to the charts until the next snapshot arrives
+            Some(remote) => match remote.take() {
+                Some(rpi) => self.platform = rpi,
+                None => return,
+            },
+            None => self.platform.update(),
+        }
+        self.fan_control_update();
+        self.chart_update();
+        self.sinks.publish(&self.platform);
+        if let Some(state) = self.energy_state.as_mut() {
+            state.update(&self.platform.energy);
+        }
+    }
+
+    fn fan_control_update(&mut self) {
+        let Some(controller) = self.fan_control.as_mut() else {
+            return;
+        };
+        let temp = self.platform.cpu.temp.current().map(|temp| **temp);
+        self.fan_control_error = controller.update(temp).err().map(|e| e.to_string());
+    }
+
     fn handle_event(&mut self, event: &Event) {
         if let Some(key) = event.as_key_press_event() {
             match key.code {
@@ -94,11 +246,22 @@ impl App {
                 }
                 KeyCode::Char('+') => self.increase_interval(),
                 KeyCode::Char('-') => self.decrease_interval(),
+                KeyCode::Tab => self.next_view(),
                 _ => {}
             }
         }
     }
 
+    fn next_view(&mut self) {
+        self.view = match self.view {
+            View::Power => View::Temperature,
+            View::Temperature => View::Load,
+            View::Load => View::Soc,
+            View::Soc if self.fan_control.is_some() => View::FanCurve,
+            _ => View::Power,
+        }
+    }
+
     fn increase_interval(&mut self) {
         let one_sec = Duration::from_secs(1);
         let five_hundred_ms = Duration::from_millis(500);
@@ -127,27 +290,96 @@ impl App {
     }
 
     fn chart_update(&mut self) {
-        self.chart_data.drain(0..1);
-        self.chart_data
-            .push(self.platform.power.power.total_power.into());
+        let power = self.platform.power.power.current();
+        let temp = self.platform.cpu.temp.current();
+        let usage = self.platform.system.usage.current();
+
+        push_chart_value(
+            &mut self.chart_data,
+            power.map_or(0.0, |power| power.total_power.into()),
+        );
+        push_chart_value(
+            &mut self.temp_chart_data,
+            temp.map_or(0.0, |temp| (**temp).into()),
+        );
+        push_chart_value(
+            &mut self.load_chart_data,
+            usage.map_or(0.0, |usage| usage.total.into()),
+        );
     }
 }
 
+fn push_chart_value(data: &mut Vec<f64>, value: f64) {
+    data.drain(0..1);
+    data.push(value);
+}
+
 /// Rendering implementations ONLY
 impl App {
     fn render(&self, frame: &mut Frame) {
-        let [main_block, chart_block] = Layout::default()
+        let hwmon_lines = self
+            .platform
+            .hwmon
+            .value()
+            .map_or(1, |hwmon| hwmon.devices.len());
+        let thermal_lines = self.platform.thermal.value().map_or(1, |thermal| {
+            thermal.zones.len() + thermal.cooling_devices.len()
+        });
+        let hwmon_height = hwmon_lines.max(thermal_lines).max(1) as u16 + 2;
+        let [header_block, main_block, hwmon_block, chart_block, sources_block] = Layout::default()
             .direction(Direction::Vertical)
-            .constraints(vec![Constraint::Max(8), Constraint::Max(15)])
+            .constraints(vec![
+                Constraint::Length(1),
+                Constraint::Max(15),
+                Constraint::Length(hwmon_height),
+                Constraint::Max(15),
+                Constraint::Length(1),
+            ])
             .areas(frame.area());
         let [main_block, throtte_block] = Layout::default()
             .direction(Direction::Horizontal)
             .constraints(vec![Constraint::Ratio(1, 3), Constraint::Ratio(2, 3)])
             .areas(main_block);
+        let [throtte_block, storage_block] = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(vec![Constraint::Length(6), Constraint::Min(0)])
+            .areas(throtte_block);
+        let [storage_block, network_block] = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(vec![Constraint::Percentage(50), Constraint::Percentage(50)])
+            .areas(storage_block);
+        let [chart_block, processes_block] = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(vec![Constraint::Ratio(2, 3), Constraint::Ratio(1, 3)])
+            .areas(chart_block);
+        let [hwmon_block, thermal_block] = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(vec![Constraint::Ratio(2, 3), Constraint::Ratio(1, 3)])
+            .areas(hwmon_block);
 
+        self.render_header_line(frame, header_block);
         self.render_main_area(frame, main_block);
         self.render_throttle_area(frame, throtte_block);
-        self.render_animated_chart(frame, chart_block);
+        self.render_storage_area(frame, storage_block);
+        self.render_network_area(frame, network_block);
+        self.render_hwmon_area(frame, hwmon_block);
+        self.render_thermal_area(frame, thermal_block);
+        match self.view {
+            View::Power => {
+                let [chart_block, energy_block] = Layout::default()
+                    .direction(Direction::Horizontal)
+                    .constraints(vec![Constraint::Min(0), Constraint::Length(28)])
+                    .areas(chart_block);
+                self.render_animated_chart(frame, chart_block);
+                self.render_energy_area(frame, energy_block);
+            }
+            View::Temperature => self.render_temperature_chart(frame, chart_block),
+            View::Load => self.render_load_chart(frame, chart_block),
+            View::Soc => self.render_soc_area(frame, chart_block),
+            View::FanCurve => self.render_fan_curve_chart(frame, chart_block),
+        }
+        self.render_processes_area(frame, processes_block);
+        self.render_sources_line(frame, sources_block);
     }
 
     fn render_animated_chart(&self, frame: &mut Frame, area: Rect) {
@@ -180,17 +412,338 @@ impl App {
         frame.render_widget(chart, area);
     }
 
+    fn render_energy_area(&self, frame: &mut Frame, area: Rect) {
+        let energy = &self.platform.energy;
+        let mut rails = energy.rails.iter().collect::<Vec<_>>();
+        rails.sort_by(|a, b| b.1.total_cmp(a.1));
+        let text = rails
+            .into_iter()
+            .map(|(rail, energy)| {
+                Line::from(vec![
+                    Span::styled(format!("{rail:<12}"), Style::new().bold()),
+                    Span::raw(format!("{energy:>10.3} Wh")),
+                ])
+            })
+            .collect::<Vec<_>>();
+
+        let energy_paragraph = Paragraph::new(text)
+            .block(Block::bordered().title(Line::from(format!("Energy {:.3} Wh:", energy.total))))
+            .alignment(Alignment::Left);
+
+        frame.render_widget(energy_paragraph, area);
+    }
+
+    fn render_temperature_chart(&self, frame: &mut Frame, area: Rect) {
+        let set = self
+            .temp_chart_data
+            .iter()
+            .enumerate()
+            .map(|(i, temp)| (i as f64, *temp))
+            .collect::<Vec<_>>();
+
+        // Horizontal marker per distinct trip point of all thermal zones
+        let mut trips = self
+            .platform
+            .thermal
+            .value()
+            .map(|thermal| {
+                thermal
+                    .zones
+                    .iter()
+                    .flat_map(|zone| zone.trips.iter())
+                    .map(|trip| (trip.kind.as_str(), trip.temp))
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        trips.sort_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(b.0)));
+        trips.dedup();
+        let trip_lines = trips
+            .iter()
+            .map(|(_, temp)| {
+                let temp = f64::from(*temp);
+                [(self.chart_bounds[0], temp), (self.chart_bounds[1], temp)]
+            })
+            .collect::<Vec<_>>();
+
+        let top = trips
+            .last()
+            .map_or(0.0, |(_, temp)| f64::from(*temp) + 5.0)
+            .max(90.0);
+        let mut datasets = vec![Dataset::default()
+            .name(format!("{:.1}°C", &set.last().unwrap_or(&(0f64, 0f64)).1))
+            .marker(symbols::Marker::Braille)
+            .style(Style::default().fg(Color::Cyan))
+            .data(&set)];
+        datasets.extend(trips.iter().zip(&trip_lines).map(|((kind, temp), line)| {
+            let color = match *kind {
+                "active" => Color::Yellow,
+                "passive" => Color::Magenta,
+                _ => Color::Red,
+            };
+            Dataset::default()
+                .name(format!("{kind} {temp}°C"))
+                .marker(symbols::Marker::Dot)
+                .graph_type(GraphType::Line)
+                .style(Style::default().fg(color))
+                .data(line)
+        }));
+
+        let chart = Chart::new(datasets)
+            .block(Block::bordered())
+            .x_axis(
+                Axis::default()
+                    .labels(["Past", "Now"])
+                    .bounds(self.chart_bounds),
+            )
+            .y_axis(
+                Axis::default()
+                    .title("Temp(°C)")
+                    .labels(["20".to_owned(), format!("{top:.0}")])
+                    .bounds([20.0, top]),
+            );
+
+        frame.render_widget(chart, area);
+    }
+
+    fn render_load_chart(&self, frame: &mut Frame, area: Rect) {
+        let set = self
+            .load_chart_data
+            .iter()
+            .enumerate()
+            .map(|(i, usage)| (i as f64, *usage))
+            .collect::<Vec<_>>();
+        let datasets = vec![Dataset::default()
+            .name(format!("{:.1}%", &set.last().unwrap_or(&(0f64, 0f64)).1))
+            .marker(symbols::Marker::Braille)
+            .style(Style::default().fg(Color::Green))
+            .data(&set)];
+
+        let chart = Chart::new(datasets)
+            .block(Block::bordered())
+            .x_axis(
+                Axis::default()
+                    .labels(["Past", "Now"])
+                    .bounds(self.chart_bounds),
+            )
+            .y_axis(
+                Axis::default()
+                    .title("CPU(%)")
+                    .labels(["0", "50", "100"])
+                    .bounds([0.0, 100.0]),
+            );
+
+        frame.render_widget(chart, area);
+    }
+
+    fn render_soc_area(&self, frame: &mut Frame, area: Rect) {
+        let soc = &self.platform.soc;
+        let label = |name: &str| Span::styled(format!("{name:<10}"), Style::new().bold());
+
+        let mut text = vec![Line::from(vec![
+            label("VOLTS"),
+            sensor_span(&soc.volts, |volts| {
+                volts
+                    .rails()
+                    .map(|(rail, value)| format!("{rail} {value:.4}V"))
+                    .join("  ")
+            }),
+        ])];
+        text.push(Line::from(vec![
+            label("MEMORY"),
+            sensor_span(&soc.memory, |memory| {
+                format!("arm {}MiB  gpu {}MiB", memory.arm, memory.gpu)
+            }),
+        ]));
+        text.push(Line::from(vec![
+            label("RING OSC"),
+            sensor_span(&soc.ring_osc, |osc| {
+                format!(
+                    "{:.3}MHz at {:.4}V {:.1}°C",
+                    osc.frequency, osc.volts, osc.temp
+                )
+            }),
+        ]));
+        match soc.clocks.value() {
+            Some(clocks) => text.extend(clocks.clocks.chunks(2).map(|pair| {
+                let mut spans = Vec::new();
+                for (clock, value) in pair {
+                    spans.push(label(clock.as_str()));
+                    spans.push(Span::raw(format!("{:<14}", format!("{:.2}MHz", **value))));
+                }
+                Line::from(spans)
+            })),
+            None => text.push(Line::from(vec![
+                label("CLOCKS"),
+                sensor_span(&soc.clocks, |_| String::new()),
+            ])),
+        }
+
+        let soc_paragraph = Paragraph::new(text)
+            .block(Block::bordered().title(Line::from("SoC:")))
+            .alignment(Alignment::Left)
+            .wrap(Wrap { trim: true });
+
+        frame.render_widget(soc_paragraph, area);
+    }
+
+    fn render_fan_curve_chart(&self, frame: &mut Frame, area: Rect) {
+        let Some(controller) = &self.fan_control else {
+            return;
+        };
+        let curve_points = controller.curve().points();
+        let (first, last) = (curve_points[0], curve_points[curve_points.len() - 1]);
+        let temp_bounds = [(first.0 - 10.0).into(), (last.0 + 10.0).into()];
+
+        let mut curve = vec![(temp_bounds[0], first.1.into())];
+        curve.extend(
+            curve_points
+                .iter()
+                .map(|(temp, duty)| ((*temp).into(), (*duty).into())),
+        );
+        curve.push((temp_bounds[1], last.1.into()));
+        let operating_point = controller
+            .operating_point()
+            .map(|(temp, duty)| vec![(temp.into(), duty.into())])
+            .unwrap_or_default();
+
+        let datasets = vec![
+            Dataset::default()
+                .name("curve")
+                .marker(symbols::Marker::Braille)
+                .graph_type(GraphType::Line)
+                .style(Style::default().fg(Color::Cyan))
+                .data(&curve),
+            Dataset::default()
+                .name("now")
+                .marker(symbols::Marker::Block)
+                .graph_type(GraphType::Scatter)
+                .style(Style::default().fg(Color::Yellow))
+                .data(&operating_point),
+        ];
+
+        let title = match (&self.fan_control_error, controller.operating_point()) {
+            (Some(error), _) => Span::styled(format!(" {error} "), Style::new().red()),
+            (None, Some((temp, duty))) => Span::raw(format!(
+                " {temp:.1}°C -> {duty} PWM, hysteresis {:.1}°C, min duty {} ",
+                controller.hysteresis(),
+                controller.min_duty()
+            )),
+            (None, None) => Span::styled(" automatic (no temperature) ", Style::new().yellow()),
+        };
+
+        let chart = Chart::new(datasets)
+            .block(Block::bordered().title(Line::from(vec![Span::raw("Fan curve:"), title])))
+            .x_axis(
+                Axis::default()
+                    .title("Temp(°C)")
+                    .labels([
+                        format!("{:.0}", temp_bounds[0]),
+                        format!("{:.0}", temp_bounds[1]),
+                    ])
+                    .bounds(temp_bounds),
+            )
+            .y_axis(
+                Axis::default()
+                    .title("PWM")
+                    .labels(["0", "128", "255"])
+                    .bounds([0.0, 255.0]),
+            );
+
+        frame.render_widget(chart, area);
+    }
+
     fn render_main_area(&self, frame: &mut Frame, area: Rect) {
+        let cpu = &self.platform.cpu;
+        let fan = &self.platform.fan;
+        let system = &self.platform.system;
         let text = vec![
-            Line::from(format!("CPU TEMP   : {:.1}", *self.platform.cpu.temp)),
-            Line::from(format!("ARM CLOCK  : {:.2}", *self.platform.cpu.clock.arm)),
-            Line::from(format!("GPU CLOCK  : {:.2}", *self.platform.cpu.clock.gpu)),
-            Line::from(format!("FAN PWM    : {}", self.platform.fan.pwm)),
-            Line::from(format!("FAN RPM    : {}", self.platform.fan.rpm)),
-            Line::from(format!(
-                "TOTAL POWER: {:<6.3}",
-                &self.platform.power.power.total_power
-            )),
+            Line::from(vec![
+                Span::raw("CPU TEMP   : "),
+                sensor_span(&cpu.temp, |temp| format!("{:.1}", **temp)),
+            ]),
+            Line::from(vec![
+                Span::raw("ARM CLOCK  : "),
+                sensor_span(&cpu.clock, |clock| format!("{:.2}", *clock.arm)),
+            ]),
+            Line::from(vec![
+                Span::raw("GPU CLOCK  : "),
+                sensor_span(&cpu.clock, |clock| format!("{:.2}", *clock.gpu)),
+            ]),
+            Line::from(vec![
+                Span::raw("FAN PWM    : "),
+                sensor_span(fan, |fan| fan.pwm.to_string()),
+            ]),
+            Line::from(vec![
+                Span::raw("FAN RPM    : "),
+                sensor_span(fan, |fan| fan.rpm.to_string()),
+            ]),
+            Line::from(vec![
+                Span::raw("FAN HEALTH : "),
+                match (fan.current(), self.platform.fan_health.issue) {
+                    (None, _) => Span::styled("n/a", Style::new().dark_gray()),
+                    (Some(_), None) => Span::styled("ok", Style::new().green()),
+                    (Some(_), Some(issue)) => Span::styled(issue.to_string(), Style::new().red()),
+                },
+            ]),
+            Line::from(vec![
+                Span::raw("TOTAL POWER: "),
+                sensor_span(&self.platform.power.power, |power| {
+                    format!("{:<6.3}", power.total_power)
+                }),
+            ]),
+            Line::from(vec![
+                Span::raw("PSU        : "),
+                psu_span(&self.platform.psu),
+            ]),
+            Line::from(vec![
+                Span::raw("ENERGY     : "),
+                match self.energy_state.as_ref().and_then(|state| state.error()) {
+                    Some(error) => Span::styled(error.to_owned(), Style::new().red()),
+                    None => Span::raw(format!(
+                        "{:.3} Wh in {}",
+                        self.platform.energy.total,
+                        format_uptime(self.platform.energy.duration())
+                    )),
+                },
+            ]),
+            Line::from(vec![
+                Span::raw("CPU LOAD   : "),
+                sensor_span(&system.usage, |usage| {
+                    let cores = usage
+                        .cores
+                        .iter()
+                        .map(|core| format!("{core:.0}"))
+                        .collect::<Vec<_>>()
+                        .join(" ");
+                    format!("{:.1}% [{cores}]", usage.total)
+                }),
+            ]),
+            Line::from(vec![
+                Span::raw("LOAD AVG   : "),
+                sensor_span(&system.load, |load| {
+                    format!("{:.2} {:.2} {:.2}", load.one, load.five, load.fifteen)
+                }),
+            ]),
+            Line::from(vec![
+                Span::raw("MEMORY     : "),
+                sensor_span(&system.memory, |memory| {
+                    format!(
+                        "{:.2}/{:.2} GiB, swap {:.2} GiB",
+                        gib(memory.used()),
+                        gib(memory.total),
+                        gib(memory.swap_used())
+                    )
+                }),
+            ]),
+            Line::from(vec![
+                Span::raw("PRESSURE   : "),
+                sensor_span(&system.pressure, |pressure| {
+                    format!(
+                        "cpu {:.1} mem {:.1} io {:.1}",
+                        pressure.cpu.some.avg10, pressure.memory.some.avg10, pressure.io.some.avg10
+                    )
+                }),
+            ]),
         ];
 
         let right_title = Line::from(vec![
@@ -214,53 +767,419 @@ impl App {
             .direction(Direction::Horizontal)
             .constraints(vec![Constraint::Percentage(50), Constraint::Percentage(50)])
             .areas(area);
-        let current_throttle_text = vec![
-            Line::from(format!(
-                "ARM FREQUENCY CAPPED: {}",
-                self.platform.power.throttle.current.arm_frequency_capped
-            )),
-            Line::from(format!(
-                "SOFT TEMP LIMIT     : {}",
-                self.platform.power.throttle.current.soft_temp_limit
-            )),
-            Line::from(format!(
-                "THROTTLED           : {}",
-                self.platform.power.throttle.current.throttled
-            )),
-            Line::from(format!(
-                "UNDER VOLTAGE       : {}",
-                self.platform.power.throttle.current.under_voltage
-            )),
-        ];
-        let previous_throttle_text = vec![
-            Line::from(format!(
-                "ARM FREQUENCY CAPPED: {}",
-                self.platform.power.throttle.happened.arm_frequency_capped
-            )),
-            Line::from(format!(
-                "SOFT TEMP LIMIT     : {}",
-                self.platform.power.throttle.happened.soft_temp_limit
-            )),
-            Line::from(format!(
-                "THROTTLED           : {}",
-                self.platform.power.throttle.happened.throttled
-            )),
-            Line::from(format!(
-                "UNDER VOLTAGE       : {}",
-                self.platform.power.throttle.happened.under_voltage
-            )),
-        ];
+        let throttle = &self.platform.power.throttle;
+        let throttle_text = |status: fn(&ThrottleStatus) -> &InnerThrottleStatus| {
+            let Some(inner) = throttle.value().map(status) else {
+                return vec![sensor_line(throttle)];
+            };
+            vec![
+                Line::from(format!(
+                    "ARM FREQUENCY CAPPED: {}",
+                    inner.arm_frequency_capped
+                )),
+                Line::from(format!("SOFT TEMP LIMIT     : {}", inner.soft_temp_limit)),
+                Line::from(format!("THROTTLED           : {}", inner.throttled)),
+                Line::from(format!("UNDER VOLTAGE       : {}", inner.under_voltage)),
+            ]
+        };
+        let current_throttle_text = throttle_text(|status| &status.current);
+        let previous_throttle_text = throttle_text(|status| &status.happened);
+        let stale = match throttle.is_stale() {
+            true => Span::styled(" (stale)", Style::new().yellow()),
+            false => Span::raw(""),
+        };
 
         let current_throttle_paragraph = Paragraph::new(current_throttle_text)
-            .block(Block::bordered().title(Line::from("Current throttle:")))
+            .block(Block::bordered().title(Line::from(vec![
+                Span::raw("Current throttle:"),
+                stale.clone(),
+            ])))
             .alignment(Alignment::Left)
             .wrap(Wrap { trim: true });
         let previous_throttle_paragraph = Paragraph::new(previous_throttle_text)
-            .block(Block::bordered().title(Line::from("Previous throttle:")))
+            .block(
+                Block::bordered().title(Line::from(vec![Span::raw("Previous throttle:"), stale])),
+            )
             .alignment(Alignment::Left)
             .wrap(Wrap { trim: true });
 
         frame.render_widget(current_throttle_paragraph, current_throttle_block);
         frame.render_widget(previous_throttle_paragraph, previous_throtte_block);
     }
+
+    fn render_hwmon_area(&self, frame: &mut Frame, area: Rect) {
+        let hwmon = &self.platform.hwmon;
+        let text = match hwmon.value() {
+            Some(hwmon) if !hwmon.devices.is_empty() => hwmon
+                .devices
+                .iter()
+                .map(|device| {
+                    let channels = device
+                        .channels
+                        .iter()
+                        .map(|channel| {
+                            let value = match channel.kind {
+                                ChannelKind::Fan | ChannelKind::Pwm => {
+                                    format!("{:.0}", channel.value)
+                                }
+                                _ => format!("{:.2}", channel.value),
+                            };
+                            format!("{} {value}{}", channel.name(), channel.kind.unit())
+                        })
+                        .collect::<Vec<_>>()
+                        .join("  ");
+                    Line::from(vec![
+                        Span::styled(format!("{:<12}", device.name), Style::new().bold()),
+                        Span::raw(channels),
+                    ])
+                })
+                .collect(),
+            _ => vec![sensor_line(hwmon)],
+        };
+
+        let hwmon_paragraph = Paragraph::new(text)
+            .block(Block::bordered().title(Line::from("Hardware sensors:")))
+            .alignment(Alignment::Left)
+            .wrap(Wrap { trim: true });
+
+        frame.render_widget(hwmon_paragraph, area);
+    }
+
+    fn render_thermal_area(&self, frame: &mut Frame, area: Rect) {
+        let thermal = &self.platform.thermal;
+        let text = match thermal.value() {
+            Some(thermal) if !thermal.zones.is_empty() || !thermal.cooling_devices.is_empty() => {
+                let zones = thermal.zones.iter().map(|zone| {
+                    let mut spans = vec![
+                        Span::styled(format!("{:<12}", zone.kind), Style::new().bold()),
+                        Span::raw(
+                            zone.temp
+                                .map_or(String::from("n/a"), |temp| format!("{temp:.1}°C")),
+                        ),
+                    ];
+                    for trip in &zone.trips {
+                        let style =
+                            match zone.active_trips().any(|active| active.index == trip.index) {
+                                true => Style::new().yellow().bold(),
+                                false => Style::new().dark_gray(),
+                            };
+                        spans.push(Span::styled(format!(" {}", trip.temp), style));
+                    }
+                    Line::from(spans)
+                });
+                let cooling_devices = thermal.cooling_devices.iter().map(|device| {
+                    Line::from(vec![
+                        Span::styled(format!("{:<12}", device.kind), Style::new().bold()),
+                        Span::raw(format!("state {}/{}", device.cur_state, device.max_state)),
+                    ])
+                });
+                zones.chain(cooling_devices).collect()
+            }
+            _ => vec![sensor_line(thermal)],
+        };
+
+        let thermal_paragraph = Paragraph::new(text)
+            .block(Block::bordered().title(Line::from("Thermal trips:")))
+            .alignment(Alignment::Left)
+            .wrap(Wrap { trim: true });
+
+        frame.render_widget(thermal_paragraph, area);
+    }
+
+    fn render_storage_area(&self, frame: &mut Frame, area: Rect) {
+        let storage = &self.platform.storage;
+        let mut text = match storage.io.value() {
+            Some(io) => io
+                .iter()
+                .map(|disk| {
+                    Line::from(vec![
+                        Span::styled(format!("{:<12}", disk.name), Style::new().bold()),
+                        Span::raw(format!(
+                            "R {:>9}/s  W {:>9}/s",
+                            bytes(disk.read),
+                            bytes(disk.write)
+                        )),
+                    ])
+                })
+                .collect(),
+            None => vec![sensor_line(&storage.io)],
+        };
+        if let Some(drives) = storage.nvme.value() {
+            text.extend(drives.iter().map(|drive| {
+                let temps = drive
+                    .temps
+                    .iter()
+                    .map(|(label, temp)| format!("{label} {temp:.1}°C"))
+                    .collect::<Vec<_>>()
+                    .join("  ");
+                Line::from(vec![
+                    Span::styled(format!("{:<12}", drive.name), Style::new().bold()),
+                    Span::raw(temps),
+                ])
+            }));
+        }
+
+        let storage_paragraph = Paragraph::new(text)
+            .block(Block::bordered().title(Line::from("Storage:")))
+            .alignment(Alignment::Left)
+            .wrap(Wrap { trim: true });
+
+        frame.render_widget(storage_paragraph, area);
+    }
+
+    fn render_network_area(&self, frame: &mut Frame, area: Rect) {
+        let interfaces = &self.platform.network.interfaces;
+        let text = match interfaces.value() {
+            Some(interfaces) => interfaces
+                .iter()
+                .map(|interface| {
+                    let errors = interface.rx_errors
+                        + interface.tx_errors
+                        + interface.rx_dropped
+                        + interface.tx_dropped;
+                    Line::from(vec![
+                        Span::styled(format!("{:<10}", interface.name), Style::new().bold()),
+                        Span::raw(format!(
+                            "RX {:>9}/s  TX {:>9}/s ",
+                            bytes(interface.rx),
+                            bytes(interface.tx)
+                        )),
+                        match errors {
+                            0 => Span::raw(""),
+                            _ => Span::styled(
+                                format!(
+                                    "err {}/{} drop {}/{}",
+                                    interface.rx_errors,
+                                    interface.tx_errors,
+                                    interface.rx_dropped,
+                                    interface.tx_dropped
+                                ),
+                                Style::new().yellow(),
+                            ),
+                        },
+                    ])
+                })
+                .collect(),
+            None => vec![sensor_line(interfaces)],
+        };
+
+        let network_paragraph = Paragraph::new(text)
+            .block(Block::bordered().title(Line::from("Network:")))
+            .alignment(Alignment::Left)
+            .wrap(Wrap { trim: true });
+
+        frame.render_widget(network_paragraph, area);
+    }
+
+    fn render_processes_area(&self, frame: &mut Frame, area: Rect) {
+        let processes = &self.platform.processes.processes;
+        let total_power = self
+            .platform
+            .power
+            .power
+            .value()
+            .map_or(0.0, |power| power.total_power);
+        let rows = area.height.saturating_sub(3) as usize;
+
+        let text = match processes.value() {
+            Some(processes) => {
+                let header = Line::from(Span::styled(
+                    format!("{:>7} {:<16} {:>6} {:>6}", "PID", "NAME", "CPU%", "W"),
+                    Style::new().bold(),
+                ));
+                std::iter::once(header)
+                    .chain(processes.top.iter().take(rows).map(|process| {
+                        Line::from(format!(
+                            "{:>7} {:<16} {:>6.1} {:>6.3}",
+                            process.pid,
+                            process.name,
+                            process.cpu,
+                            process.power(total_power)
+                        ))
+                    }))
+                    .collect()
+            }
+            None => vec![sensor_line(processes)],
+        };
+
+        let processes_paragraph = Paragraph::new(text)
+            .block(Block::bordered().title(Line::from("Top processes:")))
+            .alignment(Alignment::Left);
+
+        frame.render_widget(processes_paragraph, area);
+    }
+
+    fn render_header_line(&self, frame: &mut Frame, area: Rect) {
+        let board = &self.platform.board;
+        let na = || String::from("n/a");
+        let mut spans = Vec::new();
+        if let Some(remote) = &self.remote {
+            let name = board
+                .hostname
+                .clone()
+                .unwrap_or_else(|| remote.address().to_owned());
+            spans.push(Span::styled(
+                format!(" {name} "),
+                Style::new().bold().black().on_cyan(),
+            ));
+            spans.push(Span::raw(" "));
+        }
+        spans.extend([
+            Span::styled(
+                board
+                    .model
+                    .clone()
+                    .unwrap_or_else(|| String::from("Unknown board")),
+                Style::new().bold().cyan(),
+            ),
+            Span::raw(format!(
+                " | {} | serial {} | fw {} | kernel {}",
+                board.hostname.clone().unwrap_or_else(na),
+                board.serial.clone().unwrap_or_else(na),
+                board.firmware.clone().unwrap_or_else(na),
+                board.kernel.clone().unwrap_or_else(na),
+            )),
+        ]);
+        if !board.config.is_empty() {
+            let config = board
+                .config
+                .iter()
+                .map(|(name, value)| format!("{name}={value}"))
+                .collect::<Vec<_>>()
+                .join(" ");
+            spans.push(Span::raw(format!(" | {config}")));
+        }
+        if let Some(uptime) = board.uptime() {
+            spans.push(Span::raw(format!(" | up {}", format_uptime(uptime))));
+        }
+
+        frame.render_widget(Line::from(spans), area);
+    }
+
+    /// State and error counter of every local source, by the name in the sources line
+    fn render_sources_line(&self, frame: &mut Frame, area: Rect) {
+        let sources = self
+            .platform
+            .sources()
+            .map(|(name, state, errors)| (name, sensor_status(state, errors)));
+
+        let remote = self.remote.iter().map(|remote| {
+            let status = match (remote.is_connected(), remote.error()) {
+                (true, _) => Span::styled("ok", Style::new().green()),
+                (false, None) => Span::styled("connecting", Style::new().yellow()),
+                (false, Some(_)) => Span::styled("err", Style::new().red()),
+            };
+            ("remote", status)
+        });
+        let sinks = self
+            .sinks
+            .status()
+            .into_iter()
+            .map(|(name, error, dropped)| {
+                let status = match (error, dropped) {
+                    (Some(_), _) => Span::styled("err", Style::new().red()),
+                    (None, 0) => Span::styled("ok", Style::new().green()),
+                    (None, dropped) => {
+                        Span::styled(format!("ok ({dropped} dropped)"), Style::new().yellow())
+                    }
+                };
+                (name, status)
+            });
+
+        let mut spans = Vec::new();
+        for (i, (name, status)) in sources.into_iter().chain(remote).chain(sinks).enumerate() {
+            if i > 0 {
+                spans.push(Span::raw(" | "));
+            }
+            spans.push(Span::raw(format!("{name} ")));
+            spans.push(status);
+        }
+
+        frame.render_widget(Line::from(spans), area);
+    }
+}
+
+/// Uptime like `3d 04:12:33`
+fn format_uptime(uptime: Duration) -> String {
+    let seconds = uptime.as_secs();
+    let (days, hours) = (seconds / 86_400, seconds / 3_600 % 24);
+    let (minutes, seconds) = (seconds / 60 % 60, seconds % 60);
+    match days {
+        0 => format!("{hours:02}:{minutes:02}:{seconds:02}"),
+        days => format!("{days}d {hours:02}:{minutes:02}:{seconds:02}"),
+    }
+}
+
+/// Human readable amount of bytes, e.g. `1.5 MiB`
+fn bytes(bytes: f64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut value = bytes;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{value:.1} {}", UNITS[unit])
+}
+
+fn gib(kib: u64) -> f64 {
+    kib as f64 / (1024.0 * 1024.0)
+}
+
+/// Formats the value of the sensor, `n/a` if the source is missing
+/// and a stale marker with age if the latest update has failed
+fn sensor_span<T>(sensor: &Sensor<T>, format: impl Fn(&T) -> String) -> Span<'static> {
+    match (&sensor.state, sensor.value()) {
+        (SensorState::Ok, Some(value)) => Span::raw(format(value)),
+        (_, Some(value)) => Span::styled(
+            format!(
+                "{} (stale {}s)",
+                format(value),
+                sensor.age().unwrap_or_default().as_secs()
+            ),
+            Style::new().yellow(),
+        ),
+        (SensorState::Error(_), None) => Span::styled("err", Style::new().red()),
+        (_, None) => Span::styled("n/a", Style::new().dark_gray()),
+    }
+}
+
+/// PSU verdict colored by its severity with the input voltage and the estimated current
+fn psu_span(psu: &PsuAnalysis) -> Span<'static> {
+    let style = match psu.verdict {
+        PsuVerdict::Unknown => Style::new().dark_gray(),
+        PsuVerdict::Adequate => Style::new().green(),
+        PsuVerdict::Marginal(_) => Style::new().yellow().bold(),
+        PsuVerdict::Inadequate(_) => Style::new().red().bold(),
+    };
+    let mut text = psu.verdict.to_string();
+    if let (Some(volts), Some(min)) = (psu.volts, psu.min_volts) {
+        text.push_str(&format!(" {volts:.2}V (min {min:.2}V)"));
+    }
+    if let Some(amps) = psu.amps {
+        text.push_str(&format!(" {amps:.2}/{}A", psu.profile));
+    }
+    Span::styled(text, style)
+}
+
+/// Single line placeholder for the blocks without any value to show
+fn sensor_line<T>(sensor: &Sensor<T>) -> Line<'static> {
+    match &sensor.state {
+        SensorState::Error(reason) => Line::from(Span::styled(reason.clone(), Style::new().red())),
+        _ => Line::from(Span::styled("n/a", Style::new().dark_gray())),
+    }
+}
+
+/// Short state of the source with the error counter
+fn sensor_status(state: &SensorState, errors: u64) -> Span<'static> {
+    match (state, errors) {
+        (SensorState::Ok, 0) => Span::styled("ok", Style::new().green()),
+        (SensorState::Ok, errors) => {
+            Span::styled(format!("ok ({errors} err)"), Style::new().green())
+        }
+        (SensorState::Unavailable, _) => Span::styled("n/a", Style::new().dark_gray()),
+        (SensorState::Error(_), errors) => {
+            Span::styled(format!("err ({errors})"), Style::new().red())
+        }
+    }
 }
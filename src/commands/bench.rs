@@ -0,0 +1,436 @@
+//! `pi-stats bench` loads every selected CPU core with a built-in load generator
+//! while sampling the board at a high rate, then reports how the cooling and the
+//! power supply coped with it.
+//!
+//! The run is saved as a [recording](crate::recording), so cases, heatsinks
+//! and power supplies can be compared later.
+
+use std::{
+    collections::BTreeMap,
+    hint::black_box,
+    panic,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use tokio::{
+    signal::unix::{signal, SignalKind},
+    task,
+    time::{self, MissedTickBehavior},
+};
+
+use crate::{
+    platform::{ClockMhz, PwmValue, RpmValue, TempValue, Watt},
+    recording::Recorder,
+    Result, Rpi,
+};
+
+/// Share of the run at its end the steady state values are taken from
+const STEADY_STATE_SHARE: f64 = 0.25;
+
+#[derive(clap::Args)]
+pub struct BenchArgs {
+    /// Number of load threads, every CPU core by default
+    #[arg(short, long)]
+    threads: Option<usize>,
+
+    /// Seconds to run the load for
+    #[arg(short, long, default_value_t = 300)]
+    duration: u64,
+
+    /// Milliseconds between the samples
+    #[arg(short, long, value_parser = clap::value_parser!(u64).range(1..), default_value_t = 250)]
+    interval: u64,
+
+    /// Recording file, `bench-<unix time>.csv` by default
+    #[arg(short, long, value_name = "PATH")]
+    output: Option<PathBuf>,
+}
+
+/// Values collected at every sample
+#[derive(Default)]
+struct Stats {
+    samples: Vec<Sample>,
+    time_to_throttle: Option<Duration>,
+    throttle_read: bool,
+    rails: BTreeMap<String, (Watt, u32)>,
+}
+
+struct Sample {
+    elapsed: Duration,
+    temp: Option<TempValue>,
+    arm: Option<ClockMhz>,
+    power: Option<Watt>,
+    pwm: Option<PwmValue>,
+    rpm: Option<RpmValue>,
+}
+
+impl Stats {
+    fn add(&mut self, rpi: &Rpi, elapsed: Duration) {
+        let throttle = rpi.power.throttle.current().map(|status| &status.current);
+        self.throttle_read |= throttle.is_some();
+        if self.time_to_throttle.is_none()
+            && throttle.is_some_and(|status| {
+                status.throttled || status.arm_frequency_capped || status.soft_temp_limit
+            })
+        {
+            self.time_to_throttle = Some(elapsed);
+        }
+
+        let power = rpi.power.power.current();
+        for rail in power.iter().flat_map(|power| &power.power_map) {
+            let (sum, count) = self.rails.entry(rail.measure.clone()).or_default();
+            *sum += rail.volts * rail.amps;
+            *count += 1;
+        }
+
+        let fan = rpi.fan.current();
+        self.samples.push(Sample {
+            elapsed,
+            temp: rpi.cpu.temp.current().map(|temp| **temp),
+            arm: rpi.cpu.clock.current().map(|clock| *clock.arm),
+            power: power.map(|power| power.total_power),
+            pwm: fan.map(|fan| fan.pwm),
+            rpm: fan.map(|fan| fan.rpm),
+        });
+    }
+
+    /// Samples of the last part of the run
+    fn steady(&self) -> &[Sample] {
+        let end = self.samples.last().map_or(Duration::ZERO, |s| s.elapsed);
+        let from = end.mul_f64(1.0 - STEADY_STATE_SHARE);
+        let start = self.samples.partition_point(|sample| sample.elapsed < from);
+        &self.samples[start..]
+    }
+
+    fn summary(&self) -> Summary {
+        let all = &self.samples;
+        let steady = self.steady();
+
+        let pwm = all.iter().filter_map(|s| s.pwm.map(Into::into));
+        let rpm = all.iter().filter_map(|s| s.rpm.map(|rpm| rpm as f32));
+        let fan = match (mean(pwm), all.iter().filter_map(|s| s.rpm).max()) {
+            (Some(pwm), Some(max)) => Some((pwm, mean(rpm).unwrap_or_default(), max)),
+            _ => None,
+        };
+
+        let clocks = all.iter().filter_map(|s| s.arm).collect::<Vec<_>>();
+        let steady_clocks = steady.iter().filter_map(|s| s.arm).collect::<Vec<_>>();
+        let clock = match (
+            clocks.iter().copied().reduce(ClockMhz::min),
+            clocks.iter().copied().reduce(ClockMhz::max),
+            mean(steady_clocks.iter().copied()),
+        ) {
+            (Some(min), Some(max), Some(steady)) => Some(ClockStability {
+                min,
+                max,
+                steady,
+                deviation: deviation(&steady_clocks, steady),
+                below_max: clocks.iter().filter(|&&clock| clock < max).count() as f32 * 100.0
+                    / clocks.len() as f32,
+            }),
+            _ => None,
+        };
+
+        Summary {
+            duration: all.last().map_or(Duration::ZERO, |s| s.elapsed),
+            peak_temp: all.iter().filter_map(|s| s.temp).reduce(TempValue::max),
+            steady_temp: mean(steady.iter().filter_map(|s| s.temp)),
+            power: mean(all.iter().filter_map(|s| s.power)),
+            rails: self
+                .rails
+                .iter()
+                .map(|(rail, (sum, count))| (rail.clone(), sum / *count as Watt))
+                .collect(),
+            fan,
+            clock,
+        }
+    }
+}
+
+/// Aggregates of the run shown in the report
+struct Summary {
+    duration: Duration,
+    peak_temp: Option<TempValue>,
+    /// Mean temperature of the steady state
+    steady_temp: Option<TempValue>,
+    /// Mean total power
+    power: Option<Watt>,
+    /// Mean power per rail
+    rails: Vec<(String, Watt)>,
+    /// Mean PWM, mean and highest speed
+    fan: Option<(f32, f32, RpmValue)>,
+    clock: Option<ClockStability>,
+}
+
+struct ClockStability {
+    min: ClockMhz,
+    max: ClockMhz,
+    /// Mean clock of the steady state and its standard deviation
+    steady: ClockMhz,
+    deviation: ClockMhz,
+    /// Share of the samples below the highest clock in percent
+    below_max: f32,
+}
+
+pub async fn run(args: BenchArgs) -> Result<()> {
+    let threads = match args.threads {
+        Some(threads) => threads,
+        None => thread::available_parallelism()?.get(),
+    };
+    let duration = Duration::from_secs(args.duration);
+    let output = args.output.unwrap_or_else(|| {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        PathBuf::from(format!("bench-{}.csv", now.as_secs()))
+    });
+
+    let mut rpi = Rpi::new();
+    let mut recorder = Recorder::create(&output, &rpi.board)?;
+    let mut stats = Stats::default();
+    let mut interrupt = signal(SignalKind::interrupt())?;
+    let mut terminate = signal(SignalKind::terminate())?;
+    let mut interval = time::interval(Duration::from_millis(args.interval));
+    // A slow update delays the next sample instead of bunching the missed ones
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    println!(
+        "Loading {threads} threads for {}s, recording to {}",
+        duration.as_secs(),
+        output.display()
+    );
+    let stop = Arc::new(AtomicBool::new(false));
+    let workers = (0..threads)
+        .map(|_| {
+            let stop = stop.clone();
+            thread::spawn(move || load(&stop))
+        })
+        .collect::<Vec<_>>();
+
+    let started = Instant::now();
+    let mut interrupted = false;
+    while started.elapsed() < duration {
+        tokio::select! {
+            _ = interval.tick() => {}
+            _ = interrupt.recv() => interrupted = true,
+            _ = terminate.recv() => interrupted = true,
+        }
+        if interrupted {
+            break;
+        }
+        // The update runs the firmware commands, it blocks for a good part of the interval
+        rpi = task::spawn_blocking(move || {
+            rpi.update();
+            rpi
+        })
+        .await
+        .unwrap_or_else(|e| panic::resume_unwind(e.into_panic()));
+        stats.add(&rpi, started.elapsed());
+        recorder.record(&rpi)?;
+    }
+
+    stop.store(true, Ordering::Relaxed);
+    for worker in workers {
+        let _ = worker.join();
+    }
+    recorder.finish()?;
+
+    if interrupted {
+        println!("Interrupted after {}s", started.elapsed().as_secs());
+    }
+    report(&stats, &rpi);
+    println!("Recording: {}", output.display());
+    Ok(())
+}
+
+/// Floating point busy loop, keeps a core at full load until stopped
+fn load(stop: &AtomicBool) {
+    let mut value = 1.0f64;
+    while !stop.load(Ordering::Relaxed) {
+        for _ in 0..100_000 {
+            value = black_box(value.mul_add(1.000_000_1, 0.000_000_1).sqrt() + 1.0);
+        }
+    }
+}
+
+fn report(stats: &Stats, rpi: &Rpi) {
+    let summary = stats.summary();
+
+    println!();
+    println!("== Bench ==");
+    println!(
+        "Duration     : {:.1}s, {} samples",
+        summary.duration.as_secs_f64(),
+        stats.samples.len()
+    );
+    match (stats.time_to_throttle, stats.throttle_read) {
+        (Some(time), _) => println!("Throttled    : after {:.1}s", time.as_secs_f64()),
+        (None, true) => println!("Throttled    : never"),
+        (None, false) => println!("Throttled    : n/a"),
+    }
+
+    println!(
+        "Peak temp    : {}",
+        value(summary.peak_temp, |temp| format!("{temp:.1} °C"))
+    );
+    println!(
+        "Steady temp  : {}",
+        value(summary.steady_temp, |temp| format!("{temp:.1} °C"))
+    );
+
+    println!(
+        "Avg power    : {}",
+        value(summary.power, |power| format!("{power:.3} W"))
+    );
+    for (rail, power) in &summary.rails {
+        println!("  {rail:<11}: {power:.3} W");
+    }
+    let psu = &rpi.psu;
+    println!("PSU          : {}", psu.verdict);
+    if let (Some(min), Some(sag), Some(peak)) = (psu.min_volts, psu.sag(), psu.peak_amps) {
+        println!(
+            "  input min {min:.2} V, sag {sag:.2} V, peak {peak:.2} A of the {} A profile",
+            psu.profile
+        );
+    }
+
+    println!(
+        "Fan          : {}",
+        match summary.fan {
+            Some((pwm, rpm, max)) => format!("avg PWM {pwm:.0}, avg {rpm:.0} RPM, max {max} RPM"),
+            None => String::from("n/a"),
+        }
+    );
+    if let Some(issue) = rpi.fan_health.issue {
+        println!("Fan health   : {issue}");
+    }
+
+    match summary.clock {
+        Some(clock) => println!(
+            "ARM clock    : {:.0}-{:.0} MHz, steady {:.0} ± {:.0} MHz, {:.0}% of samples below max",
+            clock.min, clock.max, clock.steady, clock.deviation, clock.below_max
+        ),
+        None => println!("ARM clock    : n/a"),
+    }
+}
+
+fn mean(values: impl Iterator<Item = f32>) -> Option<f32> {
+    let (sum, count) = values.fold((0.0, 0), |(sum, count), value| (sum + value, count + 1));
+    (count > 0).then(|| sum / count as f32)
+}
+
+fn deviation(values: &[f32], mean: f32) -> f32 {
+    let variance = values
+        .iter()
+        .map(|value| (value - mean).powi(2))
+        .sum::<f32>()
+        / values.len().max(1) as f32;
+    variance.sqrt()
+}
+
+fn value(value: Option<f32>, format: impl Fn(f32) -> String) -> String {
+    value.map_or(String::from("n/a"), format)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(seconds: u64, temp: TempValue, arm: ClockMhz) -> Sample {
+        Sample {
+            elapsed: Duration::from_secs(seconds),
+            temp: Some(temp),
+            arm: Some(arm),
+            power: Some(8.0),
+            pwm: None,
+            rpm: None,
+        }
+    }
+
+    #[test]
+    fn steady_state_is_the_last_quarter() {
+        let mut stats = Stats::default();
+        assert!(stats.steady().is_empty());
+        stats.samples = (0..=100).map(|s| sample(s, 50.0, 2400.0)).collect();
+        let steady = stats.steady();
+        assert_eq!(steady.len(), 26);
+        assert_eq!(steady[0].elapsed, Duration::from_secs(75));
+        stats.samples.truncate(1);
+        assert_eq!(stats.steady().len(), 1);
+    }
+
+    #[test]
+    fn summarizes_the_run() {
+        let stats = Stats {
+            samples: vec![
+                sample(0, 45.0, 2400.0),
+                sample(1, 60.0, 2400.0),
+                sample(2, 85.5, 2400.0),
+                sample(3, 80.0, 1800.0),
+                // The steady state, the last quarter from 6 s on
+                sample(6, 81.0, 2000.0),
+                sample(7, 83.0, 2200.0),
+                Sample {
+                    pwm: Some(255),
+                    rpm: Some(8000),
+                    ..sample(8, 82.0, 2400.0)
+                },
+            ],
+            rails: BTreeMap::from([
+                (String::from("VDD_CORE"), (12.0, 4)),
+                (String::from("EXT5V"), (5.0, 2)),
+            ]),
+            ..Default::default()
+        };
+        let summary = stats.summary();
+
+        assert_eq!(summary.duration, Duration::from_secs(8));
+        assert_eq!(summary.peak_temp, Some(85.5));
+        assert_eq!(summary.steady_temp, Some(82.0));
+        assert_eq!(summary.power, Some(8.0));
+        assert_eq!(
+            summary.rails,
+            [
+                (String::from("EXT5V"), 2.5),
+                (String::from("VDD_CORE"), 3.0)
+            ]
+        );
+        assert_eq!(summary.fan, Some((255.0, 8000.0, 8000)));
+
+        let clock = summary.clock.unwrap();
+        assert_eq!(
+            (clock.min, clock.max, clock.steady),
+            (1800.0, 2400.0, 2200.0)
+        );
+        assert!((clock.deviation - 163.3).abs() < 0.1, "{}", clock.deviation);
+        assert!((clock.below_max - 300.0 / 7.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn summary_without_readings() {
+        let stats = Stats {
+            samples: vec![Sample {
+                elapsed: Duration::from_secs(1),
+                temp: None,
+                arm: None,
+                power: None,
+                pwm: None,
+                rpm: None,
+            }],
+            ..Default::default()
+        };
+        let summary = stats.summary();
+        assert_eq!(summary.peak_temp, None);
+        assert_eq!(summary.steady_temp, None);
+        assert_eq!(summary.power, None);
+        assert!(summary.rails.is_empty());
+        assert_eq!(summary.fan, None);
+        assert!(summary.clock.is_none());
+    }
+}
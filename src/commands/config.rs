@@ -0,0 +1,257 @@
+//! `pi-stats config` shows the effective overclocking, undervolting and temperature limit
+//! settings and compares them with the live clocks and voltages.
+//!
+//! The values applied by the firmware (`vcgencmd get_config`) are listed next to
+//! the `config.txt` line setting them, lines under filters not matching the board
+//! are listed as ignored.
+
+use std::{
+    io::{self, Write},
+    path::PathBuf,
+};
+
+use crate::{
+    platform::{BootConfig, FirmwareConfig, InnerThrottleStatus, OVERCLOCK_SETTINGS},
+    Result, Rpi,
+};
+
+/// Part of the configured clock the live clock may be below without a warning
+const CLOCK_TOLERANCE: f32 = 0.05;
+
+#[derive(clap::Args)]
+pub struct ConfigArgs {
+    /// `config.txt` to read, `/boot/firmware/config.txt` or `/boot/config.txt` by default
+    #[arg(short, long, value_name = "PATH")]
+    file: Option<PathBuf>,
+
+    /// List every applied setting, not only the overclocking ones
+    #[arg(short, long)]
+    all: bool,
+}
+
+pub async fn run(args: ConfigArgs) -> Result<()> {
+    let mut rpi = Rpi::new();
+    rpi.update();
+    let firmware = FirmwareConfig::read();
+    let config = match args.file {
+        Some(path) => BootConfig::read(&path, &rpi.board),
+        None => BootConfig::find().and_then(|path| BootConfig::read(&path, &rpi.board)),
+    };
+
+    let mut out = io::stdout().lock();
+    writeln!(out, "== Config ==")?;
+    writeln!(
+        out,
+        "Board       : {}",
+        rpi.board.model.as_deref().unwrap_or("n/a")
+    )?;
+    match &config {
+        Ok(config) => {
+            writeln!(out, "config.txt  : {}", config.path.display())?;
+            for include in &config.missing_includes {
+                writeln!(out, "Missing     : {}", include.display())?;
+            }
+        }
+        Err(e) => writeln!(out, "config.txt  : error: {e}")?,
+    }
+    if let Err(e) = &firmware {
+        writeln!(out, "Firmware    : error: {e}")?;
+    }
+    writeln!(out)?;
+
+    let firmware = firmware.unwrap_or_default();
+    let config = config.unwrap_or_default();
+    write_settings(&mut out, &firmware, &config, args.all)?;
+    write_ignored(&mut out, &config, args.all)?;
+    write_live(&mut out, &rpi, &firmware)?;
+    write_warnings(&mut out, &rpi, &firmware, &config)?;
+    Ok(())
+}
+
+fn write_settings(
+    out: &mut impl Write,
+    firmware: &FirmwareConfig,
+    config: &BootConfig,
+    all: bool,
+) -> Result<()> {
+    let mut keys = OVERCLOCK_SETTINGS
+        .iter()
+        .map(|key| key.to_string())
+        .collect::<Vec<_>>();
+    if all {
+        keys.extend(
+            firmware
+                .settings
+                .iter()
+                .map(|(key, _)| key.clone())
+                .chain(config.lines.iter().map(|line| line.key.clone()))
+                .filter(|key| !OVERCLOCK_SETTINGS.contains(&key.as_str())),
+        );
+        keys[OVERCLOCK_SETTINGS.len()..].sort_unstable();
+        keys.dedup();
+    }
+
+    writeln!(out, "== Settings ==")?;
+    writeln!(
+        out,
+        "{:<22}{:>12}{:>12}  SOURCE",
+        "SETTING", "FIRMWARE", "CONFIG.TXT"
+    )?;
+    for key in &keys {
+        let line = config.effective(key);
+        let applied = firmware.get(key);
+        if applied.is_none() && line.is_none() {
+            continue;
+        }
+        let source = match line {
+            Some(line) => format!("{}:{} {}", line.file.display(), line.line, line.section),
+            None => String::from("default"),
+        };
+        writeln!(
+            out,
+            "{key:<22}{:>12}{:>12}  {}",
+            applied.unwrap_or("-"),
+            line.map_or("-", |line| line.value.as_str()),
+            source.trim_end()
+        )?;
+    }
+    writeln!(out)?;
+    Ok(())
+}
+
+fn write_ignored(out: &mut impl Write, config: &BootConfig, all: bool) -> Result<()> {
+    let ignored = config
+        .lines
+        .iter()
+        .filter(|line| !line.applies && (all || OVERCLOCK_SETTINGS.contains(&line.key.as_str())))
+        .collect::<Vec<_>>();
+    if ignored.is_empty() {
+        return Ok(());
+    }
+
+    writeln!(out, "== Ignored, filters do not match this board ==")?;
+    for line in ignored {
+        writeln!(
+            out,
+            "{:<22}{:>12}  {}:{} {}",
+            line.key,
+            line.value,
+            line.file.display(),
+            line.line,
+            line.section
+        )?;
+    }
+    writeln!(out)?;
+    Ok(())
+}
+
+fn write_live(out: &mut impl Write, rpi: &Rpi, firmware: &FirmwareConfig) -> Result<()> {
+    let configured = |key: &str| firmware.get(key).unwrap_or("n/a");
+    writeln!(out, "== Live ==")?;
+    match rpi.cpu.clock.current() {
+        Some(clock) => {
+            writeln!(
+                out,
+                "ARM clock   : {:.0} MHz (arm_freq {} MHz)",
+                *clock.arm,
+                configured("arm_freq")
+            )?;
+            writeln!(
+                out,
+                "GPU clock   : {:.0} MHz (core_freq {} MHz)",
+                *clock.gpu,
+                configured("core_freq")
+            )?;
+        }
+        None => writeln!(out, "Clocks      : n/a")?,
+    }
+    match rpi.soc.volts.current() {
+        Some(volts) => writeln!(
+            out,
+            "Core volts  : {:.4} V (over_voltage {}, over_voltage_delta {} µV)",
+            volts.core,
+            configured("over_voltage"),
+            configured("over_voltage_delta")
+        )?,
+        None => writeln!(out, "Core volts  : n/a")?,
+    }
+    match rpi.cpu.temp.current() {
+        Some(temp) => writeln!(
+            out,
+            "Temperature : {:.1} °C (temp_limit {} °C, temp_soft_limit {} °C)",
+            **temp,
+            configured("temp_limit"),
+            configured("temp_soft_limit")
+        )?,
+        None => writeln!(out, "Temperature : n/a")?,
+    }
+    writeln!(out)?;
+    Ok(())
+}
+
+fn write_warnings(
+    out: &mut impl Write,
+    rpi: &Rpi,
+    firmware: &FirmwareConfig,
+    config: &BootConfig,
+) -> Result<()> {
+    let mut warnings = Vec::new();
+
+    for key in OVERCLOCK_SETTINGS {
+        if let (Some(line), Some(applied)) = (config.effective(key), firmware.get(key))
+            && line.value != applied
+        {
+            warnings.push(format!(
+                "{key}={} in config.txt, but the firmware applied {applied} (clamped or changed since boot)",
+                line.value
+            ));
+        }
+    }
+
+    let throttle = rpi.power.throttle.current();
+    let arm_freq = firmware
+        .get("arm_freq")
+        .and_then(|value| value.parse::<f32>().ok());
+    if let (Some(throttle), Some(clock), Some(arm_freq)) =
+        (throttle, rpi.cpu.clock.current(), arm_freq)
+    {
+        let reasons = reasons(&throttle.current);
+        if !reasons.is_empty() && *clock.arm < arm_freq * (1.0 - CLOCK_TOLERANCE) {
+            warnings.push(format!(
+                "ARM clock {:.0} MHz is below arm_freq {arm_freq:.0} MHz: {}",
+                *clock.arm,
+                reasons.join(", ")
+            ));
+        }
+    }
+    if let Some(throttle) = throttle {
+        let reasons = reasons(&throttle.happened);
+        if !reasons.is_empty() {
+            warnings.push(format!(
+                "{} occurred since boot, the configured settings may not be sustained",
+                reasons.join(", ")
+            ));
+        }
+    }
+
+    writeln!(out, "== Warnings ==")?;
+    if warnings.is_empty() {
+        writeln!(out, "none")?;
+    }
+    for warning in warnings {
+        writeln!(out, "! {warning}")?;
+    }
+    Ok(())
+}
+
+fn reasons(status: &InnerThrottleStatus) -> Vec<&'static str> {
+    [
+        (status.under_voltage, "under-voltage"),
+        (status.arm_frequency_capped, "ARM frequency capped"),
+        (status.throttled, "throttled"),
+        (status.soft_temp_limit, "soft temperature limit"),
+    ]
+    .into_iter()
+    .filter_map(|(set, reason)| set.then_some(reason))
+    .collect()
+}
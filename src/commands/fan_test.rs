@@ -0,0 +1,141 @@
+//! `pi-stats fan-test` sweeps the fan PWM from 0 to 255, waits for the fan
+//! to settle at each step and reports the measured PWM to RPM response curve.
+//!
+//! The fan is taken over with [`ManualFan`], so the kernel automatic control
+//! is restored when the test ends, fails or is interrupted.
+
+use std::{
+    io::{self, Write},
+    time::Duration,
+};
+
+use tokio::{
+    signal::unix::{signal, SignalKind},
+    time,
+};
+
+use crate::{
+    platform::{FanStatus, Hwmon, ManualFan, PwmValue, RpmValue},
+    Result,
+};
+
+/// Number of RPM readings averaged at each step
+const SAMPLES: u32 = 5;
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(200);
+const BAR_WIDTH: usize = 40;
+
+#[derive(clap::Args)]
+pub struct FanTestArgs {
+    /// PWM step between the measurements
+    #[arg(long, default_value_t = 32, value_parser = clap::value_parser!(u8).range(1..))]
+    step: u8,
+
+    /// Seconds to let the fan settle at each step
+    #[arg(long, default_value_t = 5)]
+    settle: u64,
+
+    /// Do not ask for the confirmation
+    #[arg(short, long)]
+    yes: bool,
+}
+
+pub async fn run(args: FanTestArgs) -> Result<()> {
+    let mut duties = (0..=PwmValue::MAX)
+        .step_by(args.step as usize)
+        .collect::<Vec<_>>();
+    if duties.last() != Some(&PwmValue::MAX) {
+        duties.push(PwmValue::MAX);
+    }
+    let settle = Duration::from_secs(args.settle);
+
+    if !args.yes && !confirm(duties.len() as u32 * (settle + SAMPLE_INTERVAL * SAMPLES))? {
+        println!("Fan test cancelled");
+        return Ok(());
+    }
+
+    let mut interrupt = signal(SignalKind::interrupt())?;
+    let mut terminate = signal(SignalKind::terminate())?;
+    let fan = ManualFan::engage()?;
+    let mut response = Vec::with_capacity(duties.len());
+
+    for duty in duties {
+        fan.set(duty)?;
+        print!("PWM {duty:>3} ... ");
+        io::stdout().flush()?;
+
+        let rpm = tokio::select! {
+            rpm = measure(settle) => rpm?,
+            _ = interrupt.recv() => return interrupted(),
+            _ = terminate.recv() => return interrupted(),
+        };
+        println!("{rpm} RPM");
+        response.push((duty, rpm));
+    }
+    drop(fan);
+
+    report(&response);
+    Ok(())
+}
+
+fn confirm(duration: Duration) -> Result<bool> {
+    println!("The fan is going to be taken over and driven from 0 to 255 PWM.");
+    println!("It stops completely at the low duties, keep the load on the board low.");
+    print!(
+        "The test takes about {}s, continue? [y/N] ",
+        duration.as_secs()
+    );
+    io::stdout().flush()?;
+
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+fn interrupted() -> Result<()> {
+    println!("interrupted, fan control is handed back to the kernel");
+    Ok(())
+}
+
+async fn measure(settle: Duration) -> Result<RpmValue> {
+    time::sleep(settle).await;
+
+    let mut sum = 0;
+    for _ in 0..SAMPLES {
+        sum += FanStatus::new(&Hwmon::scan()?)?.rpm;
+        time::sleep(SAMPLE_INTERVAL).await;
+    }
+    Ok(sum / SAMPLES)
+}
+
+fn report(response: &[(PwmValue, RpmValue)]) {
+    let max_rpm = response.iter().map(|(_, rpm)| *rpm).max().unwrap_or(0);
+
+    println!();
+    println!("Fan response curve:");
+    for (duty, rpm) in response {
+        let bar = match max_rpm {
+            0 => 0,
+            max => *rpm as usize * BAR_WIDTH / max as usize,
+        };
+        println!("{duty:>3} PWM {rpm:>5} RPM |{}", "#".repeat(bar));
+    }
+    println!();
+
+    if max_rpm == 0 {
+        println!("No RPM reported at any duty: the fan is stalled or its tach is disconnected");
+        return;
+    }
+
+    if let Some((duty, _)) = response.iter().find(|(_, rpm)| *rpm > 0) {
+        println!("Spin-up duty: {duty} PWM");
+    }
+    println!("Maximum speed: {max_rpm} RPM");
+
+    // RPM is expected to grow with the duty, small drops are measurement noise
+    for pair in response.windows(2) {
+        let ((d0, r0), (d1, r1)) = (pair[0], pair[1]);
+        if (r1 as f64) < r0 as f64 * 0.95 {
+            println!("Warning: speed dropped from {r0} RPM at {d0} PWM to {r1} RPM at {d1} PWM");
+        }
+    }
+}
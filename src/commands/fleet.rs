@@ -0,0 +1,779 @@
+//! `pi-stats fleet hosts.toml` watches many boards in one table, one row per board
+//! with its temperature, clock, fan, power and throttle flags coloured by severity.
+//!
+//! The boards run `pi-stats serve` and are streamed from, or are sampled over SSH with
+//! `pi-stats snapshot --json` once per `ssh_interval`:
+//!
+//! ```toml
+//! # Seconds between the samples of the boards reached over SSH, 10 by default
+//! ssh_interval = 10
+//!
+//! [[host]]
+//! name = "rack1-01"            # the board hostname by default
+//! url = "rack1-01.local:9180"  # pi-stats serve
+//! interval = 5                 # seconds, the `--interval` of serve, 1 by default
+//!
+//! [[host]]
+//! ssh = "pi@rack1-02.local"    # key based, passwords are not asked for
+//! command = "/usr/local/bin/pi-stats snapshot --json"
+//! ```
+//!
+//! `Enter` opens the full view of the selected board, `q` gets back to the table.
+
+use std::{cmp::Ordering, fs, path::PathBuf, time::Duration};
+
+use crossterm::event::{Event, EventStream, KeyCode, KeyModifiers};
+use ratatui::{
+    layout::{Constraint, Direction, Layout},
+    style::{Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, Cell, Row, Table, TableState},
+    DefaultTerminal, Frame,
+};
+use serde::Deserialize;
+use tokio::{
+    signal::unix::{signal, SignalKind},
+    time,
+};
+use tokio_stream::StreamExt;
+
+use crate::{
+    platform::{ClockMhz, InnerThrottleStatus, PsuVerdict, RpmValue, TempValue, Watt},
+    remote::SSH_COMMAND,
+    App, Error, Remote, Result, Rpi,
+};
+
+/// Temperature from which a board is shown as warning, the firmware soft limit starts at 80 °C
+const TEMP_WARNING: TempValue = 70.0;
+const TEMP_CRITICAL: TempValue = 80.0;
+/// Missed updates after which the values of a board are shown as stale
+const STALE_UPDATES: u32 = 3;
+/// Default interval of `pi-stats serve`
+const SERVE_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(clap::Args)]
+pub struct FleetArgs {
+    /// TOML file listing the boards, see the README for the format
+    hosts: PathBuf,
+
+    /// Milliseconds between the redraws of the table and the board views
+    #[arg(short, long, value_parser = clap::value_parser!(u64).range(1..), default_value_t = 1000)]
+    interval: u64,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct HostsFile {
+    #[serde(default = "default_ssh_interval")]
+    ssh_interval: u64,
+    #[serde(default, rename = "host")]
+    hosts: Vec<HostEntry>,
+}
+
+fn default_ssh_interval() -> u64 {
+    10
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct HostEntry {
+    name: Option<String>,
+    url: Option<String>,
+    ssh: Option<String>,
+    command: Option<String>,
+    /// Seconds between the updates of the board, `ssh_interval` over SSH
+    interval: Option<u64>,
+}
+
+struct Host {
+    name: Option<String>,
+    remote: Remote,
+    /// Expected time between the updates
+    interval: Duration,
+    rpi: Option<Rpi>,
+}
+
+impl Host {
+    fn name(&self) -> String {
+        self.name
+            .clone()
+            .or_else(|| self.rpi.as_ref()?.board.hostname.clone())
+            .unwrap_or_else(|| self.remote.address().to_owned())
+    }
+
+    fn is_stale(&self) -> bool {
+        self.remote
+            .age()
+            .is_some_and(|age| age > self.interval * STALE_UPDATES)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Severity {
+    Ok,
+    Warning,
+    Critical,
+}
+
+impl Severity {
+    fn style(self) -> Style {
+        match self {
+            Severity::Ok => Style::new().green(),
+            Severity::Warning => Style::new().yellow(),
+            Severity::Critical => Style::new().red(),
+        }
+    }
+}
+
+/// Columns the table can be sorted by, switched with `s`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SortBy {
+    Name,
+    Temperature,
+    Clock,
+    Fan,
+    Power,
+    Severity,
+}
+
+impl SortBy {
+    fn next(self) -> Self {
+        match self {
+            SortBy::Name => SortBy::Temperature,
+            SortBy::Temperature => SortBy::Clock,
+            SortBy::Clock => SortBy::Fan,
+            SortBy::Fan => SortBy::Power,
+            SortBy::Power => SortBy::Severity,
+            SortBy::Severity => SortBy::Name,
+        }
+    }
+}
+
+/// Values of one row, `None` where the board has no reading
+struct Summary {
+    index: usize,
+    name: String,
+    model: Option<String>,
+    temp: Option<TempValue>,
+    clock: Option<ClockMhz>,
+    rpm: Option<RpmValue>,
+    power: Option<Watt>,
+    throttle: Option<(InnerThrottleStatus, InnerThrottleStatus)>,
+    fan_issue: bool,
+    psu: Severity,
+    status: (String, Severity),
+}
+
+impl Summary {
+    fn new(index: usize, host: &Host) -> Self {
+        let rpi = host.rpi.as_ref();
+        let status = match (host.remote.error(), rpi) {
+            (Some(error), _) => (error, Severity::Critical),
+            (None, None) => (String::from("connecting"), Severity::Warning),
+            (None, Some(_)) if host.is_stale() => (String::from("stale"), Severity::Warning),
+            (None, Some(_)) => (String::from("ok"), Severity::Ok),
+        };
+        Self {
+            index,
+            name: host.name(),
+            model: rpi.and_then(|rpi| rpi.board.model.clone()),
+            temp: rpi.and_then(|rpi| rpi.cpu.temp.value().map(|temp| **temp)),
+            clock: rpi.and_then(|rpi| rpi.cpu.clock.value().map(|clock| *clock.arm)),
+            rpm: rpi.and_then(|rpi| rpi.fan.value().map(|fan| fan.rpm)),
+            power: rpi.and_then(|rpi| rpi.power.power.value().map(|power| power.total_power)),
+            throttle: rpi.and_then(|rpi| {
+                let throttle = rpi.power.throttle.value()?;
+                Some((throttle.current, throttle.happened))
+            }),
+            fan_issue: rpi.is_some_and(|rpi| rpi.fan_health.issue.is_some()),
+            psu: match rpi.map(|rpi| &rpi.psu.verdict) {
+                Some(PsuVerdict::Inadequate(_)) => Severity::Critical,
+                Some(PsuVerdict::Marginal(_)) => Severity::Warning,
+                _ => Severity::Ok,
+            },
+            status,
+        }
+    }
+
+    fn temp_severity(&self) -> Severity {
+        match self.temp {
+            Some(temp) if temp >= TEMP_CRITICAL => Severity::Critical,
+            Some(temp) if temp >= TEMP_WARNING => Severity::Warning,
+            _ => Severity::Ok,
+        }
+    }
+
+    fn throttle_severity(&self) -> Severity {
+        let any = |status: &InnerThrottleStatus| {
+            status.under_voltage
+                || status.arm_frequency_capped
+                || status.throttled
+                || status.soft_temp_limit
+        };
+        match &self.throttle {
+            Some((current, _)) if any(current) => Severity::Critical,
+            Some((_, happened)) if any(happened) => Severity::Warning,
+            _ => Severity::Ok,
+        }
+    }
+
+    fn fan_severity(&self) -> Severity {
+        match self.fan_issue {
+            true => Severity::Critical,
+            false => Severity::Ok,
+        }
+    }
+
+    /// Worst of the columns, the colour of the name
+    fn severity(&self) -> Severity {
+        [
+            self.temp_severity(),
+            self.throttle_severity(),
+            self.fan_severity(),
+            self.psu,
+            self.status.1,
+        ]
+        .into_iter()
+        .max()
+        .unwrap_or(Severity::Ok)
+    }
+
+    fn matches(&self, filter: &str) -> bool {
+        let filter = filter.to_lowercase();
+        [Some(&self.name), self.model.as_ref()]
+            .into_iter()
+            .flatten()
+            .any(|text| text.to_lowercase().contains(&filter))
+    }
+}
+
+struct Fleet {
+    hosts: Vec<Host>,
+    interval: Duration,
+    quit: bool,
+    /// Host to open the full view of
+    open: Option<usize>,
+    sort_by: SortBy,
+    descending: bool,
+    filter: String,
+    editing_filter: bool,
+    table: TableState,
+    /// Host of every shown row, in the order shown
+    rows: Vec<usize>,
+}
+
+pub async fn run(args: FleetArgs) -> Result<()> {
+    let hosts = read_hosts(&args.hosts)?;
+    let mut fleet = Fleet {
+        hosts,
+        interval: Duration::from_millis(args.interval),
+        quit: false,
+        open: None,
+        sort_by: SortBy::Name,
+        descending: false,
+        filter: String::new(),
+        editing_filter: false,
+        table: TableState::default().with_selected(0),
+        rows: Vec::new(),
+    };
+
+    let mut terminal = ratatui::init();
+    let result = fleet.run(&mut terminal).await;
+    ratatui::restore();
+    result
+}
+
+fn read_hosts(path: &PathBuf) -> Result<Vec<Host>> {
+    let invalid =
+        |e: &dyn std::fmt::Display| Error::InvalidInput(format!("{}: {e}", path.display()));
+    let file: HostsFile = toml::from_str(&fs::read_to_string(path)?).map_err(|e| invalid(&e))?;
+    if file.hosts.is_empty() {
+        return Err(invalid(&"no [[host]] entries"));
+    }
+    let ssh_interval = Duration::from_secs(file.ssh_interval.max(1));
+
+    file.hosts
+        .into_iter()
+        .map(|entry| {
+            let interval = |default: Duration| {
+                entry
+                    .interval
+                    .map_or(default, |secs| Duration::from_secs(secs.max(1)))
+            };
+            let (remote, interval) = match (&entry.url, &entry.ssh) {
+                (Some(url), None) => (Remote::connect(url)?, interval(SERVE_INTERVAL)),
+                (None, Some(destination)) => {
+                    let command = entry.command.as_deref().unwrap_or(SSH_COMMAND);
+                    let interval = interval(ssh_interval);
+                    (Remote::ssh(destination, command, interval), interval)
+                }
+                _ => return Err(invalid(&"every host needs either url or ssh")),
+            };
+            Ok(Host {
+                name: entry.name,
+                remote,
+                interval,
+                rpi: None,
+            })
+        })
+        .collect()
+}
+
+impl Fleet {
+    async fn run(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        let mut terminate = signal(SignalKind::terminate())?;
+        let mut hangup = signal(SignalKind::hangup())?;
+        let mut tick = time::interval(self.interval);
+
+        while !self.quit {
+            // The board view reads the terminal events itself
+            let mut events = EventStream::new();
+            while !self.quit && self.open.is_none() {
+                tokio::select! {
+                    Some(Ok(event)) = events.next() => {
+                        self.handle_event(&event);
+                        terminal.draw(|frame| self.render(frame))?;
+                    },
+                    _ = terminate.recv() => self.quit = true,
+                    _ = hangup.recv() => self.quit = true,
+                    _ = tick.tick() => {
+                        self.update();
+                        terminal.draw(|frame| self.render(frame))?;
+                    },
+                }
+            }
+            drop(events);
+
+            if let Some(index) = self.open.take() {
+                let host = &mut self.hosts[index];
+                App::new()
+                    .with_tick_duration(self.interval)
+                    .with_remote(host.remote.clone())
+                    .run(terminal)
+                    .await?;
+                host.rpi = host.remote.latest().or(host.rpi.take());
+                terminal.clear()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn update(&mut self) {
+        for host in &mut self.hosts {
+            if let Some(rpi) = host.remote.take() {
+                host.rpi = Some(rpi);
+            }
+        }
+    }
+
+    fn handle_event(&mut self, event: &Event) {
+        let Some(key) = event.as_key_press_event() else {
+            return;
+        };
+        if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+            self.quit = true;
+            return;
+        }
+
+        if self.editing_filter {
+            match key.code {
+                KeyCode::Enter => self.editing_filter = false,
+                KeyCode::Esc => {
+                    self.editing_filter = false;
+                    self.filter.clear();
+                }
+                KeyCode::Backspace => {
+                    self.filter.pop();
+                }
+                KeyCode::Char(c) => self.filter.push(c),
+                _ => {}
+            }
+            self.table.select(Some(0));
+            return;
+        }
+
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => self.quit = true,
+            KeyCode::Down | KeyCode::Char('j') => self.table.select_next(),
+            KeyCode::Up | KeyCode::Char('k') => self.table.select_previous(),
+            KeyCode::Enter => {
+                self.open = self
+                    .table
+                    .selected()
+                    .and_then(|row| self.rows.get(row))
+                    .copied()
+            }
+            KeyCode::Char('s') => self.sort_by = self.sort_by.next(),
+            KeyCode::Char('r') => self.descending = !self.descending,
+            KeyCode::Char('/') => self.editing_filter = true,
+            _ => {}
+        }
+    }
+
+    /// Rows after the filter, in the sort order
+    fn summaries(&self) -> Vec<Summary> {
+        let mut summaries = self
+            .hosts
+            .iter()
+            .enumerate()
+            .map(|(index, host)| Summary::new(index, host))
+            .filter(|summary| summary.matches(&self.filter))
+            .collect::<Vec<_>>();
+        sort(&mut summaries, self.sort_by, self.descending);
+        summaries
+    }
+}
+
+/// Sorts the rows by the column, then by name. Missing readings sort last in both orders.
+fn sort(summaries: &mut [Summary], sort_by: SortBy, descending: bool) {
+    let order = |ordering: Ordering| match descending {
+        true => ordering.reverse(),
+        false => ordering,
+    };
+    let by = |a: Option<f32>, b: Option<f32>| match (a, b) {
+        (Some(a), Some(b)) => order(a.total_cmp(&b)),
+        (a, b) => b.is_some().cmp(&a.is_some()),
+    };
+    summaries.sort_by(|a, b| {
+        match sort_by {
+            SortBy::Name => order(a.name.cmp(&b.name)),
+            SortBy::Temperature => by(a.temp, b.temp),
+            SortBy::Clock => by(a.clock, b.clock),
+            SortBy::Fan => by(a.rpm.map(|rpm| rpm as f32), b.rpm.map(|rpm| rpm as f32)),
+            SortBy::Power => by(a.power, b.power),
+            SortBy::Severity => order(a.severity().cmp(&b.severity())),
+        }
+        .then_with(|| a.name.cmp(&b.name))
+    });
+}
+
+/// Rendering implementations ONLY
+impl Fleet {
+    fn render(&mut self, frame: &mut Frame) {
+        let [header_block, table_block, help_block] = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(vec![
+                Constraint::Length(1),
+                Constraint::Min(0),
+                Constraint::Length(1),
+            ])
+            .areas(frame.area());
+
+        let summaries = self.summaries();
+        self.rows = summaries.iter().map(|summary| summary.index).collect();
+        if self
+            .table
+            .selected()
+            .is_some_and(|row| row >= self.rows.len())
+        {
+            self.table.select(Some(self.rows.len().saturating_sub(1)));
+        }
+
+        self.render_header_line(frame, header_block, &summaries);
+        self.render_table(frame, table_block, &summaries);
+        frame.render_widget(
+            Line::from(
+                "↑↓ select | Enter open | s sort | r reverse | / filter | q quit".dark_gray(),
+            ),
+            help_block,
+        );
+    }
+
+    fn render_header_line(&self, frame: &mut Frame, area: ratatui::layout::Rect, rows: &[Summary]) {
+        let count = |severity| {
+            rows.iter()
+                .filter(|summary| summary.severity() == severity)
+                .count()
+        };
+        let mut spans = vec![
+            Span::styled("pi-stats fleet", Style::new().bold().cyan()),
+            Span::raw(format!(
+                " | {} of {} boards | ",
+                rows.len(),
+                self.hosts.len()
+            )),
+            Span::styled(format!("{} ok", count(Severity::Ok)), Severity::Ok.style()),
+            Span::raw(" "),
+            Span::styled(
+                format!("{} warning", count(Severity::Warning)),
+                Severity::Warning.style(),
+            ),
+            Span::raw(" "),
+            Span::styled(
+                format!("{} critical", count(Severity::Critical)),
+                Severity::Critical.style(),
+            ),
+        ];
+        if self.editing_filter || !self.filter.is_empty() {
+            let cursor = if self.editing_filter { "_" } else { "" };
+            spans.push(Span::raw(format!(" | filter: {}{cursor}", self.filter)));
+        }
+        frame.render_widget(Line::from(spans), area);
+    }
+
+    fn render_table(&mut self, frame: &mut Frame, area: ratatui::layout::Rect, rows: &[Summary]) {
+        let arrow = if self.descending { " ▼" } else { " ▲" };
+        let header = [
+            ("BOARD", Some(SortBy::Name)),
+            ("MODEL", None),
+            ("TEMP °C", Some(SortBy::Temperature)),
+            ("ARM MHz", Some(SortBy::Clock)),
+            ("FAN RPM", Some(SortBy::Fan)),
+            ("POWER W", Some(SortBy::Power)),
+            ("THROTTLE", None),
+            ("STATUS", Some(SortBy::Severity)),
+        ]
+        .into_iter()
+        .map(|(title, sort_by)| match sort_by == Some(self.sort_by) {
+            true => Cell::from(format!("{title}{arrow}")),
+            false => Cell::from(title),
+        });
+
+        let na = || Cell::from("n/a".dark_gray());
+        let number = |value: Option<f32>, precision: usize, severity: Severity| {
+            value.map_or_else(na, |value| {
+                Cell::from(Span::styled(
+                    format!("{value:.precision$}"),
+                    severity.style(),
+                ))
+            })
+        };
+        let table_rows = rows.iter().map(|summary| {
+            Row::new(vec![
+                Cell::from(Span::styled(
+                    summary.name.clone(),
+                    summary.severity().style().bold(),
+                )),
+                summary.model.clone().map_or_else(na, Cell::from),
+                number(summary.temp, 1, summary.temp_severity()),
+                number(summary.clock, 0, Severity::Ok),
+                number(summary.rpm.map(|rpm| rpm as f32), 0, summary.fan_severity()),
+                number(summary.power, 2, summary.psu),
+                throttle_cell(summary.throttle.as_ref()),
+                Cell::from(Span::styled(
+                    summary.status.0.clone(),
+                    summary.status.1.style(),
+                )),
+            ])
+        });
+
+        let table = Table::new(
+            table_rows,
+            [
+                Constraint::Length(18),
+                Constraint::Length(32),
+                Constraint::Length(9),
+                Constraint::Length(9),
+                Constraint::Length(9),
+                Constraint::Length(9),
+                Constraint::Length(10),
+                Constraint::Min(10),
+            ],
+        )
+        .header(Row::new(header).bold())
+        .row_highlight_style(Style::new().reversed())
+        .block(Block::bordered());
+
+        frame.render_stateful_widget(table, area, &mut self.table);
+    }
+}
+
+/// `UFTS`, under-voltage, frequency capped, throttled and soft temperature limit,
+/// red while active, yellow when it has happened since boot
+fn throttle_cell(throttle: Option<&(InnerThrottleStatus, InnerThrottleStatus)>) -> Cell<'static> {
+    let Some((current, happened)) = throttle else {
+        return Cell::from("n/a".dark_gray());
+    };
+    let flags = [
+        ('U', current.under_voltage, happened.under_voltage),
+        (
+            'F',
+            current.arm_frequency_capped,
+            happened.arm_frequency_capped,
+        ),
+        ('T', current.throttled, happened.throttled),
+        ('S', current.soft_temp_limit, happened.soft_temp_limit),
+    ];
+    let spans = flags
+        .into_iter()
+        .map(|(letter, current, happened)| match (current, happened) {
+            (true, _) => Span::styled(letter.to_string(), Severity::Critical.style()),
+            (false, true) => Span::styled(letter.to_string(), Severity::Warning.style()),
+            (false, false) => Span::styled("-", Style::new().dark_gray()),
+        })
+        .collect::<Vec<_>>();
+    Cell::from(Line::from(spans))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::fixture;
+
+    fn hosts(name: &str, toml: &str) -> Result<Vec<Host>> {
+        let root = fixture(name, &[("hosts.toml", toml)]);
+        let hosts = read_hosts(&root.join("hosts.toml"));
+        fs::remove_dir_all(&root).unwrap();
+        hosts
+    }
+
+    fn summary(name: &str) -> Summary {
+        Summary {
+            index: 0,
+            name: name.to_owned(),
+            model: Some(String::from("Raspberry Pi 5 Model B Rev 1.0")),
+            temp: None,
+            clock: None,
+            rpm: None,
+            power: None,
+            throttle: None,
+            fan_issue: false,
+            psu: Severity::Ok,
+            status: (String::from("ok"), Severity::Ok),
+        }
+    }
+
+    #[tokio::test]
+    async fn reads_hosts() {
+        let hosts = hosts(
+            "fleet-hosts",
+            r#"
+            ssh_interval = 30
+
+            [[host]]
+            name = "rack1-01"
+            url = "127.0.0.1:9"
+
+            [[host]]
+            url = "http://127.0.0.1:9"
+            interval = 5
+
+            [[host]]
+            ssh = "pi@127.0.0.1"
+            command = "true"
+            "#,
+        )
+        .unwrap();
+        let intervals = hosts.iter().map(|host| host.interval).collect::<Vec<_>>();
+        assert_eq!(
+            intervals,
+            [
+                SERVE_INTERVAL,
+                Duration::from_secs(5),
+                Duration::from_secs(30)
+            ]
+        );
+        assert_eq!(hosts[0].name(), "rack1-01");
+        assert_eq!(hosts[1].name(), hosts[1].remote.address());
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_hosts() {
+        for (name, toml, error) in [
+            ("fleet-empty", "ssh_interval = 10\n", "no [[host]] entries"),
+            (
+                "fleet-neither",
+                "[[host]]\nname = \"pi\"\n",
+                "either url or ssh",
+            ),
+            (
+                "fleet-both",
+                "[[host]]\nurl = \"127.0.0.1:9\"\nssh = \"pi@127.0.0.1\"\n",
+                "either url or ssh",
+            ),
+            (
+                "fleet-unknown",
+                "[[host]]\nurl = \"127.0.0.1:9\"\nport = 9\n",
+                "port",
+            ),
+        ] {
+            let Err(e) = hosts(name, toml) else {
+                panic!("{toml:?} accepted");
+            };
+            let e = e.to_string();
+            assert!(
+                e.contains("hosts.toml") && e.contains(error),
+                "{toml:?}: {e}"
+            );
+        }
+    }
+
+    #[test]
+    fn severity_thresholds() {
+        let temp = |temp| {
+            let mut summary = summary("pi");
+            summary.temp = Some(temp);
+            summary.temp_severity()
+        };
+        assert_eq!(temp(69.9), Severity::Ok);
+        assert_eq!(temp(TEMP_WARNING), Severity::Warning);
+        assert_eq!(temp(TEMP_CRITICAL), Severity::Critical);
+
+        let flagged = InnerThrottleStatus {
+            under_voltage: true,
+            ..Default::default()
+        };
+        let throttle = |current, happened| {
+            let mut summary = summary("pi");
+            summary.throttle = Some((current, happened));
+            summary.throttle_severity()
+        };
+        assert_eq!(
+            throttle(Default::default(), Default::default()),
+            Severity::Ok
+        );
+        assert_eq!(throttle(Default::default(), flagged), Severity::Warning);
+        assert_eq!(throttle(flagged, flagged), Severity::Critical);
+
+        let mut summary = summary("pi");
+        assert_eq!(summary.severity(), Severity::Ok);
+        summary.psu = Severity::Warning;
+        assert_eq!(summary.severity(), Severity::Warning);
+        summary.fan_issue = true;
+        assert_eq!(summary.fan_severity(), Severity::Critical);
+        assert_eq!(summary.severity(), Severity::Critical);
+    }
+
+    #[test]
+    fn sorts_missing_readings_last() {
+        let mut summaries = [
+            ("a", Some(60.0)),
+            ("b", None),
+            ("c", Some(75.0)),
+            ("d", Some(60.0)),
+        ]
+        .map(|(name, temp)| {
+            let mut summary = summary(name);
+            summary.temp = temp;
+            summary
+        });
+        let names = |summaries: &[Summary]| {
+            summaries
+                .iter()
+                .map(|summary| summary.name.as_str())
+                .collect::<Vec<_>>()
+                .join("")
+        };
+
+        sort(&mut summaries, SortBy::Temperature, false);
+        assert_eq!(names(&summaries), "adcb");
+        sort(&mut summaries, SortBy::Temperature, true);
+        assert_eq!(names(&summaries), "cadb");
+        sort(&mut summaries, SortBy::Name, true);
+        assert_eq!(names(&summaries), "dcba");
+
+        // c is warm, d stale
+        summaries[0].status = (String::from("stale"), Severity::Warning);
+        sort(&mut summaries, SortBy::Severity, true);
+        assert_eq!(names(&summaries), "cdab");
+    }
+
+    #[test]
+    fn filters_by_name_and_model() {
+        let summary = summary("Rack1-01");
+        assert!(summary.matches("rack1"));
+        assert!(summary.matches("PI 5"));
+        assert!(summary.matches(""));
+        assert!(!summary.matches("rack2"));
+    }
+}
@@ -0,0 +1,39 @@
+mod bench;
+mod config;
+mod fan_test;
+mod fleet;
+mod serve;
+mod snapshot;
+
+use clap::Subcommand;
+
+use crate::Result;
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Load the CPU and report temperature, power, fan and clock behaviour
+    Bench(bench::BenchArgs),
+    /// Show the effective overclocking settings and compare them with the live clocks
+    Config(config::ConfigArgs),
+    /// Sweep the fan PWM and report the fan response curve
+    FanTest(fan_test::FanTestArgs),
+    /// Watch many boards in one table and open the full view of any of them
+    Fleet(fleet::FleetArgs),
+    /// Serve the board state, its history and a live stream as a JSON API
+    Serve(serve::ServeArgs),
+    /// Print a one-shot report of the board state
+    Snapshot(snapshot::SnapshotArgs),
+}
+
+impl Command {
+    pub async fn run(self) -> Result<()> {
+        match self {
+            Command::Bench(args) => bench::run(args).await,
+            Command::Config(args) => config::run(args).await,
+            Command::FanTest(args) => fan_test::run(args).await,
+            Command::Fleet(args) => fleet::run(args).await,
+            Command::Serve(args) => serve::run(args).await,
+            Command::Snapshot(args) => snapshot::run(args).await,
+        }
+    }
+}
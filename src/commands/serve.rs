@@ -0,0 +1,92 @@
+//! `pi-stats serve` samples the board and serves its state over HTTP,
+//! see the [server](crate::server) for the endpoints.
+
+use std::{net::SocketAddr, path::PathBuf, time::Duration};
+
+use tokio::{
+    net::TcpListener,
+    signal::unix::{signal, SignalKind},
+    time,
+};
+
+use crate::{
+    server::{self, ServerState},
+    systemd::{ErrorLog, Service},
+    EnergyMeter, EnergyState, Result, Rpi,
+};
+
+#[derive(clap::Args)]
+pub struct ServeArgs {
+    /// Address to listen on, e.g. `0.0.0.0:9180` to be reachable from other hosts
+    #[arg(short, long, default_value = "127.0.0.1:9180")]
+    listen: SocketAddr,
+
+    /// Update interval in milliseconds
+    #[arg(short, long, value_parser = clap::value_parser!(u64).range(1..), default_value_t = 1000)]
+    interval: u64,
+
+    /// Seconds of history kept in memory
+    #[arg(long, value_name = "SECONDS", default_value_t = 3600)]
+    history: u64,
+
+    /// File the energy counters are kept in across restarts,
+    /// `$XDG_STATE_HOME/pi-stats/energy` or `~/.local/state/pi-stats/energy` by default
+    #[arg(long, value_name = "PATH")]
+    energy_state: Option<PathBuf>,
+}
+
+pub async fn run(args: ServeArgs) -> Result<()> {
+    let interval = Duration::from_millis(args.interval);
+    let mut rpi = Rpi::new();
+    let mut energy = args
+        .energy_state
+        .or_else(EnergyMeter::default_path)
+        .map(|path| {
+            let (energy, meter) = EnergyState::load(path);
+            rpi.energy = meter;
+            energy
+        });
+    rpi.energy.set_interval(interval);
+    let state = ServerState::new(Duration::from_secs(args.history));
+    rpi.update();
+    state.update(&rpi)?;
+
+    let listener = TcpListener::bind(args.listen).await?;
+    tracing::info!("Serving on http://{}", listener.local_addr()?);
+    // The live streams never end, so the server is stopped with the sampling loop
+    // instead of waiting for a graceful shutdown
+    let server = tokio::spawn(axum::serve(listener, server::router(state.clone())).into_future());
+
+    let mut service = Service::new(interval);
+    let mut errors = ErrorLog::default();
+    let mut tick = time::interval(interval);
+    let mut interrupt = signal(SignalKind::interrupt())?;
+    let mut terminate = signal(SignalKind::terminate())?;
+    loop {
+        tokio::select! {
+            _ = interrupt.recv() => break,
+            _ = terminate.recv() => break,
+            _ = tick.tick() => {
+                rpi.update();
+                state.update(&rpi)?;
+                if let Some(energy) = energy.as_mut() {
+                    energy.update(&rpi.energy);
+                }
+                errors.report(&rpi, energy_error(energy.as_ref()));
+                service.sampled();
+            }
+        }
+    }
+    service.stopping();
+    server.abort();
+    if let Some(energy) = energy.as_mut() {
+        energy.save(&rpi.energy);
+    }
+    errors.report(&rpi, energy_error(energy.as_ref()));
+    Ok(())
+}
+
+fn energy_error(energy: Option<&EnergyState>) -> Option<(String, String)> {
+    let error = energy?.error()?;
+    Some((String::from("energy state"), error.to_owned()))
+}
@@ -0,0 +1,436 @@
+//! `pi-stats snapshot` prints a one-shot report of the board state.
+//!
+//! The board is sampled twice, `--interval` apart, since the CPU utilisation
+//! and the process shares are measured between two updates.
+//! With `--json` the state is printed like the snapshot of `pi-stats serve`,
+//! e.g. for `pi-stats fleet` sampling a board over SSH.
+
+use std::{
+    io::{self, Write},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use tokio::time;
+
+use crate::{
+    platform::{Sensor, SensorState},
+    server::Snapshot,
+    Result, Rpi,
+};
+
+#[derive(clap::Args)]
+pub struct SnapshotArgs {
+    /// Milliseconds between the two samples the utilisation is measured over
+    #[arg(short, long, default_value_t = 1000)]
+    interval: u64,
+
+    /// Number of the top processes to list, at most 32
+    #[arg(long, default_value_t = 10)]
+    top: usize,
+
+    /// Print the state as JSON, the same as `GET /api/v1/snapshot` of `pi-stats serve`
+    #[arg(long)]
+    json: bool,
+}
+
+pub async fn run(args: SnapshotArgs) -> Result<()> {
+    let mut rpi = Rpi::new();
+    rpi.update();
+    time::sleep(Duration::from_millis(args.interval)).await;
+    rpi.update();
+
+    let mut out = io::stdout().lock();
+    if args.json {
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs_f64();
+        serde_json::to_writer(&mut out, &Snapshot { time, rpi: &rpi })?;
+        writeln!(out)?;
+        return Ok(());
+    }
+    write_board(&mut out, &rpi)?;
+    write_cpu(&mut out, &rpi)?;
+    write_soc(&mut out, &rpi)?;
+    write_fan(&mut out, &rpi)?;
+    write_power(&mut out, &rpi)?;
+    write_thermal(&mut out, &rpi)?;
+    write_hwmon(&mut out, &rpi)?;
+    write_system(&mut out, &rpi)?;
+    write_storage(&mut out, &rpi)?;
+    write_network(&mut out, &rpi)?;
+    write_processes(&mut out, &rpi, args.top)?;
+    Ok(())
+}
+
+fn write_board(out: &mut impl Write, rpi: &Rpi) -> Result<()> {
+    let board = &rpi.board;
+    let value = |value: &Option<String>| value.clone().unwrap_or_else(|| String::from("n/a"));
+    writeln!(out, "== Board ==")?;
+    writeln!(out, "Model       : {}", value(&board.model))?;
+    writeln!(out, "Serial      : {}", value(&board.serial))?;
+    writeln!(out, "Hostname    : {}", value(&board.hostname))?;
+    writeln!(out, "Firmware    : {}", value(&board.firmware))?;
+    writeln!(out, "Kernel      : {}", value(&board.kernel))?;
+    for (name, setting) in &board.config {
+        writeln!(out, "{name:<12}: {setting}")?;
+    }
+    if let Some(uptime) = board.uptime() {
+        writeln!(out, "Uptime      : {}s", uptime.as_secs())?;
+    }
+    writeln!(out)?;
+    Ok(())
+}
+
+fn write_cpu(out: &mut impl Write, rpi: &Rpi) -> Result<()> {
+    let cpu = &rpi.cpu;
+    writeln!(out, "== CPU ==")?;
+    writeln!(
+        out,
+        "Temperature : {}",
+        text(&cpu.temp, |temp| format!("{:.1} °C", **temp))
+    )?;
+    writeln!(
+        out,
+        "ARM clock   : {}",
+        text(&cpu.clock, |clock| format!("{:.2} MHz", *clock.arm))
+    )?;
+    writeln!(
+        out,
+        "GPU clock   : {}",
+        text(&cpu.clock, |clock| format!("{:.2} MHz", *clock.gpu))
+    )?;
+    writeln!(out)?;
+    Ok(())
+}
+
+fn write_soc(out: &mut impl Write, rpi: &Rpi) -> Result<()> {
+    let soc = &rpi.soc;
+    writeln!(out, "== SoC ==")?;
+    match soc.volts.value() {
+        Some(volts) => {
+            for (rail, value) in volts.rails() {
+                writeln!(out, "{:<12}: {value:.4} V", format!("{rail} volts"))?;
+            }
+        }
+        None => writeln!(out, "Volts       : {}", text(&soc.volts, |_| String::new()))?,
+    }
+    writeln!(
+        out,
+        "Memory      : {}",
+        text(&soc.memory, |memory| format!(
+            "arm {} MiB, gpu {} MiB",
+            memory.arm, memory.gpu
+        ))
+    )?;
+    writeln!(
+        out,
+        "Ring osc    : {}",
+        text(&soc.ring_osc, |osc| format!(
+            "{:.3} MHz at {:.4} V, {:.1} °C",
+            osc.frequency, osc.volts, osc.temp
+        ))
+    )?;
+    match soc.clocks.value() {
+        Some(clocks) => {
+            for (clock, value) in &clocks.clocks {
+                writeln!(out, "{:<12}: {:.2} MHz", clock.as_str(), **value)?;
+            }
+        }
+        None => writeln!(
+            out,
+            "Clocks      : {}",
+            text(&soc.clocks, |_| String::new())
+        )?,
+    }
+    writeln!(out)?;
+    Ok(())
+}
+
+fn write_fan(out: &mut impl Write, rpi: &Rpi) -> Result<()> {
+    writeln!(out, "== Fan ==")?;
+    writeln!(
+        out,
+        "PWM         : {}",
+        text(&rpi.fan, |fan| fan.pwm.to_string())
+    )?;
+    writeln!(
+        out,
+        "RPM         : {}",
+        text(&rpi.fan, |fan| fan.rpm.to_string())
+    )?;
+    writeln!(out)?;
+    Ok(())
+}
+
+fn write_power(out: &mut impl Write, rpi: &Rpi) -> Result<()> {
+    let power = &rpi.power;
+    writeln!(out, "== Power ==")?;
+    writeln!(
+        out,
+        "Total power : {}",
+        text(&power.power, |power| format!("{:.3} W", power.total_power))
+    )?;
+    if let Some(power) = power.power.current() {
+        for rail in &power.power_map {
+            writeln!(
+                out,
+                "  {:<14} {:>7.4} V {:>7.4} A {:>7.4} W",
+                rail.measure,
+                rail.volts,
+                rail.amps,
+                rail.volts * rail.amps
+            )?;
+        }
+    }
+    writeln!(
+        out,
+        "Throttled   : {}",
+        text(&power.throttle, |throttle| {
+            let current = &throttle.current;
+            format!(
+                "under voltage {}, arm frequency capped {}, throttled {}, soft temp limit {}",
+                current.under_voltage,
+                current.arm_frequency_capped,
+                current.throttled,
+                current.soft_temp_limit
+            )
+        })
+    )?;
+    writeln!(
+        out,
+        "Has happened: {}",
+        text(&power.throttle, |throttle| {
+            let happened = &throttle.happened;
+            format!(
+                "under voltage {}, arm frequency capped {}, throttled {}, soft temp limit {}",
+                happened.under_voltage,
+                happened.arm_frequency_capped,
+                happened.throttled,
+                happened.soft_temp_limit
+            )
+        })
+    )?;
+    writeln!(out, "PSU         : {}", rpi.psu.verdict)?;
+    if let Some(volts) = rpi.psu.volts {
+        writeln!(out, "  input      {volts:>7.4} V")?;
+    }
+    if let Some(amps) = rpi.psu.amps {
+        writeln!(
+            out,
+            "  current    {amps:>7.4} A of the {} A profile (estimated)",
+            rpi.psu.profile
+        )?;
+    }
+    writeln!(out)?;
+    Ok(())
+}
+
+fn write_thermal(out: &mut impl Write, rpi: &Rpi) -> Result<()> {
+    writeln!(out, "== Thermal ==")?;
+    let Some(thermal) = rpi.thermal.current() else {
+        writeln!(out, "{}", text(&rpi.thermal, |_| String::new()))?;
+        writeln!(out)?;
+        return Ok(());
+    };
+
+    for zone in &thermal.zones {
+        let temp = zone
+            .temp
+            .map_or(String::from("n/a"), |temp| format!("{temp:.1} °C"));
+        writeln!(out, "{:<12}: {temp}", zone.kind)?;
+        for trip in &zone.trips {
+            let active = zone.active_trips().any(|active| active.index == trip.index);
+            writeln!(
+                out,
+                "  trip {} {:<8} {:>6.1} °C{}",
+                trip.index,
+                trip.kind,
+                trip.temp,
+                if active { " (reached)" } else { "" }
+            )?;
+        }
+    }
+    for device in &thermal.cooling_devices {
+        writeln!(
+            out,
+            "{:<12}: state {}/{}",
+            device.kind, device.cur_state, device.max_state
+        )?;
+    }
+    writeln!(out)?;
+    Ok(())
+}
+
+fn write_hwmon(out: &mut impl Write, rpi: &Rpi) -> Result<()> {
+    writeln!(out, "== Hardware sensors ==")?;
+    let Some(hwmon) = rpi.hwmon.current() else {
+        writeln!(out, "{}", text(&rpi.hwmon, |_| String::new()))?;
+        writeln!(out)?;
+        return Ok(());
+    };
+
+    for device in &hwmon.devices {
+        writeln!(out, "{}", device.name)?;
+        for channel in &device.channels {
+            writeln!(
+                out,
+                "  {:<14} {} {}",
+                channel.name(),
+                channel.value,
+                channel.kind.unit()
+            )?;
+        }
+    }
+    writeln!(out)?;
+    Ok(())
+}
+
+fn write_system(out: &mut impl Write, rpi: &Rpi) -> Result<()> {
+    let system = &rpi.system;
+    writeln!(out, "== System ==")?;
+    writeln!(
+        out,
+        "CPU load    : {}",
+        text(&system.usage, |usage| {
+            let cores = usage
+                .cores
+                .iter()
+                .map(|core| format!("{core:.1}%"))
+                .collect::<Vec<_>>()
+                .join(" ");
+            format!("{:.1}% ({cores})", usage.total)
+        })
+    )?;
+    writeln!(
+        out,
+        "Load average: {}",
+        text(&system.load, |load| format!(
+            "{:.2} {:.2} {:.2}",
+            load.one, load.five, load.fifteen
+        ))
+    )?;
+    writeln!(
+        out,
+        "Memory      : {}",
+        text(&system.memory, |memory| format!(
+            "{} / {} KiB used, swap {} / {} KiB",
+            memory.used(),
+            memory.total,
+            memory.swap_used(),
+            memory.swap_total
+        ))
+    )?;
+    writeln!(
+        out,
+        "Pressure    : {}",
+        text(&system.pressure, |pressure| format!(
+            "some avg10 cpu {:.2}%, memory {:.2}%, io {:.2}%",
+            pressure.cpu.some.avg10, pressure.memory.some.avg10, pressure.io.some.avg10
+        ))
+    )?;
+    writeln!(out)?;
+    Ok(())
+}
+
+fn write_storage(out: &mut impl Write, rpi: &Rpi) -> Result<()> {
+    let storage = &rpi.storage;
+    writeln!(out, "== Storage ==")?;
+    match storage.io.current() {
+        Some(io) => {
+            for disk in io {
+                writeln!(
+                    out,
+                    "{:<12}: read {:.0} B/s, write {:.0} B/s",
+                    disk.name, disk.read, disk.write
+                )?;
+            }
+        }
+        None => writeln!(
+            out,
+            "Disk I/O    : {}",
+            text(&storage.io, |_| String::new())
+        )?,
+    }
+    match storage.nvme.current() {
+        Some(drives) => {
+            for drive in drives {
+                for (label, temp) in &drive.temps {
+                    writeln!(out, "{:<12}: {label} {temp:.1} °C", drive.name)?;
+                }
+            }
+        }
+        None => writeln!(
+            out,
+            "NVMe        : {}",
+            text(&storage.nvme, |_| String::new())
+        )?,
+    }
+    writeln!(out)?;
+    Ok(())
+}
+
+fn write_network(out: &mut impl Write, rpi: &Rpi) -> Result<()> {
+    let interfaces = &rpi.network.interfaces;
+    writeln!(out, "== Network ==")?;
+    let Some(interfaces) = interfaces.current() else {
+        writeln!(out, "{}", text(interfaces, |_| String::new()))?;
+        writeln!(out)?;
+        return Ok(());
+    };
+
+    for interface in interfaces {
+        writeln!(
+            out,
+            "{:<12}: rx {:.0} B/s, tx {:.0} B/s, errors {}/{}, dropped {}/{}",
+            interface.name,
+            interface.rx,
+            interface.tx,
+            interface.rx_errors,
+            interface.tx_errors,
+            interface.rx_dropped,
+            interface.tx_dropped
+        )?;
+    }
+    writeln!(out)?;
+    Ok(())
+}
+
+fn write_processes(out: &mut impl Write, rpi: &Rpi, top: usize) -> Result<()> {
+    writeln!(out, "== Top processes ==")?;
+    let Some(processes) = rpi.processes.processes.current() else {
+        writeln!(out, "{}", text(&rpi.processes.processes, |_| String::new()))?;
+        return Ok(());
+    };
+
+    let total_power = rpi
+        .power
+        .power
+        .current()
+        .map_or(0.0, |power| power.total_power);
+    writeln!(
+        out,
+        "{:>7} {:<16} {:>6} {:>8}",
+        "PID", "NAME", "CPU%", "POWER(W)"
+    )?;
+    for process in processes.top.iter().take(top) {
+        writeln!(
+            out,
+            "{:>7} {:<16} {:>6.1} {:>8.3}",
+            process.pid,
+            process.name,
+            process.cpu,
+            process.power(total_power)
+        )?;
+    }
+    Ok(())
+}
+
+/// Formats the value of the sensor, or why there is none
+fn text<T>(sensor: &Sensor<T>, format: impl Fn(&T) -> String) -> String {
+    match (&sensor.state, sensor.value()) {
+        (SensorState::Ok, Some(value)) => format(value),
+        (SensorState::Error(reason), _) => format!("error: {reason}"),
+        _ => String::from("n/a"),
+    }
+}
@@ -5,6 +5,12 @@ pub enum Error {
     ParseInt(std::num::ParseIntError),
     ParseFloat(std::num::ParseFloatError),
     ParseCommand(String),
+    Command(String),
+    Unavailable(String),
+    InvalidInput(String),
+    Sink(String),
+    Remote(String),
+    Json(serde_json::Error),
 }
 
 impl std::fmt::Display for Error {
@@ -15,6 +21,12 @@ impl std::fmt::Display for Error {
             Error::Utf8(e) => write!(f, "Command output UTF8 exception: {e}"),
             Error::ParseCommand(e) => write!(f, "Command output parsing exception: {e}"),
             Error::ParseFloat(e) => write!(f, "ParseFloat error: {e}"),
+            Error::Command(e) => write!(f, "Command failed: {e}"),
+            Error::Unavailable(e) => write!(f, "Source unavailable: {e}"),
+            Error::InvalidInput(e) => write!(f, "Invalid input: {e}"),
+            Error::Sink(e) => write!(f, "Output failed: {e}"),
+            Error::Remote(e) => write!(f, "Remote failed: {e}"),
+            Error::Json(e) => write!(f, "JSON error: {e}"),
         }
     }
 }
@@ -27,6 +39,12 @@ impl std::error::Error for Error {
             Error::Utf8(e) => Some(e),
             Error::ParseCommand(_) => None,
             Error::ParseFloat(e) => Some(e),
+            Error::Command(_) => None,
+            Error::Unavailable(_) => None,
+            Error::InvalidInput(_) => None,
+            Error::Sink(_) => None,
+            Error::Remote(_) => None,
+            Error::Json(e) => Some(e),
         }
     }
 }
@@ -54,3 +72,15 @@ impl From<std::string::FromUtf8Error> for Error {
         Error::Utf8(e)
     }
 }
+
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        Error::Sink(e.to_string())
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Error::Json(e)
+    }
+}
@@ -1,11 +1,26 @@
 //! # Crate lib
 
+mod args;
 mod cli;
+mod commands;
 mod error;
 mod platform;
+mod recording;
+mod remote;
+mod server;
+mod sink;
+mod systemd;
 
+pub use args::Args;
 pub use cli::App;
+pub use commands::Command;
 pub use error::Error;
-pub use platform::Rpi;
+pub use platform::{EnergyMeter, EnergyState, FanController, Rpi};
+pub use remote::Remote;
+pub use sink::{
+    GraphiteSink, InfluxSink, InfluxTarget, MqttConfig, MqttSink, OtlpSink, Sinks, SnmpSink,
+    StatsdSink,
+};
+pub use systemd::init_logging;
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -1,14 +1,86 @@
-use color_eyre::Result;
-use pi_stats::App;
+use std::time::Duration;
+
+use clap::Parser;
+use color_eyre::{eyre::bail, Result};
+use pi_stats::{
+    init_logging, App, Args, Command, EnergyMeter, FanController, GraphiteSink, InfluxSink,
+    InfluxTarget, MqttConfig, MqttSink, OtlpSink, Remote, Sinks, SnmpSink, StatsdSink,
+};
 
 #[tokio::main]
 async fn main() -> Result<()> {
     color_eyre::install()?;
-    let terminal = ratatui::init();
-    let app_result = App::new()
-        .with_tick_duration(std::time::Duration::from_millis(1000))
-        .run(terminal)
-        .await;
+    let args = Args::parse();
+    // The modes running as a service log to the journal, the others keep the terminal to themselves
+    if args.headless || matches!(args.command, Some(Command::Serve(_))) {
+        init_logging();
+    }
+    if let Some(command) = args.command {
+        return Ok(command.run().await?);
+    }
+
+    let mut app = App::new().with_tick_duration(Duration::from_millis(args.interval));
+    if let Some(curve) = args.fan_curve {
+        app = app.with_fan_control(FanController::new(
+            curve,
+            args.fan_hysteresis,
+            args.fan_min_duty,
+        )?);
+    }
+
+    // The energy of a remote board is counted by the remote instance
+    if let Some(address) = args.remote {
+        app = app.with_remote(Remote::connect(&address)?);
+    } else if let Some(path) = args.energy_state.or_else(EnergyMeter::default_path) {
+        app = app.with_energy_state(path);
+    }
+
+    let mut sinks = Sinks::default();
+    if let Some(target) = args.influx {
+        if matches!(target, InfluxTarget::Stdout) && !args.headless {
+            bail!("--influx - writes to stdout and needs --headless");
+        }
+        let sink = InfluxSink::new(target, args.influx_token, args.influx_batch).await?;
+        sinks.spawn("influx", sink);
+    }
+    if let Some(mut broker) = args.mqtt {
+        broker.username = args.mqtt_username.or(broker.username);
+        broker.password = args.mqtt_password.or(broker.password);
+        let sink = MqttSink::new(MqttConfig {
+            broker,
+            topic: args.mqtt_topic,
+            retain: args.mqtt_retain,
+            discovery: args.mqtt_discovery,
+            ca: args.mqtt_ca,
+        })?;
+        sinks.spawn("mqtt", sink);
+    }
+    if let Some(address) = args.statsd {
+        let sink = StatsdSink::new(&address, args.metric_prefix.clone()).await?;
+        sinks.spawn("statsd", sink);
+    }
+    if let Some(address) = args.graphite {
+        sinks.spawn("graphite", GraphiteSink::new(address, args.metric_prefix));
+    }
+    if let Some(endpoint) = args.otlp {
+        let interval = Duration::from_secs(args.otlp_interval);
+        sinks.spawn(
+            "otlp",
+            OtlpSink::new(&endpoint, args.otlp_header, interval)?,
+        );
+    }
+    if let Some(address) = args.snmp {
+        let sink = SnmpSink::new(&address, args.snmp_community).await?;
+        sinks.spawn("snmp", sink);
+    }
+    app = app.with_sinks(sinks);
+
+    if args.headless {
+        return Ok(app.run_headless().await?);
+    }
+
+    let mut terminal = ratatui::init();
+    let app_result = app.run(&mut terminal).await;
     ratatui::restore();
-    app_result
+    Ok(app_result?)
 }
@@ -0,0 +1,431 @@
+//! Firmware configuration from `config.txt` and `vcgencmd get_config`
+//!
+//! `config.txt` is read the way the firmware reads it:
+//!
+//! - `[filter]` sections apply the following lines only if the board matches.
+//!   Filters of different kinds are combined, a filter replaces the previous one
+//!   of the same kind (`[pi4]` then `[pi5]`), `[all]` resets them.
+//! - `include <file>` is read in place, relative to the directory of `config.txt`
+//! - the last applicable value of a setting wins
+//!
+//! Filters which can not be evaluated here (`[EDID=...]`, `[gpio4=1]`, `[HDMI:0]`, ...)
+//! are assumed to match. `vcgencmd get_config int` and `get_config str` report
+//! the values the firmware has actually applied, including the defaults.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    platform::{
+        command::{Arg, Vcgencmd},
+        identity::BoardInfo,
+    },
+    Error, Result,
+};
+
+pub const CONFIG_PATHS: [&str; 2] = ["/boot/firmware/config.txt", "/boot/config.txt"];
+const MAX_INCLUDE_DEPTH: usize = 8;
+
+/// Settings affecting the clocks, voltages and temperature limits
+pub const OVERCLOCK_SETTINGS: [&str; 17] = [
+    "arm_freq",
+    "arm_freq_min",
+    "arm_boost",
+    "core_freq",
+    "core_freq_min",
+    "gpu_freq",
+    "gpu_freq_min",
+    "v3d_freq",
+    "sdram_freq",
+    "over_voltage",
+    "over_voltage_delta",
+    "over_voltage_min",
+    "over_voltage_sdram",
+    "force_turbo",
+    "initial_turbo",
+    "temp_limit",
+    "temp_soft_limit",
+];
+
+#[derive(Debug)]
+pub struct ConfigLine {
+    pub key: String,
+    pub value: String,
+    pub file: PathBuf,
+    pub line: usize,
+    /// Filters the line is under, e.g. `[pi5] [0x1234abcd]`, empty without any
+    pub section: String,
+    /// Whether the filters match this board
+    pub applies: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct BootConfig {
+    pub path: PathBuf,
+    pub lines: Vec<ConfigLine>,
+    /// Included files which do not exist
+    pub missing_includes: Vec<PathBuf>,
+}
+
+impl BootConfig {
+    /// First existing `config.txt` of the [`CONFIG_PATHS`]
+    pub fn find() -> Result<PathBuf> {
+        CONFIG_PATHS
+            .iter()
+            .map(PathBuf::from)
+            .find(|path| path.is_file())
+            .ok_or(Error::Unavailable(String::from("no config.txt")))
+    }
+
+    pub fn read(path: &Path, board: &BoardInfo) -> Result<Self> {
+        let mut parser = Parser {
+            board,
+            dir: path.parent().unwrap_or(Path::new("/")).to_owned(),
+            filters: Vec::new(),
+            config: Self {
+                path: path.to_owned(),
+                ..Default::default()
+            },
+        };
+        parser.read_file(path, 0)?;
+        Ok(parser.config)
+    }
+
+    /// Last applicable line setting the `key`
+    pub fn effective(&self, key: &str) -> Option<&ConfigLine> {
+        self.lines
+            .iter()
+            .rev()
+            .find(|line| line.applies && line.key == key)
+    }
+}
+
+/// Section filter with its kind, a filter replaces the previous one of the same kind
+struct Filter {
+    kind: String,
+    text: String,
+    matches: bool,
+}
+
+struct Parser<'a> {
+    board: &'a BoardInfo,
+    dir: PathBuf,
+    filters: Vec<Filter>,
+    config: BootConfig,
+}
+
+impl Parser<'_> {
+    fn read_file(&mut self, path: &Path, depth: usize) -> Result<()> {
+        if depth > MAX_INCLUDE_DEPTH {
+            return Err(Error::InvalidInput(format!(
+                "config.txt includes nested deeper than {MAX_INCLUDE_DEPTH} at {}",
+                path.display()
+            )));
+        }
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if depth > 0 && e.kind() == io::ErrorKind::NotFound => {
+                self.config.missing_includes.push(path.to_owned());
+                return Ok(());
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        for (index, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if let Some(filter) = line
+                .strip_prefix('[')
+                .and_then(|line| line.split_once(']'))
+                .map(|(filter, _)| filter.trim())
+            {
+                self.section(filter);
+                continue;
+            }
+
+            let (key, value) = match line.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => line.split_once(char::is_whitespace).unwrap_or((line, "")),
+            };
+            if key == "include" {
+                // Lines of a file included under a filter are only read if it matches
+                if self.applies() {
+                    let include = self.dir.join(value.trim());
+                    self.read_file(&include, depth + 1)?;
+                }
+                continue;
+            }
+
+            self.config.lines.push(ConfigLine {
+                key: key.to_owned(),
+                value: value.trim().to_owned(),
+                file: path.to_owned(),
+                line: index + 1,
+                section: self
+                    .filters
+                    .iter()
+                    .map(|filter| format!("[{}]", filter.text))
+                    .collect::<Vec<_>>()
+                    .join(" "),
+                applies: self.applies(),
+            });
+        }
+        Ok(())
+    }
+
+    fn applies(&self) -> bool {
+        self.filters.iter().all(|filter| filter.matches)
+    }
+
+    fn section(&mut self, text: &str) {
+        if text.eq_ignore_ascii_case("all") {
+            self.filters.clear();
+            return;
+        }
+
+        let lower = text.to_ascii_lowercase();
+        let (kind, matches) = if lower == "none" {
+            (String::from("none"), false)
+        } else if lower == "tryboot" {
+            // Only set for a single boot after `reboot '0 tryboot'`
+            (lower, false)
+        } else if let Some(serial) = lower.strip_prefix("0x") {
+            let matches = match (u64::from_str_radix(serial, 16), self.board_serial()) {
+                // 64-bit serials are matched by their lower 32 bits as well
+                (Ok(filter), Some(board)) => filter == board || filter == board & 0xffff_ffff,
+                (Ok(_), None) => true,
+                (Err(_), _) => false,
+            };
+            (String::from("serial"), matches)
+        } else if let Some(matches) = self.model_matches(&lower) {
+            (String::from("model"), matches)
+        } else {
+            // `gpio4=1`, `EDID=...`, `HDMI:0`, `board-type=...`, combined per name
+            let kind = lower
+                .split(['=', ':'])
+                .next()
+                .unwrap_or_default()
+                .to_owned();
+            (kind, true)
+        };
+
+        self.filters.retain(|filter| filter.kind != kind);
+        self.filters.push(Filter {
+            kind,
+            text: text.to_owned(),
+            matches,
+        });
+    }
+
+    /// Serial number of the board, `None` if it is unknown
+    fn board_serial(&self) -> Option<u64> {
+        let serial = self.board.serial.as_deref()?;
+        u64::from_str_radix(serial.trim().trim_start_matches("0x"), 16).ok()
+    }
+
+    /// Evaluates the model filter, `None` if it is not one, unknown boards match every model
+    fn model_matches(&self, filter: &str) -> Option<bool> {
+        let model = self.board.model.as_deref().unwrap_or_default();
+        let has = |name: &str| model.is_empty() || model.contains(name);
+        Some(match filter {
+            "pi5" => has("Raspberry Pi 5") || has("Compute Module 5"),
+            "pi500" => has("Raspberry Pi 500"),
+            "cm5" => has("Compute Module 5"),
+            "pi4" => has("Raspberry Pi 4") || has("Compute Module 4"),
+            "pi400" => has("Raspberry Pi 400"),
+            "cm4" => has("Compute Module 4"),
+            "cm4s" => has("Compute Module 4S"),
+            "pi3" => has("Raspberry Pi 3") || has("Compute Module 3"),
+            "pi3+" => has("Raspberry Pi 3 Model B Plus") || has("Raspberry Pi 3 Model A Plus"),
+            "pi2" => has("Raspberry Pi 2"),
+            "pi1" => has("Raspberry Pi Model") || has("Raspberry Pi Compute Module Rev"),
+            "pi0" => has("Raspberry Pi Zero"),
+            "pi0w" => has("Raspberry Pi Zero W"),
+            "pi02" => has("Raspberry Pi Zero 2"),
+            _ => return None,
+        })
+    }
+}
+
+/// Settings applied by the firmware from `vcgencmd get_config int` and `get_config str`
+#[derive(Debug, Default)]
+pub struct FirmwareConfig {
+    pub settings: Vec<(String, String)>,
+}
+
+impl FirmwareConfig {
+    pub fn read() -> Result<Self> {
+        let mut settings = Vec::new();
+        for kind in [Arg::Int, Arg::Str] {
+            let output = Vcgencmd::run(&[Arg::GetConfig.as_str(), kind.as_str()])?;
+            settings.extend(output.lines().filter_map(|line| {
+                let (key, value) = line.trim().split_once('=')?;
+                Some((key.to_owned(), value.to_owned()))
+            }));
+        }
+        settings.sort_unstable();
+        Ok(Self { settings })
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.settings
+            .iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::fixture;
+
+    fn board(model: &str, serial: &str) -> BoardInfo {
+        let mut board = BoardInfo::default();
+        board.model = Some(model.to_owned());
+        board.serial = Some(serial.to_owned());
+        board
+    }
+
+    /// Effective values of the keys after reading the files, `config.txt` first
+    fn effective(
+        name: &str,
+        board: &BoardInfo,
+        files: &[(&str, &str)],
+        keys: &[&str],
+    ) -> Vec<Option<String>> {
+        let root = fixture(name, files);
+        let config = BootConfig::read(&root.join("config.txt"), board).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+        keys.iter()
+            .map(|key| config.effective(key).map(|line| line.value.clone()))
+            .collect()
+    }
+
+    const PI5: &str = "Raspberry Pi 5 Model B Rev 1.0";
+
+    #[test]
+    fn last_applicable_value_wins() {
+        let config = "\
+# comment
+arm_freq=2400
+[pi4]
+arm_freq=1800
+over_voltage=2
+[pi5]
+arm_freq = 2800
+[all]
+temp_limit 75
+";
+        let values = effective(
+            "boot-last",
+            &board(PI5, "abcd1234"),
+            &[("config.txt", config)],
+            &["arm_freq", "over_voltage", "temp_limit", "gpu_freq"],
+        );
+        assert_eq!(values, [Some("2800".into()), None, Some("75".into()), None]);
+    }
+
+    #[test]
+    fn filters_of_different_kinds_combine() {
+        let config = "\
+[pi5]
+[0xabcd1234]
+arm_freq=3000
+[0x11111111]
+arm_freq=1000
+[pi4]
+arm_freq=1500
+";
+        let values = effective(
+            "boot-combine",
+            &board(PI5, "abcd1234"),
+            &[("config.txt", config)],
+            &["arm_freq"],
+        );
+        // `[0x11111111]` replaced the matching serial filter, `[pi4]` the model filter
+        assert_eq!(values, [Some("3000".into())]);
+    }
+
+    #[test]
+    fn serial_filters_compare_the_numbers() {
+        let check = |serial: &str, filter: &str| {
+            let config = format!("[{filter}]\narm_freq=3000\n");
+            let values = effective(
+                "boot-serial",
+                &board(PI5, serial),
+                &[("config.txt", &config)],
+                &["arm_freq"],
+            );
+            values[0].is_some()
+        };
+        assert!(check("abcd1234", "0xabcd1234"));
+        assert!(check("abcd1234", "0xABCD1234"));
+        assert!(check("0000abcd", "0xabcd"));
+        assert!(check("0000abcd", "0x0000abcd"));
+        assert!(check("6c1d3c8f0ac0cd79", "0x0ac0cd79"));
+        assert!(check("6c1d3c8f0ac0cd79", "0x6c1d3c8f0ac0cd79"));
+        assert!(!check("abcd1234", "0x0"));
+        assert!(!check("abcd1234", "0x00000000"));
+        assert!(!check("abcd1234", "0x1234"));
+        assert!(!check("6c1d3c8f0ac0cd79", "0x6c1d3c8f"));
+        assert!(!check("abcd1234", "0xnot-hex"));
+    }
+
+    #[test]
+    fn unknown_board_matches_model_and_serial_filters() {
+        let config = "[pi4]\n[0x1234]\narm_freq=1800\n[none]\narm_freq=1\n";
+        let values = effective(
+            "boot-unknown",
+            &BoardInfo::default(),
+            &[("config.txt", config)],
+            &["arm_freq"],
+        );
+        assert_eq!(values, [Some("1800".into())]);
+    }
+
+    #[test]
+    fn includes_are_read_in_place_under_matching_filters() {
+        let config = "\
+arm_freq=2000
+include extra.txt
+over_voltage=1
+[pi4]
+include pi4.txt
+[all]
+include missing.txt
+";
+        let root = fixture(
+            "boot-include",
+            &[
+                ("config.txt", config),
+                ("extra.txt", "arm_freq=2600\nover_voltage=4\n"),
+                ("pi4.txt", "arm_freq=1800\n"),
+            ],
+        );
+        let config = BootConfig::read(&root.join("config.txt"), &board(PI5, "1")).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+
+        let arm_freq = config.effective("arm_freq").unwrap();
+        assert_eq!(arm_freq.value, "2600");
+        assert_eq!(arm_freq.file, root.join("extra.txt"));
+        assert_eq!(arm_freq.line, 1);
+        assert_eq!(config.effective("over_voltage").unwrap().value, "1");
+        // The lines of pi4.txt are not even listed
+        assert_eq!(config.lines.len(), 4);
+        assert_eq!(config.missing_includes, [root.join("missing.txt")]);
+    }
+
+    #[test]
+    fn recursive_includes_are_cut_off() {
+        let root = fixture("boot-recursive", &[("config.txt", "include config.txt\n")]);
+        let result = BootConfig::read(&root.join("config.txt"), &board(PI5, "1"));
+        fs::remove_dir_all(&root).unwrap();
+        assert!(matches!(result, Err(Error::InvalidInput(_))));
+    }
+}
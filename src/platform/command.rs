@@ -1,27 +1,74 @@
-use std::process;
+use std::{io, process};
 
-use crate::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::{Error, Result};
 
 const VCGENCMD: &str = "vcgencmd";
 
+/// Serialized by its `vcgencmd` name, e.g. the clocks as `arm` and `core`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum Arg {
     MeasureTemp,  // measure_temp
     MeasureClock, // measure_clock
+    MeasureVolts, // measure_volts
     Arm,          // arm
     Core,         // core
+    H264,         // h264
+    Isp,          // isp
+    V3d,          // v3d
+    Uart,         // uart
+    Pwm,          // pwm
+    Emmc,         // emmc
+    Pixel,        // pixel
+    Vec,          // vec
+    Hdmi,         // hdmi
+    Dpi,          // dpi
+    SdramC,       // sdram_c
+    SdramI,       // sdram_i
+    SdramP,       // sdram_p
+    Gpu,          // gpu
     PmicReadAdc,  // pmic_read_adc
     GetThrottled, // get_throttled
+    GetMem,       // get_mem
+    ReadRingOsc,  // read_ring_osc
+    Version,      // version
+    GetConfig,    // get_config
+    Int,          // int
+    Str,          // str
 }
 
 impl Arg {
-    pub fn as_str(&self) -> &str {
+    pub fn as_str(&self) -> &'static str {
         match self {
             Arg::MeasureTemp => "measure_temp",
             Arg::MeasureClock => "measure_clock",
+            Arg::MeasureVolts => "measure_volts",
             Arg::Arm => "arm",
             Arg::Core => "core",
+            Arg::H264 => "h264",
+            Arg::Isp => "isp",
+            Arg::V3d => "v3d",
+            Arg::Uart => "uart",
+            Arg::Pwm => "pwm",
+            Arg::Emmc => "emmc",
+            Arg::Pixel => "pixel",
+            Arg::Vec => "vec",
+            Arg::Hdmi => "hdmi",
+            Arg::Dpi => "dpi",
+            Arg::SdramC => "sdram_c",
+            Arg::SdramI => "sdram_i",
+            Arg::SdramP => "sdram_p",
+            Arg::Gpu => "gpu",
             Arg::PmicReadAdc => "pmic_read_adc",
             Arg::GetThrottled => "get_throttled",
+            Arg::GetMem => "get_mem",
+            Arg::ReadRingOsc => "read_ring_osc",
+            Arg::Version => "version",
+            Arg::GetConfig => "get_config",
+            Arg::Int => "int",
+            Arg::Str => "str",
         }
     }
 }
@@ -30,8 +77,23 @@ pub struct Vcgencmd;
 
 impl Vcgencmd {
     pub fn run(args: &[&str]) -> Result<String> {
-        Ok(String::from_utf8(
-            process::Command::new(VCGENCMD).args(args).output()?.stdout,
-        )?)
+        let output = match process::Command::new(VCGENCMD).args(args).output() {
+            Ok(output) => output,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                return Err(Error::Unavailable(format!("{VCGENCMD} not found")));
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        if !output.status.success() {
+            return Err(Error::Command(format!(
+                "{VCGENCMD} {} exited with {}: {}",
+                args.join(" "),
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+
+        Ok(String::from_utf8(output.stdout)?)
     }
 }
@@ -1,7 +1,12 @@
 use std::{ops::Deref, str::FromStr};
 
+use serde::{Deserialize, Serialize};
+
 use crate::{
-    platform::command::{Arg, Vcgencmd},
+    platform::{
+        command::{Arg, Vcgencmd},
+        sensor::Sensor,
+    },
     Error, Result,
 };
 
@@ -9,7 +14,7 @@ pub type ClockHz = u32;
 pub type ClockMhz = f32;
 pub type TempValue = f32;
 
-#[derive(Default)]
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
 pub struct Clock(ClockMhz);
 
 impl Deref for Clock {
@@ -28,7 +33,23 @@ impl FromStr for Clock {
     }
 }
 
-#[derive(Default)]
+impl Clock {
+    /// Parses `vcgencmd measure_clock <clock>` printing `frequency(<id>)=<hz>`
+    pub fn measure(clock: Arg) -> Result<Self> {
+        Vcgencmd::run(&[Arg::MeasureClock.as_str(), clock.as_str()])?
+            .trim()
+            .strip_prefix("frequency(")
+            .and_then(|output| output.split_once(")="))
+            .ok_or(Error::ParseCommand(format!(
+                "Failed to strip prefix: frequency(..)= of {} clock",
+                clock.as_str()
+            )))?
+            .1
+            .parse()
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
 pub struct CpuTemp(TempValue);
 
 impl Deref for CpuTemp {
@@ -57,7 +78,8 @@ impl CpuTemp {
     }
 }
 
-#[derive(Default)]
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct CpuClock {
     pub arm: Clock,
     pub gpu: Clock,
@@ -65,37 +87,23 @@ pub struct CpuClock {
 
 impl CpuClock {
     pub fn new() -> Result<Self> {
-        use Arg::*;
         Ok(Self {
-            arm: Vcgencmd::run(&[MeasureClock.as_str(), Arm.as_str()])?
-                .trim()
-                .strip_prefix("frequency(0)=")
-                .ok_or(Error::ParseCommand(String::from(
-                    "Failed to strip prefix: frequency(0)=",
-                )))?
-                .parse::<Clock>()?,
-            gpu: Vcgencmd::run(&[MeasureClock.as_str(), Core.as_str()])?
-                .trim()
-                .strip_prefix("frequency(0)=")
-                .ok_or(Error::ParseCommand(String::from(
-                    "Failed to strip prefix: frequency(0)=",
-                )))?
-                .parse::<Clock>()?,
+            arm: Clock::measure(Arg::Arm)?,
+            gpu: Clock::measure(Arg::Core)?,
         })
     }
 }
 
-#[derive(Default)]
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct CpuStatus {
-    pub clock: CpuClock,
-    pub temp: CpuTemp,
+    pub clock: Sensor<CpuClock>,
+    pub temp: Sensor<CpuTemp>,
 }
 
 impl CpuStatus {
-    pub fn new() -> Result<Self> {
-        Ok(Self {
-            clock: CpuClock::new()?,
-            temp: CpuTemp::new()?,
-        })
+    pub fn update(&mut self) {
+        self.clock.update(CpuClock::new());
+        self.temp.update(CpuTemp::new());
     }
 }
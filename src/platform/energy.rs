@@ -0,0 +1,365 @@
+//! Energy counters integrated from the power readings
+//!
+//! Each update adds the energy since the previous power reading, using the mean
+//! of both readings over the actual time between them, so the counters stay
+//! correct when the update interval changes. Gaps longer than [`MAX_GAP`] or
+//! [`MAX_MISSED`] update intervals, whichever is longer, (suspended board,
+//! failing power source) are not counted.
+//!
+//! The counters are monotonic and can be persisted in a small state file, see [`EnergyState`]:
+//!
+//! ```text
+//! since 1760000000
+//! total 12.345678
+//! rail VDD_CORE 8.123456
+//! ```
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    platform::power::{PowerStatus, Watt},
+    Error, Result,
+};
+
+pub type WattHour = f64;
+/// Power of each rail by its name
+type RailPower = Vec<(String, Watt)>;
+
+const MAX_GAP: Duration = Duration::from_secs(60);
+/// Missed updates after which the time between two readings is a gap
+const MAX_MISSED: u32 = 3;
+/// How often the counters are written to the state file
+const SAVE_INTERVAL: Duration = Duration::from_secs(60);
+const STATE_DIR: &str = "pi-stats";
+const STATE_FILE: &str = "energy";
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EnergyMeter {
+    pub total: WattHour,
+    pub rails: BTreeMap<String, WattHour>,
+    /// Unix time the counters were started at
+    pub since: u64,
+    #[serde(skip)]
+    /// Previous reading, total and per rail power
+    last: Option<(Instant, Watt, RailPower)>,
+    #[serde(skip)]
+    /// Time between the updates
+    interval: Duration,
+}
+
+impl EnergyMeter {
+    pub fn new() -> Self {
+        Self {
+            since: unix_time(),
+            ..Default::default()
+        }
+    }
+
+    /// Sets the time between the updates, slow sampling allows for longer gaps
+    pub fn set_interval(&mut self, interval: Duration) {
+        self.interval = interval;
+    }
+
+    pub fn update(&mut self, power: &PowerStatus) {
+        let Some(power) = power.power.current() else {
+            return;
+        };
+        let rails = power
+            .power_map
+            .iter()
+            .map(|rail| (rail.measure.clone(), rail.volts * rail.amps))
+            .collect::<Vec<_>>();
+        self.add(Instant::now(), power.total_power, rails);
+    }
+
+    /// Adds the energy since the previous reading
+    fn add(&mut self, now: Instant, total: Watt, rails: RailPower) {
+        if let Some((last, last_total, last_rails)) = &self.last {
+            let elapsed = now.duration_since(*last);
+            if elapsed <= MAX_GAP.max(self.interval * MAX_MISSED) {
+                let hours = elapsed.as_secs_f64() / 3_600.0;
+                let energy = |from: Watt, to: Watt| (from as f64 + to as f64) / 2.0 * hours;
+
+                self.total += energy(*last_total, total);
+                for (name, watts) in &rails {
+                    let Some((_, from)) = last_rails.iter().find(|(last, _)| last == name) else {
+                        continue;
+                    };
+                    *self.rails.entry(name.clone()).or_default() += energy(*from, *watts);
+                }
+            }
+        }
+        self.last = Some((now, total, rails));
+    }
+
+    /// Default state file under `$XDG_STATE_HOME` or `~/.local/state`
+    pub fn default_path() -> Option<PathBuf> {
+        let state = match std::env::var_os("XDG_STATE_HOME") {
+            Some(state) => PathBuf::from(state),
+            None => PathBuf::from(std::env::var_os("HOME")?).join(".local/state"),
+        };
+        Some(state.join(STATE_DIR).join(STATE_FILE))
+    }
+
+    /// Counters from the state file, new ones if it does not exist yet
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut meter = Self::new();
+        for line in content.lines() {
+            let mut it = line.split_ascii_whitespace();
+            let invalid = || {
+                Error::InvalidInput(format!(
+                    "unexpected line {line} in the energy state {}",
+                    path.display()
+                ))
+            };
+            match it.next() {
+                Some("since") => meter.since = it.next().ok_or_else(invalid)?.parse()?,
+                Some("total") => meter.total = it.next().ok_or_else(invalid)?.parse()?,
+                Some("rail") => {
+                    let name = it.next().ok_or_else(invalid)?;
+                    let energy = it.next().ok_or_else(invalid)?.parse()?;
+                    meter.rails.insert(name.to_owned(), energy);
+                }
+                None => {}
+                Some(_) => return Err(invalid()),
+            }
+        }
+        Ok(meter)
+    }
+
+    /// Writes the counters next to the state file first, so a crash never leaves a partial file
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut content = format!("since {}\ntotal {:.6}\n", self.since, self.total);
+        for (name, energy) in &self.rails {
+            content.push_str(&format!("rail {name} {energy:.6}\n"));
+        }
+
+        let temp = path.with_extension("tmp");
+        fs::write(&temp, content)?;
+        Ok(fs::rename(temp, path)?)
+    }
+
+    /// Time the counters have been running for
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs(unix_time().saturating_sub(self.since))
+    }
+}
+
+/// State file of the counters, written periodically and on exit
+#[derive(Debug)]
+pub struct EnergyState {
+    path: PathBuf,
+    saved: Instant,
+    error: Option<String>,
+}
+
+impl EnergyState {
+    /// Counters from the state file at `path`. A file which can not be read is moved aside
+    /// to `<path>.bad` and the counting starts over, reported by [`EnergyState::error`]
+    /// until the next successful save.
+    pub fn load(path: PathBuf) -> (Self, EnergyMeter) {
+        let (meter, error) = match EnergyMeter::load(&path) {
+            Ok(meter) => (meter, None),
+            Err(e) => {
+                let mut bad = path.clone().into_os_string();
+                bad.push(".bad");
+                let bad = PathBuf::from(bad);
+                let error = match fs::rename(&path, &bad) {
+                    Ok(()) => format!("{e}, moved to {} and started over", bad.display()),
+                    Err(rename) => format!("{e}, started over ({rename} moving it aside)"),
+                };
+                (EnergyMeter::new(), Some(error))
+            }
+        };
+        let state = Self {
+            path,
+            saved: Instant::now(),
+            error,
+        };
+        (state, meter)
+    }
+
+    /// Saves the counters once the save interval has passed
+    pub fn update(&mut self, meter: &EnergyMeter) {
+        if self.saved.elapsed() >= SAVE_INTERVAL {
+            self.save(meter);
+        }
+    }
+
+    pub fn save(&mut self, meter: &EnergyMeter) {
+        self.saved = Instant::now();
+        self.error = meter.save(&self.path).err().map(|e| e.to_string());
+    }
+
+    /// Why the counters could not be loaded or saved, cleared by the next successful save
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::fixture;
+
+    fn rails(rails: &[(&str, Watt)]) -> RailPower {
+        rails
+            .iter()
+            .map(|(name, watts)| (name.to_string(), *watts))
+            .collect()
+    }
+
+    fn assert_close(actual: WattHour, expected: WattHour) {
+        assert!(
+            (actual - expected).abs() < 1e-9,
+            "{actual} Wh, expected {expected} Wh"
+        );
+    }
+
+    #[test]
+    fn integrates_mean_power_over_time() {
+        let start = Instant::now();
+        let mut meter = EnergyMeter::new();
+        meter.add(start, 2.0, rails(&[("VDD_CORE", 1.0)]));
+        assert_eq!(meter.total, 0.0);
+
+        // 36 s at a mean of 3 W and 1.5 W
+        let second = start + Duration::from_secs(36);
+        meter.add(second, 4.0, rails(&[("VDD_CORE", 2.0), ("EXT5V", 5.0)]));
+        assert_close(meter.total, 0.03);
+        assert_close(meter.rails["VDD_CORE"], 0.015);
+        // No previous reading of the rail yet
+        assert!(!meter.rails.contains_key("EXT5V"));
+
+        meter.add(
+            second + Duration::from_secs(18),
+            4.0,
+            rails(&[("VDD_CORE", 2.0), ("EXT5V", 5.0)]),
+        );
+        assert_close(meter.total, 0.05);
+        assert_close(meter.rails["EXT5V"], 0.025);
+    }
+
+    #[test]
+    fn skips_gaps_longer_than_max_gap() {
+        let start = Instant::now();
+        let mut meter = EnergyMeter::new();
+        meter.add(start, 10.0, Vec::new());
+        let resumed = start + MAX_GAP + Duration::from_secs(1);
+        meter.add(resumed, 10.0, Vec::new());
+        assert_eq!(meter.total, 0.0);
+
+        // Counting resumes from the reading after the gap
+        meter.add(resumed + Duration::from_secs(36), 10.0, Vec::new());
+        assert_close(meter.total, 0.1);
+    }
+
+    #[test]
+    fn counts_long_intervals() {
+        let interval = Duration::from_secs(300);
+        let start = Instant::now();
+        let mut meter = EnergyMeter::new();
+        meter.set_interval(interval);
+        meter.add(start, 10.0, Vec::new());
+        meter.add(start + interval, 10.0, Vec::new());
+        assert_close(meter.total, 10.0 * 5.0 / 60.0);
+
+        // A late reading still counts, a gap of more than the missed updates does not
+        let late = start + interval * 2 + Duration::from_secs(30);
+        meter.add(late, 10.0, Vec::new());
+        assert_close(meter.total, 10.0 * 10.5 / 60.0);
+        meter.add(
+            late + interval * MAX_MISSED + Duration::from_secs(1),
+            10.0,
+            Vec::new(),
+        );
+        assert_close(meter.total, 10.0 * 10.5 / 60.0);
+    }
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let root = fixture("energy-round-trip", &[]);
+        let path = root.join("state/energy");
+        let mut meter = EnergyMeter::new();
+        meter.since = 1_760_000_000;
+        meter.total = 12.345678;
+        meter.rails.insert(String::from("VDD_CORE"), 8.123456);
+        meter.rails.insert(String::from("EXT5V"), 0.5);
+        meter.save(&path).unwrap();
+
+        let content = fs::read_to_string(&path).unwrap();
+        let loaded = EnergyMeter::load(&path).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(
+            content,
+            "since 1760000000\ntotal 12.345678\nrail EXT5V 0.500000\nrail VDD_CORE 8.123456\n"
+        );
+        assert_eq!(loaded.since, meter.since);
+        assert_eq!(loaded.total, meter.total);
+        assert_eq!(loaded.rails, meter.rails);
+    }
+
+    #[test]
+    fn missing_state_starts_over() {
+        let meter = EnergyMeter::load(Path::new("/nonexistent/pi-stats/energy")).unwrap();
+        assert_eq!(meter.total, 0.0);
+        assert!(meter.since > 0);
+    }
+
+    #[test]
+    fn rejects_corrupt_state() {
+        for (name, content) in [
+            ("energy-garbage", "since 1760000000\nwatts 5\n"),
+            ("energy-truncated", "since 1760000000\ntotal\n"),
+            ("energy-number", "since 1760000000\ntotal 12.3.4\n"),
+        ] {
+            let root = fixture(name, &[("energy", content)]);
+            let result = EnergyMeter::load(&root.join("energy"));
+            fs::remove_dir_all(&root).unwrap();
+            assert!(result.is_err(), "{content:?} accepted");
+        }
+    }
+
+    #[test]
+    fn corrupt_state_is_moved_aside() {
+        let root = fixture("energy-aside", &[("energy", "total 1.0\nrail VDD_CORE\n")]);
+        let path = root.join("energy");
+        let (mut state, meter) = EnergyState::load(path.clone());
+
+        assert_eq!(meter.total, 0.0);
+        assert!(state.error().is_some_and(|e| e.contains("energy.bad")));
+        let bad = fs::read_to_string(root.join("energy.bad")).unwrap();
+        assert_eq!(bad, "total 1.0\nrail VDD_CORE\n");
+
+        state.save(&meter);
+        let saved = EnergyMeter::load(&path);
+        fs::remove_dir_all(&root).unwrap();
+        assert_eq!(state.error(), None);
+        assert!(saved.is_ok());
+    }
+}
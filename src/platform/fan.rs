@@ -1,42 +1,40 @@
-use std::fs;
+use serde::{Deserialize, Serialize};
 
-use crate::Result;
+use crate::{
+    platform::hwmon::{ChannelKind, Hwmon},
+    Error, Result,
+};
 
 pub type PwmValue = u8;
 pub type RpmValue = u32;
 
-const FAN_PATH: &str = "/sys/devices/platform/cooling_fan/hwmon";
-const FAN_PWM_SUFFIX: &str = "pwm1";
-const FAN_RPS_SUFFIX: &str = "fan1_input";
+/// hwmon `name` of the Raspberry Pi 5 cooling fan
+pub const FAN_DEVICE_NAME: &str = "pwmfan";
+pub const FAN_CHANNEL: u32 = 1;
 
-#[derive(Default)]
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct FanStatus {
     pub pwm: PwmValue,
     pub rpm: RpmValue,
 }
 
 impl FanStatus {
-    pub fn new() -> Result<Self> {
-        //FIXME Need to find more elegant solution to this
-        //FIXME Error propogation
-        let mut hwmon_dir = fs::read_dir(FAN_PATH)?
-            .next()
-            .expect("expected one folder under hwmon")?
-            .path();
-
-        if !hwmon_dir.is_dir() {
-            return Err(crate::Error::ParseCommand(String::from("error")));
-        }
-
-        hwmon_dir.push(FAN_PWM_SUFFIX);
-        let pwm = fs::read_to_string(hwmon_dir.as_path())?;
-        hwmon_dir.pop();
-        hwmon_dir.push(FAN_RPS_SUFFIX);
-        let rpm = fs::read_to_string(hwmon_dir.as_path())?;
+    pub fn new(hwmon: &Hwmon) -> Result<Self> {
+        let device = hwmon.find(FAN_DEVICE_NAME)?;
+        let value = |kind| {
+            device
+                .channel(kind, FAN_CHANNEL)
+                .map(|channel| channel.value)
+                .ok_or(Error::Unavailable(format!(
+                    "no {}{FAN_CHANNEL} on {FAN_DEVICE_NAME}",
+                    kind.as_str()
+                )))
+        };
 
         Ok(Self {
-            pwm: pwm.trim().parse()?,
-            rpm: rpm.trim().parse()?,
+            pwm: value(ChannelKind::Pwm)? as PwmValue,
+            rpm: value(ChannelKind::Fan)? as RpmValue,
         })
     }
 }
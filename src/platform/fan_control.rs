@@ -0,0 +1,343 @@
+//! Opt-in user control of the cooling fan
+//!
+//! The controller switches `pwm1_enable` of the `pwmfan` hwmon device
+//! into manual mode and writes `pwm1` according to a [`FanCurve`].
+//! The original `pwm1_enable` value (kernel automatic control) is restored when:
+//!
+//! - the controller or [`ManualFan`] is dropped (normal exit, errors)
+//! - the application panics, see [`install_panic_hook`], installed with the first controller
+//! - the temperature can not be read anymore
+//!
+//! Nothing can be done on `SIGKILL`, the fan stays at the last written duty then.
+
+use std::{
+    fs,
+    path::PathBuf,
+    str::FromStr,
+    sync::{Mutex, Once},
+};
+
+use crate::{
+    platform::{
+        cpu::TempValue,
+        fan::{PwmValue, FAN_CHANNEL, FAN_DEVICE_NAME},
+        hwmon::Hwmon,
+    },
+    Error, Result,
+};
+
+const PWM_ENABLE_MANUAL: &str = "1";
+
+/// `pwm1_enable` path and its original value, shared with the panic hook
+static RESTORE: Mutex<Option<(PathBuf, String)>> = Mutex::new(None);
+static PANIC_HOOK: Once = Once::new();
+
+/// Temperature to PWM duty mapping, linearly interpolated between the points
+#[derive(Debug, Clone)]
+pub struct FanCurve {
+    points: Vec<(TempValue, PwmValue)>,
+}
+
+impl FanCurve {
+    pub fn points(&self) -> &[(TempValue, PwmValue)] {
+        &self.points
+    }
+
+    pub fn duty(&self, temp: TempValue) -> PwmValue {
+        let (first, last) = (self.points[0], self.points[self.points.len() - 1]);
+        if temp <= first.0 {
+            return first.1;
+        }
+        if temp >= last.0 {
+            return last.1;
+        }
+
+        self.points
+            .windows(2)
+            .find(|pair| temp <= pair[1].0)
+            .map(|pair| {
+                let ((t0, d0), (t1, d1)) = (pair[0], pair[1]);
+                let ratio = (temp - t0) / (t1 - t0);
+                (d0 as f32 + ratio * (d1 as f32 - d0 as f32)).round() as PwmValue
+            })
+            .unwrap_or(last.1)
+    }
+}
+
+/// Parses the comma separated `TEMP:PWM` points, e.g. `50:0,60:100,70:180,80:255`
+impl FromStr for FanCurve {
+    type Err = Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let points = s
+            .split(',')
+            .map(|point| {
+                let (temp, duty) =
+                    point
+                        .trim()
+                        .split_once(':')
+                        .ok_or(Error::InvalidInput(format!(
+                            "expected TEMP:PWM fan curve point, got {point}"
+                        )))?;
+                Ok((temp.trim().parse()?, duty.trim().parse()?))
+            })
+            .collect::<Result<Vec<(TempValue, PwmValue)>>>()?;
+
+        if points.is_empty() {
+            return Err(Error::InvalidInput(String::from("empty fan curve")));
+        }
+        if points.windows(2).any(|pair| pair[0].0 >= pair[1].0) {
+            return Err(Error::InvalidInput(String::from(
+                "fan curve temperatures must be strictly ascending",
+            )));
+        }
+
+        Ok(Self { points })
+    }
+}
+
+/// Manual fan mode, the original `pwm1_enable` value is restored on drop
+pub struct ManualFan {
+    pwm_path: PathBuf,
+}
+
+impl ManualFan {
+    /// Switches the fan into manual mode
+    pub fn engage() -> Result<Self> {
+        PANIC_HOOK.call_once(install_panic_hook);
+
+        let hwmon = Hwmon::scan()?;
+        let device = hwmon.find(FAN_DEVICE_NAME)?;
+        let enable_path = device.path.join(format!("pwm{FAN_CHANNEL}_enable"));
+
+        let original = fs::read_to_string(&enable_path)?.trim().to_owned();
+        fs::write(&enable_path, PWM_ENABLE_MANUAL)?;
+        *RESTORE.lock().unwrap_or_else(|e| e.into_inner()) = Some((enable_path, original));
+
+        Ok(Self {
+            pwm_path: device.path.join(format!("pwm{FAN_CHANNEL}")),
+        })
+    }
+
+    pub fn set(&self, duty: PwmValue) -> Result<()> {
+        Ok(fs::write(&self.pwm_path, duty.to_string())?)
+    }
+}
+
+impl Drop for ManualFan {
+    fn drop(&mut self) {
+        restore_automatic();
+    }
+}
+
+pub struct FanController {
+    curve: FanCurve,
+    /// Degrees the temperature has to fall before the duty is lowered
+    hysteresis: TempValue,
+    /// Lowest non zero duty, the fan may not spin up below it
+    min_duty: PwmValue,
+    /// `None` while the control is handed back to the kernel
+    fan: Option<ManualFan>,
+    duty: Option<PwmValue>,
+    temp: Option<TempValue>,
+    /// Temperature at which the current duty was set
+    setpoint: TempValue,
+}
+
+impl FanController {
+    pub fn new(curve: FanCurve, hysteresis: TempValue, min_duty: PwmValue) -> Result<Self> {
+        Ok(Self {
+            curve,
+            hysteresis,
+            min_duty,
+            fan: Some(ManualFan::engage()?),
+            duty: None,
+            temp: None,
+            setpoint: TempValue::MIN,
+        })
+    }
+
+    pub fn curve(&self) -> &FanCurve {
+        &self.curve
+    }
+
+    pub fn hysteresis(&self) -> TempValue {
+        self.hysteresis
+    }
+
+    pub fn min_duty(&self) -> PwmValue {
+        self.min_duty
+    }
+
+    /// Current operating point, `None` until the first update or after the fallback
+    pub fn operating_point(&self) -> Option<(TempValue, PwmValue)> {
+        Some((self.temp?, self.duty?))
+    }
+
+    /// Applies the curve for the given temperature, without a temperature
+    /// the control is handed back to the kernel until the next valid reading
+    pub fn update(&mut self, temp: Option<TempValue>) -> Result<()> {
+        self.temp = temp;
+        let Some(temp) = temp else {
+            self.release();
+            return Ok(());
+        };
+        let fan = match self.fan.take() {
+            Some(fan) => fan,
+            None => ManualFan::engage()?,
+        };
+
+        let duty = self.next_duty(temp);
+        if self.duty != Some(duty) {
+            if let Err(e) = fan.set(duty) {
+                self.release();
+                return Err(e);
+            }
+            self.duty = Some(duty);
+        }
+        self.fan = Some(fan);
+        Ok(())
+    }
+
+    /// Duty for the temperature, lowered only once the temperature has fallen by the hysteresis
+    /// below the one the current duty was set at
+    fn next_duty(&mut self, temp: TempValue) -> PwmValue {
+        let target = match self.curve.duty(temp) {
+            0 => 0,
+            duty => duty.max(self.min_duty),
+        };
+        let duty = match self.duty {
+            Some(duty) if target < duty && temp > self.setpoint - self.hysteresis => duty,
+            _ => target,
+        };
+        if self.duty != Some(duty) || temp > self.setpoint {
+            self.setpoint = temp;
+        }
+        duty
+    }
+
+    fn release(&mut self) {
+        self.fan = None;
+        self.duty = None;
+    }
+}
+
+/// Writes back the original `pwm1_enable` value if the control is active
+fn restore_automatic() {
+    if let Some((path, original)) = RESTORE.lock().unwrap_or_else(|e| e.into_inner()).take() {
+        let _ = fs::write(path, original);
+    }
+}
+
+/// Chains a panic hook restoring the automatic fan control before the previous hook
+fn install_panic_hook() {
+    let hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        restore_automatic();
+        hook(info);
+    }));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn controller(curve: &str, hysteresis: TempValue, min_duty: PwmValue) -> FanController {
+        FanController {
+            curve: curve.parse().unwrap(),
+            hysteresis,
+            min_duty,
+            fan: None,
+            duty: None,
+            temp: None,
+            setpoint: TempValue::MIN,
+        }
+    }
+
+    /// Duties the controller settles on for the temperatures, as if every write succeeded
+    fn run(controller: &mut FanController, temps: &[TempValue]) -> Vec<PwmValue> {
+        temps
+            .iter()
+            .map(|&temp| {
+                let duty = controller.next_duty(temp);
+                controller.duty = Some(duty);
+                duty
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parses_curve() {
+        let curve: FanCurve = " 50:0, 60:100 ,70:180,80:255".parse().unwrap();
+        assert_eq!(
+            curve.points(),
+            [(50.0, 0), (60.0, 100), (70.0, 180), (80.0, 255)]
+        );
+        let curve: FanCurve = "42.5:128".parse().unwrap();
+        assert_eq!(curve.points(), [(42.5, 128)]);
+    }
+
+    #[test]
+    fn rejects_invalid_curves() {
+        for curve in [
+            "",
+            "50",
+            "50:0,60",
+            "50:0;60:100",
+            "hot:100",
+            "50:256",
+            "50:-1",
+            "60:100,50:0",
+            "50:0,50:100",
+        ] {
+            assert!(curve.parse::<FanCurve>().is_err(), "{curve:?} accepted");
+        }
+    }
+
+    #[test]
+    fn interpolates_duty() {
+        let curve: FanCurve = "50:0,60:100,80:255".parse().unwrap();
+        assert_eq!(curve.duty(20.0), 0);
+        assert_eq!(curve.duty(50.0), 0);
+        assert_eq!(curve.duty(55.0), 50);
+        assert_eq!(curve.duty(60.0), 100);
+        assert_eq!(curve.duty(70.0), 178);
+        assert_eq!(curve.duty(80.0), 255);
+        assert_eq!(curve.duty(95.0), 255);
+    }
+
+    #[test]
+    fn single_point_curve_is_flat() {
+        let curve: FanCurve = "60:120".parse().unwrap();
+        assert_eq!(curve.duty(30.0), 120);
+        assert_eq!(curve.duty(90.0), 120);
+    }
+
+    #[test]
+    fn raises_duty_at_once_and_lowers_after_hysteresis() {
+        let mut controller = controller("50:0,60:100,70:200", 3.0, 0);
+        assert_eq!(
+            run(&mut controller, &[60.0, 65.0, 63.0, 62.1, 61.9, 58.0]),
+            [100, 150, 150, 150, 119, 80]
+        );
+    }
+
+    #[test]
+    fn hysteresis_counts_from_the_peak() {
+        let mut controller = controller("50:0,60:100,70:200", 3.0, 0);
+        // Set at 62, raised at 66: 62.9 is within 3 degrees of the first but not of the peak
+        assert_eq!(
+            run(&mut controller, &[62.0, 66.0, 63.1, 62.9]),
+            [120, 160, 160, 129]
+        );
+    }
+
+    #[test]
+    fn applies_min_duty_but_keeps_the_fan_off() {
+        let mut controller = controller("50:0,60:100", 0.0, 60);
+        assert_eq!(
+            run(&mut controller, &[45.0, 51.0, 58.0, 40.0]),
+            [0, 60, 80, 0]
+        );
+    }
+}
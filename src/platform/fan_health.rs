@@ -0,0 +1,243 @@
+//! Fan health diagnostics based on the PWM to RPM relationship
+//!
+//! The PWM range is split into buckets, each bucket learns its baseline RPM
+//! from the first samples and keeps a moving average of the recent ones.
+//! Samples taken right after a PWM change are skipped, the fan needs time to settle.
+//!
+//! | Issue             | Condition                                                                     |
+//! | ----------------- | ----------------------------------------------------------------------------- |
+//! | Stall             | RPM is 0 at a high PWM for a while, although the fan has been spinning before |
+//! | Tach disconnected | RPM is 0 at a high PWM for a while and has never been reported above 0        |
+//! | Degraded          | Recent RPM is noticeably below the baseline learned for the same PWM          |
+//!
+//! The baseline is learned per run, so the degradation is only detected while the application is running.
+
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::platform::fan::{FanStatus, PwmValue, RpmValue};
+
+const BUCKET_WIDTH: PwmValue = 32;
+const BUCKETS: usize = (PwmValue::MAX as usize + 1) / BUCKET_WIDTH as usize;
+/// PWM from which a spinning fan is expected
+const STALL_PWM: PwmValue = 100;
+const STALL_TIME: Duration = Duration::from_secs(5);
+const BASELINE_SAMPLES: u32 = 30;
+const RECENT_SAMPLES: u32 = 10;
+const RECENT_WEIGHT: f64 = 0.1;
+/// Part of the baseline RPM the recent average is allowed to lose
+const DEGRADED_RATIO: f64 = 0.15;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum FanIssue {
+    Stall,
+    TachDisconnected,
+    Degraded {
+        pwm: PwmValue,
+        expected: RpmValue,
+        actual: RpmValue,
+    },
+}
+
+impl std::fmt::Display for FanIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FanIssue::Stall => write!(f, "STALL"),
+            FanIssue::TachDisconnected => write!(f, "TACH DISCONNECTED"),
+            FanIssue::Degraded {
+                pwm,
+                expected,
+                actual,
+            } => write!(f, "DEGRADED ({actual}/{expected} RPM at {pwm} PWM)"),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct Bucket {
+    baseline_sum: f64,
+    baseline_samples: u32,
+    recent: f64,
+    recent_samples: u32,
+}
+
+impl Bucket {
+    fn add(&mut self, rpm: RpmValue) {
+        if self.baseline_samples < BASELINE_SAMPLES {
+            self.baseline_sum += rpm as f64;
+            self.baseline_samples += 1;
+            return;
+        }
+
+        self.recent = match self.recent_samples {
+            0 => rpm as f64,
+            _ => self.recent + RECENT_WEIGHT * (rpm as f64 - self.recent),
+        };
+        self.recent_samples += 1;
+    }
+
+    fn baseline(&self) -> Option<f64> {
+        (self.baseline_samples >= BASELINE_SAMPLES)
+            .then(|| self.baseline_sum / self.baseline_samples as f64)
+    }
+
+    fn recent(&self) -> Option<f64> {
+        (self.recent_samples >= RECENT_SAMPLES).then_some(self.recent)
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FanHealth {
+    #[serde(skip)]
+    buckets: [Bucket; BUCKETS],
+    #[serde(skip)]
+    last_pwm: Option<PwmValue>,
+    #[serde(skip)]
+    spinning_seen: bool,
+    #[serde(skip)]
+    /// Since when the fan reports 0 RPM at a high PWM
+    stopped_since: Option<Instant>,
+    pub issue: Option<FanIssue>,
+}
+
+impl FanHealth {
+    pub fn update(&mut self, fan: &FanStatus) {
+        let settled = self.last_pwm == Some(fan.pwm);
+        self.last_pwm = Some(fan.pwm);
+
+        if fan.rpm > 0 {
+            self.spinning_seen = true;
+            self.stopped_since = None;
+        } else if fan.pwm >= STALL_PWM {
+            self.stopped_since.get_or_insert_with(Instant::now);
+        } else {
+            self.stopped_since = None;
+        }
+
+        if self
+            .stopped_since
+            .is_some_and(|since| since.elapsed() >= STALL_TIME)
+        {
+            self.issue = Some(match self.spinning_seen {
+                true => FanIssue::Stall,
+                false => FanIssue::TachDisconnected,
+            });
+            return;
+        }
+
+        let index = (fan.pwm / BUCKET_WIDTH) as usize;
+        if settled && fan.pwm > 0 && fan.rpm > 0 {
+            self.buckets[index].add(fan.rpm);
+        }
+
+        let bucket = &self.buckets[index];
+        self.issue = match (bucket.baseline(), bucket.recent()) {
+            (Some(expected), Some(actual)) if actual < expected * (1.0 - DEGRADED_RATIO) => {
+                Some(FanIssue::Degraded {
+                    pwm: fan.pwm,
+                    expected: expected as RpmValue,
+                    actual: actual as RpmValue,
+                })
+            }
+            _ => None,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn update(health: &mut FanHealth, pwm: PwmValue, rpm: RpmValue) {
+        health.update(&FanStatus { pwm, rpm });
+    }
+
+    /// Pretends the fan has reported 0 RPM for longer than the stall time
+    fn expire_stall_time(health: &mut FanHealth) {
+        health.stopped_since = Instant::now().checked_sub(STALL_TIME + Duration::from_secs(1));
+    }
+
+    #[test]
+    fn buckets_cover_the_pwm_range() {
+        assert_eq!(BUCKETS, 8);
+        assert_eq!((31 / BUCKET_WIDTH) as usize, 0);
+        assert_eq!((32 / BUCKET_WIDTH) as usize, 1);
+        assert_eq!((PwmValue::MAX / BUCKET_WIDTH) as usize, BUCKETS - 1);
+    }
+
+    #[test]
+    fn learns_baseline_from_settled_samples_only() {
+        let mut health = FanHealth::default();
+        // Every sample follows a PWM change
+        for i in 0..100 {
+            update(&mut health, 150 + i % 2, 3000);
+        }
+        assert_eq!(health.buckets[4].baseline_samples, 0);
+
+        update(&mut health, 150, 3000);
+        update(&mut health, 150, 3000);
+        assert_eq!(health.buckets[4].baseline_samples, 1);
+    }
+
+    #[test]
+    fn detects_degradation_against_the_baseline() {
+        let mut health = FanHealth::default();
+        for _ in 0..=BASELINE_SAMPLES {
+            update(&mut health, 200, 4000);
+        }
+        for _ in 0..RECENT_SAMPLES {
+            update(&mut health, 200, 3500);
+            assert_eq!(health.issue, None);
+        }
+        for _ in 0..30 {
+            update(&mut health, 200, 3000);
+        }
+        assert!(matches!(
+            health.issue,
+            Some(FanIssue::Degraded {
+                pwm: 200,
+                expected: 4000,
+                actual: 3000..3400,
+            })
+        ));
+
+        // Other buckets have their own baseline
+        update(&mut health, 100, 2000);
+        assert_eq!(health.issue, None);
+    }
+
+    #[test]
+    fn stall_after_spinning() {
+        let mut health = FanHealth::default();
+        update(&mut health, 150, 3000);
+        update(&mut health, 150, 0);
+        assert_eq!(health.issue, None);
+
+        expire_stall_time(&mut health);
+        update(&mut health, 150, 0);
+        assert_eq!(health.issue, Some(FanIssue::Stall));
+
+        update(&mut health, 150, 2900);
+        assert_eq!(health.issue, None);
+    }
+
+    #[test]
+    fn tach_disconnected_without_any_rpm() {
+        let mut health = FanHealth::default();
+        update(&mut health, 255, 0);
+        expire_stall_time(&mut health);
+        update(&mut health, 255, 0);
+        assert_eq!(health.issue, Some(FanIssue::TachDisconnected));
+    }
+
+    #[test]
+    fn stopped_fan_at_low_pwm_is_fine() {
+        let mut health = FanHealth::default();
+        update(&mut health, STALL_PWM - 1, 0);
+        assert_eq!(health.stopped_since, None);
+        update(&mut health, 0, 0);
+        assert_eq!(health.issue, None);
+    }
+}
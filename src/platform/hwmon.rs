@@ -0,0 +1,298 @@
+//! Generic discovery of the hwmon devices under `/sys/class/hwmon`
+//!
+//! Devices are matched by the content of their `name` file
+//! (`pwmfan`, `rp1_adc`, `cpu_thermal`, ...) instead of the hwmon index,
+//! which is not stable between boots.
+//!
+//! ## Supported channels
+//!
+//! | File          | Kind  | Raw unit    | Value unit |
+//! | ------------- | ----- | ----------- | ---------- |
+//! | `fanN_input`  | Fan   | RPM         | RPM        |
+//! | `pwmN`        | Pwm   | 0-255       | 0-255      |
+//! | `tempN_input` | Temp  | millidegree | °C         |
+//! | `inN_input`   | In    | millivolt   | V          |
+//! | `currN_input` | Curr  | milliamp    | A          |
+//! | `powerN_input`| Power | microwatt   | W          |
+//!
+//! Optional `<kind>N_label` files are used as channel labels.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    platform::{read_sysfs_dir, read_trimmed},
+    Error, Result,
+};
+
+const HWMON_PATH: &str = "/sys/class/hwmon";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum ChannelKind {
+    Fan,
+    Pwm,
+    Temp,
+    In,
+    Curr,
+    Power,
+}
+
+impl ChannelKind {
+    pub fn as_str(&self) -> &str {
+        match self {
+            ChannelKind::Fan => "fan",
+            ChannelKind::Pwm => "pwm",
+            ChannelKind::Temp => "temp",
+            ChannelKind::In => "in",
+            ChannelKind::Curr => "curr",
+            ChannelKind::Power => "power",
+        }
+    }
+
+    pub fn unit(&self) -> &str {
+        match self {
+            ChannelKind::Fan => "RPM",
+            ChannelKind::Pwm => "",
+            ChannelKind::Temp => "°C",
+            ChannelKind::In => "V",
+            ChannelKind::Curr => "A",
+            ChannelKind::Power => "W",
+        }
+    }
+
+    fn scale(&self) -> f64 {
+        match self {
+            ChannelKind::Fan | ChannelKind::Pwm => 1.0,
+            ChannelKind::Temp | ChannelKind::In | ChannelKind::Curr => 1_000.0,
+            ChannelKind::Power => 1_000_000.0,
+        }
+    }
+
+    /// Splits the value file name like `fan1_input` or `pwm1` into kind and index
+    fn from_file_name(name: &str) -> Option<(Self, u32)> {
+        use ChannelKind::*;
+
+        let (kind, index) = match name.strip_suffix("_input") {
+            Some(name) => [Fan, Temp, In, Curr, Power]
+                .into_iter()
+                .find_map(|kind| Some((kind, name.strip_prefix(kind.as_str())?)))?,
+            None => (Pwm, name.strip_prefix(Pwm.as_str())?),
+        };
+
+        Some((kind, index.parse().ok()?))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Channel {
+    pub kind: ChannelKind,
+    pub index: u32,
+    pub label: Option<String>,
+    pub value: f64,
+}
+
+impl Channel {
+    /// Label of the channel if present, otherwise the file prefix like `temp1`
+    pub fn name(&self) -> String {
+        match &self.label {
+            Some(label) => label.clone(),
+            None => format!("{}{}", self.kind.as_str(), self.index),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HwmonDevice {
+    pub name: String,
+    pub path: PathBuf,
+    pub channels: Vec<Channel>,
+}
+
+impl HwmonDevice {
+    pub fn read(path: &Path) -> Result<Self> {
+        let name = read_trimmed(&path.join("name"))?;
+        let mut channels = Vec::new();
+
+        for entry in fs::read_dir(path)? {
+            let file_name = entry?.file_name();
+            let Some((kind, index)) = file_name.to_str().and_then(ChannelKind::from_file_name)
+            else {
+                continue;
+            };
+
+            // Disconnected sensors may fail on read, they are just skipped
+            let Ok(raw) = read_trimmed(&path.join(&file_name)) else {
+                continue;
+            };
+            let Ok(raw) = raw.parse::<f64>() else {
+                continue;
+            };
+
+            let label = read_trimmed(&path.join(format!("{}{index}_label", kind.as_str()))).ok();
+            channels.push(Channel {
+                kind,
+                index,
+                label,
+                value: raw / kind.scale(),
+            });
+        }
+
+        channels.sort_unstable_by_key(|channel| (channel.kind, channel.index));
+
+        Ok(Self {
+            name,
+            path: path.to_owned(),
+            channels,
+        })
+    }
+
+    /// Name of the underlying device, e.g. `nvme0` for the `nvme` hwmon device
+    pub fn device_name(&self) -> Option<String> {
+        let device = fs::read_link(self.path.join("device")).ok()?;
+        Some(device.file_name()?.to_string_lossy().into_owned())
+    }
+
+    pub fn channel(&self, kind: ChannelKind, index: u32) -> Option<&Channel> {
+        self.channels
+            .iter()
+            .find(|channel| channel.kind == kind && channel.index == index)
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Hwmon {
+    pub devices: Vec<HwmonDevice>,
+}
+
+impl Hwmon {
+    pub fn scan() -> Result<Self> {
+        Self::scan_from(HWMON_PATH)
+    }
+
+    fn scan_from(root: &str) -> Result<Self> {
+        let mut paths = read_sysfs_dir(root)?
+            .map(|entry| Ok(entry?.path()))
+            .collect::<Result<Vec<_>>>()?;
+        paths.sort_unstable();
+
+        let mut devices = paths
+            .iter()
+            .filter_map(|path| HwmonDevice::read(path).ok())
+            .collect::<Vec<_>>();
+        devices.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(Self { devices })
+    }
+
+    /// First device with the given `name`
+    pub fn find(&self, name: &str) -> Result<&HwmonDevice> {
+        self.devices
+            .iter()
+            .find(|device| device.name == name)
+            .ok_or(Error::Unavailable(format!("no {name} hwmon device")))
+    }
+
+    /// All devices with the given `name`, e.g. every NVMe drive
+    pub fn find_all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a HwmonDevice> {
+        self.devices
+            .iter()
+            .filter(move |device| device.name == name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::fixture;
+
+    #[test]
+    fn splits_file_names() {
+        assert_eq!(
+            ChannelKind::from_file_name("temp1_input"),
+            Some((ChannelKind::Temp, 1))
+        );
+        assert_eq!(
+            ChannelKind::from_file_name("in0_input"),
+            Some((ChannelKind::In, 0))
+        );
+        assert_eq!(
+            ChannelKind::from_file_name("power12_input"),
+            Some((ChannelKind::Power, 12))
+        );
+        assert_eq!(
+            ChannelKind::from_file_name("pwm1"),
+            Some((ChannelKind::Pwm, 1))
+        );
+        for name in [
+            "name",
+            "temp1_crit",
+            "in0_label",
+            "pwm1_enable",
+            "fan_input",
+            "uevent",
+        ] {
+            assert_eq!(ChannelKind::from_file_name(name), None, "{name}");
+        }
+    }
+
+    #[test]
+    fn scans_devices_by_name() {
+        let root = fixture(
+            "hwmon",
+            &[
+                ("hwmon0/name", "rp1_adc\n"),
+                ("hwmon0/in0_input", "3300\n"),
+                ("hwmon0/in0_label", "vdd\n"),
+                ("hwmon0/in1_input", "n/a\n"),
+                ("hwmon0/in0_min", "3000\n"),
+                ("hwmon1/name", "cpu_thermal\n"),
+                ("hwmon1/temp1_input", "52150\n"),
+                ("hwmon1/temp1_crit", "110000\n"),
+                ("hwmon1/uevent", "OF_NAME=thermal\n"),
+                ("hwmon2/name", "pwmfan\n"),
+                ("hwmon2/fan1_input", "2400\n"),
+                ("hwmon2/pwm1", "128\n"),
+                ("hwmon2/pwm1_enable", "1\n"),
+                ("hwmon3/temp1_input", "40000\n"),
+            ],
+        );
+        let result = Hwmon::scan_from(root.to_str().unwrap());
+        fs::remove_dir_all(&root).unwrap();
+
+        let hwmon = result.unwrap();
+        let names = hwmon
+            .devices
+            .iter()
+            .map(|device| device.name.as_str())
+            .collect::<Vec<_>>();
+        // The device without a name is left out
+        assert_eq!(names, ["cpu_thermal", "pwmfan", "rp1_adc"]);
+
+        let cpu = hwmon.find("cpu_thermal").unwrap();
+        assert_eq!(cpu.channels.len(), 1);
+        assert_eq!(cpu.channel(ChannelKind::Temp, 1).unwrap().value, 52.15);
+
+        let fan = hwmon.find("pwmfan").unwrap();
+        assert_eq!(fan.channels.len(), 2);
+        assert_eq!(fan.channel(ChannelKind::Fan, 1).unwrap().value, 2400.0);
+        assert_eq!(fan.channel(ChannelKind::Pwm, 1).unwrap().value, 128.0);
+
+        // The unreadable value is skipped
+        let adc = hwmon.find("rp1_adc").unwrap();
+        assert_eq!(adc.channels.len(), 1);
+        let vdd = adc.channel(ChannelKind::In, 0).unwrap();
+        assert_eq!(vdd.value, 3.3);
+        assert_eq!(vdd.name(), "vdd");
+
+        assert!(matches!(hwmon.find("nvme"), Err(Error::Unavailable(_))));
+        assert!(matches!(
+            Hwmon::scan_from("/nonexistent/hwmon"),
+            Err(Error::Unavailable(_))
+        ));
+    }
+}
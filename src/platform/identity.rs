@@ -0,0 +1,135 @@
+//! Board identity and firmware info, read once at startup
+//!
+//! Tells the boards apart in the UI header, snapshots and recordings.
+//! Every field is read on its own, a missing one is just left empty.
+
+use std::{
+    fs,
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{
+    platform::{
+        command::{Arg, Vcgencmd},
+        read_trimmed,
+    },
+    Result,
+};
+
+const MODEL_PATH: &str = "/proc/device-tree/model";
+const SERIAL_PATH: &str = "/proc/device-tree/serial-number";
+const HOSTNAME_PATH: &str = "/proc/sys/kernel/hostname";
+const KERNEL_PATH: &str = "/proc/sys/kernel/osrelease";
+const UPTIME_PATH: &str = "/proc/uptime";
+/// `config.txt` settings worth knowing when comparing boards
+const CONFIG_HIGHLIGHTS: [&str; 3] = ["arm_freq", "over_voltage", "temp_limit"];
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BoardInfo {
+    pub model: Option<String>,
+    pub serial: Option<String>,
+    pub hostname: Option<String>,
+    /// Firmware build date and version hash from `vcgencmd version`
+    pub firmware: Option<String>,
+    pub kernel: Option<String>,
+    /// `config.txt` highlights from `vcgencmd get_config`
+    pub config: Vec<(String, String)>,
+    #[serde(
+        rename = "uptime_s",
+        serialize_with = "serialize_uptime",
+        deserialize_with = "deserialize_uptime"
+    )]
+    uptime: Option<(Duration, Instant)>,
+}
+
+impl BoardInfo {
+    pub fn read() -> Self {
+        Self {
+            model: read_device_tree(MODEL_PATH).ok(),
+            serial: read_device_tree(SERIAL_PATH).ok(),
+            hostname: read_trimmed(HOSTNAME_PATH.as_ref()).ok(),
+            firmware: firmware().ok(),
+            kernel: read_trimmed(KERNEL_PATH.as_ref()).ok(),
+            config: CONFIG_HIGHLIGHTS
+                .iter()
+                .filter_map(|name| Some((name.to_string(), config(name).ok()?)))
+                .collect(),
+            uptime: uptime().ok().map(|uptime| (uptime, Instant::now())),
+        }
+    }
+
+    /// Uptime of the board, kept running since the startup
+    pub fn uptime(&self) -> Option<Duration> {
+        self.uptime
+            .map(|(uptime, read_at)| uptime + read_at.elapsed())
+    }
+}
+
+/// Device tree strings are NUL terminated
+fn read_device_tree(path: &str) -> Result<String> {
+    let value = fs::read(path)?;
+    Ok(String::from_utf8(value)?
+        .trim_end_matches('\0')
+        .trim()
+        .to_owned())
+}
+
+/// Compacts the `vcgencmd version` output
+///
+/// ```text
+/// Mar 17 2025 10:50:39
+/// Copyright (c) 2012 Broadcom
+/// version 2bee7a3b8e8bdd1b8d2f4e2fa7d1c0e1a5e0b6b8 (clean) (release) (start)
+/// ```
+fn firmware() -> Result<String> {
+    let output = Vcgencmd::run(&[Arg::Version.as_str()])?;
+    let mut lines = output.lines().map(str::trim);
+    let date = lines.next().unwrap_or_default();
+    let version = lines
+        .find_map(|line| line.strip_prefix("version "))
+        .and_then(|version| version.split_ascii_whitespace().next())
+        .unwrap_or_default();
+    Ok(format!("{date} {version}").trim().to_owned())
+}
+
+/// Value of the `config.txt` setting from `vcgencmd get_config <name>` printing `name=value`
+fn config(name: &str) -> Result<String> {
+    let output = Vcgencmd::run(&[Arg::GetConfig.as_str(), name])?;
+    Ok(output
+        .trim()
+        .split_once('=')
+        .map_or(output.trim(), |(_, value)| value)
+        .to_owned())
+}
+
+/// Uptime in whole seconds at the time of serialization
+fn serialize_uptime<S: Serializer>(
+    uptime: &Option<(Duration, Instant)>,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    uptime
+        .map(|(uptime, read_at)| (uptime + read_at.elapsed()).as_secs())
+        .serialize(serializer)
+}
+
+/// Uptime in seconds counted on from the time of deserialization
+fn deserialize_uptime<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<Option<(Duration, Instant)>, D::Error> {
+    Ok(Option::<u64>::deserialize(deserializer)?
+        .map(|uptime| (Duration::from_secs(uptime), Instant::now())))
+}
+
+/// Seconds since boot, the first value of `/proc/uptime`
+fn uptime() -> Result<Duration> {
+    let uptime = read_trimmed(UPTIME_PATH.as_ref())?;
+    let seconds = uptime
+        .split_ascii_whitespace()
+        .next()
+        .unwrap_or_default()
+        .parse::<f64>()?;
+    Ok(Duration::from_secs_f64(seconds))
+}
@@ -1,25 +1,179 @@
+mod boot_config;
 mod command;
 mod cpu;
+mod energy;
 mod fan;
+mod fan_control;
+mod fan_health;
+mod hwmon;
+mod identity;
+mod network;
 mod power;
+mod processes;
+mod psu;
+mod sensor;
+mod soc;
+mod storage;
+mod system;
+mod thermal;
+
+pub use boot_config::{BootConfig, FirmwareConfig, OVERCLOCK_SETTINGS};
+pub use cpu::{ClockMhz, TempValue};
+pub use energy::{EnergyMeter, EnergyState};
+pub use fan::{FanStatus, PwmValue, RpmValue};
+pub use fan_control::{FanController, FanCurve, ManualFan};
+pub use fan_health::FanHealth;
+pub use hwmon::{ChannelKind, Hwmon};
+pub use identity::BoardInfo;
+pub use power::{InnerThrottleStatus, ThrottleStatus, Watt};
+pub use psu::{PsuAnalysis, PsuVerdict};
+pub use sensor::{Sensor, SensorState};
+pub use thermal::Thermal;
+
+use std::{fs, io, path::Path};
+
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    platform::{cpu::CpuStatus, fan::FanStatus, power::PowerStatus},
-    Result,
+    platform::{
+        cpu::CpuStatus, network::NetworkStatus, power::PowerStatus, processes::ProcessStatus,
+        soc::SocStatus, storage::StorageStatus, system::SystemStatus,
+    },
+    Error, Result,
 };
 
-#[derive(Default)]
+/// Name, state and error counter of a source
+pub type SourceState<'a> = (&'static str, &'a SensorState, u64);
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Rpi {
+    pub board: BoardInfo,
     pub cpu: CpuStatus,
-    pub fan: FanStatus,
+    pub soc: SocStatus,
+    pub fan: Sensor<FanStatus>,
+    pub fan_health: FanHealth,
+    pub hwmon: Sensor<Hwmon>,
     pub power: PowerStatus,
+    pub psu: PsuAnalysis,
+    pub energy: EnergyMeter,
+    pub thermal: Sensor<Thermal>,
+    pub system: SystemStatus,
+    pub processes: ProcessStatus,
+    pub storage: StorageStatus,
+    pub network: NetworkStatus,
 }
 
 impl Rpi {
-    pub fn update(&mut self) -> Result<()> {
-        self.cpu = CpuStatus::new()?;
-        self.fan = FanStatus::new()?;
-        self.power = PowerStatus::new()?;
-        Ok(())
+    /// Status with the board identity read, the sources are empty until the first update
+    pub fn new() -> Self {
+        Self {
+            board: BoardInfo::read(),
+            psu: PsuAnalysis::new(),
+            energy: EnergyMeter::new(),
+            ..Default::default()
+        }
+    }
+
+    /// Refreshes every source independently, a failing source
+    /// keeps its last good value and does not stop the others.
+    pub fn update(&mut self) {
+        self.cpu.update();
+        self.soc.update();
+        self.hwmon.update(Hwmon::scan());
+        self.fan.update(match self.hwmon.current() {
+            Some(hwmon) => FanStatus::new(hwmon),
+            None => Err(Error::Unavailable(String::from("no hwmon devices"))),
+        });
+        if let Some(fan) = self.fan.current() {
+            self.fan_health.update(fan);
+        }
+        self.power.update();
+        self.psu.update(&self.power);
+        self.energy.update(&self.power);
+        self.thermal.update(Thermal::read());
+        self.system.update();
+        self.processes.update();
+        self.storage.update(self.hwmon.current());
+        self.network.update();
+    }
+
+    /// State of the sources shown in the sources line and logged by the headless modes
+    pub fn sources(&self) -> [SourceState<'_>; 19] {
+        fn sensor<'a, T>(name: &'static str, sensor: &'a Sensor<T>) -> SourceState<'a> {
+            (name, &sensor.state, sensor.errors)
+        }
+        [
+            sensor("temp", &self.cpu.temp),
+            sensor("clock", &self.cpu.clock),
+            sensor("soc clocks", &self.soc.clocks),
+            sensor("volts", &self.soc.volts),
+            sensor("gpu memory", &self.soc.memory),
+            sensor("ring osc", &self.soc.ring_osc),
+            sensor("fan", &self.fan),
+            sensor("hwmon", &self.hwmon),
+            sensor("throttle", &self.power.throttle),
+            sensor("power", &self.power.power),
+            sensor("thermal", &self.thermal),
+            sensor("cpu load", &self.system.usage),
+            sensor("load", &self.system.load),
+            sensor("memory", &self.system.memory),
+            sensor("pressure", &self.system.pressure),
+            sensor("processes", &self.processes.processes),
+            sensor("disk io", &self.storage.io),
+            sensor("nvme", &self.storage.nvme),
+            sensor("network", &self.network.interfaces),
+        ]
+    }
+}
+
+/// Reads a sysfs attribute without the trailing newline
+fn read_trimmed(path: &Path) -> Result<String> {
+    Ok(fs::read_to_string(path)?.trim().to_owned())
+}
+
+/// Lists a sysfs directory, a missing directory means the source is unavailable
+fn read_sysfs_dir(path: &str) -> Result<fs::ReadDir> {
+    match fs::read_dir(path) {
+        Ok(entries) => Ok(entries),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            Err(Error::Unavailable(format!("no {path}")))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Sysfs-like tree of `(path, content)` files in a fresh temporary directory
+#[cfg(test)]
+pub(crate) fn fixture(name: &str, files: &[(&str, &str)]) -> std::path::PathBuf {
+    let root = std::env::temp_dir().join(format!("pi-stats-{name}-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(&root).unwrap();
+    for (path, content) in files {
+        let path = root.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+    root
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deserializes_snapshots_with_missing_fields() {
+        let mut snapshot = serde_json::to_value(Rpi::default()).unwrap();
+        snapshot.as_object_mut().unwrap().remove("network");
+        snapshot["soc"].as_object_mut().unwrap().remove("memory");
+        snapshot["cpu"]["temp"]
+            .as_object_mut()
+            .unwrap()
+            .remove("errors");
+
+        let rpi: Rpi = serde_json::from_value(snapshot).unwrap();
+        assert_eq!(rpi.cpu.temp.errors, 0);
+        assert!(rpi.network.interfaces.value().is_none());
+        assert!(rpi.soc.memory.value().is_none());
     }
 }
@@ -0,0 +1,226 @@
+//! Per interface network throughput and error counters from `/proc/net/dev`
+//!
+//! Ethernet and Wi-Fi traffic noticeably adds to the power draw of the board.
+
+use std::{collections::HashMap, fs, time::Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    platform::{sensor::Sensor, storage::BytesPerSecond},
+    Error, Result,
+};
+
+const NET_DEV_PATH: &str = "/proc/net/dev";
+const LOOPBACK: &str = "lo";
+
+/// Cumulative counters of an interface since boot
+#[derive(Debug, Default, Clone, Copy)]
+struct Counters {
+    rx_bytes: u64,
+    rx_errors: u64,
+    rx_dropped: u64,
+    tx_bytes: u64,
+    tx_errors: u64,
+    tx_dropped: u64,
+}
+
+impl Counters {
+    /// Parses the fields after `iface:`,
+    /// 8 receive fields (bytes packets errs drop fifo frame compressed multicast) then transmit ones
+    fn parse(fields: &str) -> Result<Self> {
+        let fields = fields
+            .split_ascii_whitespace()
+            .map(|field| field.parse::<u64>())
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        if fields.len() < 12 {
+            return Err(Error::ParseCommand(format!(
+                "unexpected {NET_DEV_PATH} fields {fields:?}"
+            )));
+        }
+
+        Ok(Self {
+            rx_bytes: fields[0],
+            rx_errors: fields[2],
+            rx_dropped: fields[3],
+            tx_bytes: fields[8],
+            tx_errors: fields[10],
+            tx_dropped: fields[11],
+        })
+    }
+}
+
+fn read_net_dev(path: &str) -> Result<HashMap<String, Counters>> {
+    fs::read_to_string(path)?
+        .lines()
+        // Two header lines, then `iface: counters`
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, fields)| (name.trim(), fields))
+        .filter(|(name, _)| *name != LOOPBACK)
+        .map(|(name, fields)| Ok((name.to_owned(), Counters::parse(fields)?)))
+        .collect()
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NetworkInterface {
+    pub name: String,
+    pub rx: BytesPerSecond,
+    pub tx: BytesPerSecond,
+    pub rx_errors: u64,
+    pub tx_errors: u64,
+    pub rx_dropped: u64,
+    pub tx_dropped: u64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetworkStatus {
+    pub interfaces: Sensor<Vec<NetworkInterface>>,
+    #[serde(skip)]
+    previous: Option<(Instant, HashMap<String, Counters>)>,
+}
+
+impl NetworkStatus {
+    pub fn update(&mut self) {
+        let interfaces =
+            read_net_dev(NET_DEV_PATH).and_then(|counters| self.rates(Instant::now(), counters));
+        self.interfaces.update(interfaces);
+    }
+
+    /// Rates since the previous update, unavailable on the first one
+    fn rates(
+        &mut self,
+        now: Instant,
+        counters: HashMap<String, Counters>,
+    ) -> Result<Vec<NetworkInterface>> {
+        let previous = self.previous.replace((now, counters));
+        let (Some((previous_time, previous)), Some((_, counters))) = (previous, &self.previous)
+        else {
+            return Err(Error::Unavailable(String::from(
+                "no previous network counters yet",
+            )));
+        };
+
+        let elapsed = now.duration_since(previous_time).as_secs_f64();
+        let rate = |current: u64, previous: u64| current.saturating_sub(previous) as f64 / elapsed;
+        let mut interfaces = counters
+            .iter()
+            .filter_map(|(name, counters)| {
+                let previous = previous.get(name)?;
+                Some(NetworkInterface {
+                    name: name.clone(),
+                    rx: rate(counters.rx_bytes, previous.rx_bytes),
+                    tx: rate(counters.tx_bytes, previous.tx_bytes),
+                    rx_errors: counters.rx_errors,
+                    tx_errors: counters.tx_errors,
+                    rx_dropped: counters.rx_dropped,
+                    tx_dropped: counters.tx_dropped,
+                })
+            })
+            .collect::<Vec<_>>();
+        interfaces.sort_unstable_by(|a, b| a.name.cmp(&b.name));
+        Ok(interfaces)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::platform::fixture;
+
+    const NET_DEV: &str = "\
+Inter-|   Receive                                                |  Transmit
+ face |bytes    packets errs drop fifo frame compressed multicast|bytes    packets errs drop fifo colls carrier compressed
+    lo:  123456     100    0    0    0     0          0         0   123456     100    0    0    0     0       0          0
+  eth0: 9876543    7000    2    5    0     0          0        10  1234567    5000    1    3    0     0       0          0
+ wlan0:       0       0    0    0    0     0          0         0        0       0    0    0    0     0       0          0
+";
+
+    fn counters(rx_bytes: u64, tx_bytes: u64) -> Counters {
+        Counters {
+            rx_bytes,
+            tx_bytes,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn reads_the_interfaces() {
+        let root = fixture("net-dev", &[("dev", NET_DEV)]);
+        let result = read_net_dev(root.join("dev").to_str().unwrap());
+        fs::remove_dir_all(&root).unwrap();
+
+        let interfaces = result.unwrap();
+        assert_eq!(interfaces.len(), 2);
+        assert!(!interfaces.contains_key(LOOPBACK));
+        let eth0 = interfaces["eth0"];
+        assert_eq!(
+            (eth0.rx_bytes, eth0.rx_errors, eth0.rx_dropped),
+            (9876543, 2, 5)
+        );
+        assert_eq!(
+            (eth0.tx_bytes, eth0.tx_errors, eth0.tx_dropped),
+            (1234567, 1, 3)
+        );
+        assert_eq!(interfaces["wlan0"].rx_bytes, 0);
+    }
+
+    #[test]
+    fn rejects_malformed_counters() {
+        assert!(matches!(
+            Counters::parse(" 1 2 3 4 5 6 7 8 9 10 11"),
+            Err(Error::ParseCommand(_))
+        ));
+        assert!(matches!(
+            Counters::parse(" 1 2 3 4 5 6 7 8 9 10 11 x"),
+            Err(Error::ParseInt(_))
+        ));
+    }
+
+    #[test]
+    fn rates_from_the_previous_update() {
+        let start = Instant::now();
+        let mut network = NetworkStatus::default();
+        let first = network.rates(
+            start,
+            HashMap::from([
+                (String::from("eth0"), counters(1_000, 4_000_000_000)),
+                (String::from("usb0"), counters(10, 10)),
+            ]),
+        );
+        assert!(matches!(first, Err(Error::Unavailable(_))));
+
+        // eth0 received 4000 bytes in 2 s and its transmit counter wrapped,
+        // usb0 is gone and wlan0 came up
+        let interfaces = network
+            .rates(
+                start + Duration::from_secs(2),
+                HashMap::from([
+                    (String::from("eth0"), counters(5_000, 1_000)),
+                    (String::from("wlan0"), counters(100, 100)),
+                ]),
+            )
+            .unwrap();
+        assert_eq!(interfaces.len(), 1);
+        assert_eq!(interfaces[0].name, "eth0");
+        assert_eq!(interfaces[0].rx, 2_000.0);
+        assert_eq!(interfaces[0].tx, 0.0);
+
+        let interfaces = network
+            .rates(
+                start + Duration::from_secs(3),
+                HashMap::from([
+                    (String::from("eth0"), counters(5_000, 3_000)),
+                    (String::from("wlan0"), counters(600, 100)),
+                ]),
+            )
+            .unwrap();
+        let rates = interfaces
+            .iter()
+            .map(|interface| (interface.name.as_str(), interface.rx, interface.tx))
+            .collect::<Vec<_>>();
+        assert_eq!(rates, [("eth0", 0.0, 2_000.0), ("wlan0", 500.0, 0.0)]);
+    }
+}
@@ -17,11 +17,17 @@
 
 use std::{collections::HashMap, str::FromStr};
 
+use serde::{Deserialize, Serialize};
+
 use crate::{
-    platform::command::{Arg, Vcgencmd},
-    Result,
+    platform::{
+        command::{Arg, Vcgencmd},
+        sensor::Sensor,
+    },
+    Error, Result,
 };
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
 pub struct InnerThrottleStatus {
     pub under_voltage: bool,
     pub arm_frequency_capped: bool,
@@ -29,7 +35,8 @@ pub struct InnerThrottleStatus {
     pub soft_temp_limit: bool,
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ThrottleStatus {
     pub current: InnerThrottleStatus,
     pub happened: InnerThrottleStatus,
@@ -84,14 +91,15 @@ pub type Watt = f32;
 pub type Volt = f32;
 pub type Amp = f32;
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct PowerMeasure {
     pub measure: String,
     pub volts: Volt,
     pub amps: Amp,
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Power {
     pub power_map: Vec<PowerMeasure>,
     pub total_power: Watt,
@@ -100,25 +108,28 @@ pub struct Power {
 impl Power {
     pub fn new() -> Result<Self> {
         //FIXME Power map
-        //FIXME Error propogation
         let output = Vcgencmd::run(&[Arg::PmicReadAdc.as_str()])?;
         let mut measurements = HashMap::new();
 
         for line in output.lines() {
             let mut it = line.split_ascii_whitespace();
-            let key = it.next().expect("expected the name of the parameter");
-
-            let (key, value_type) = key
-                .trim()
-                .rsplit_once('_')
-                .expect("expected the values ended with suffix _A or _V");
-
-            let value = it
-                .next()
-                .expect("expected value")
-                .split('=')
-                .nth(1)
-                .expect("expected value after the name");
+            let Some(key) = it.next() else {
+                continue;
+            };
+
+            let (key, value_type) =
+                key.trim()
+                    .rsplit_once('_')
+                    .ok_or(Error::ParseCommand(format!(
+                        "expected the value {key} ended with suffix _A or _V"
+                    )))?;
+
+            let value =
+                it.next()
+                    .and_then(|value| value.split('=').nth(1))
+                    .ok_or(Error::ParseCommand(format!(
+                        "expected value after the name {key}"
+                    )))?;
 
             let entry = measurements.entry(key).or_insert(PowerMeasure {
                 measure: key.to_owned(),
@@ -127,19 +138,13 @@ impl Power {
             });
 
             match value_type {
-                "A" => {
-                    entry.amps = value
-                        .trim_end_matches('A')
-                        .parse()
-                        .expect("expected float number")
-                }
-                "V" => {
-                    entry.volts = value
-                        .trim_end_matches('V')
-                        .parse()
-                        .expect("expected float number")
+                "A" => entry.amps = value.trim_end_matches('A').parse()?,
+                "V" => entry.volts = value.trim_end_matches('V').parse()?,
+                _ => {
+                    return Err(Error::ParseCommand(format!(
+                        "unexpected value type {value_type} of {key}"
+                    )));
                 }
-                _ => unreachable!(),
             }
         }
 
@@ -158,17 +163,16 @@ impl Power {
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct PowerStatus {
-    pub throttle: ThrottleStatus,
-    pub power: Power,
+    pub throttle: Sensor<ThrottleStatus>,
+    pub power: Sensor<Power>,
 }
 
 impl PowerStatus {
-    pub fn new() -> Result<Self> {
-        Ok(Self {
-            throttle: ThrottleStatus::new()?,
-            power: Power::new()?,
-        })
+    pub fn update(&mut self) {
+        self.throttle.update(ThrottleStatus::new());
+        self.power.update(Power::new());
     }
 }
@@ -0,0 +1,165 @@
+//! Top processes by CPU share from `/proc/[pid]/stat`
+//!
+//! The share is the part of all CPU time (every core, busy and idle) a process used
+//! since the previous update, so the shares of all processes add up to the total
+//! CPU utilisation. The power estimate attributes the same part of the total power
+//! to the process, the remaining part is the idle and peripheral consumption.
+
+use std::{collections::HashMap, fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    platform::{
+        power::Watt,
+        read_sysfs_dir,
+        sensor::Sensor,
+        system::{read_total_jiffies, Percent},
+    },
+    Error, Result,
+};
+
+const PROC_PATH: &str = "/proc";
+/// Field indices after the `(comm)` of `/proc/[pid]/stat`
+const UTIME_FIELD: usize = 11;
+const STIME_FIELD: usize = 12;
+/// Processes kept of every update, the views list at most this many
+pub const MAX_PROCESSES: usize = 32;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Process {
+    pub pid: u32,
+    pub name: String,
+    pub cpu: Percent,
+}
+
+impl Process {
+    /// Part of the total power attributed to the process by its CPU share
+    pub fn power(&self, total: Watt) -> Watt {
+        total * self.cpu / 100.0
+    }
+}
+
+/// The busiest [`MAX_PROCESSES`] processes sorted by the CPU share, the busiest first
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Processes {
+    pub top: Vec<Process>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProcessStatus {
+    pub processes: Sensor<Processes>,
+    #[serde(skip)]
+    /// Total jiffies and jiffies of every process at the previous update
+    previous: Option<(u64, HashMap<u32, u64>)>,
+}
+
+impl ProcessStatus {
+    pub fn update(&mut self) {
+        let processes = self.read();
+        self.processes.update(processes);
+    }
+
+    fn read(&mut self) -> Result<Processes> {
+        let total = read_total_jiffies()?;
+        let mut names = HashMap::new();
+        let mut jiffies = HashMap::new();
+
+        for entry in read_sysfs_dir(PROC_PATH)? {
+            let Some(pid) = entry?.file_name().to_str().and_then(|pid| pid.parse().ok()) else {
+                continue;
+            };
+            // The process may exit in between
+            let Ok((name, process_jiffies)) = read_process(Path::new(PROC_PATH), pid) else {
+                continue;
+            };
+            names.insert(pid, name);
+            jiffies.insert(pid, process_jiffies);
+        }
+
+        let previous = self.previous.replace((total, jiffies));
+        let (Some((previous_total, previous)), Some((_, jiffies))) = (previous, &self.previous)
+        else {
+            return Err(Error::Unavailable(String::from(
+                "no previous process times yet",
+            )));
+        };
+        let elapsed = total.saturating_sub(previous_total).max(1);
+
+        let mut top = jiffies
+            .iter()
+            .filter_map(|(pid, jiffies)| {
+                // New processes are counted from their start
+                let used = jiffies.saturating_sub(previous.get(pid).copied().unwrap_or(0));
+                (used > 0).then(|| Process {
+                    pid: *pid,
+                    name: names.remove(pid).unwrap_or_default(),
+                    cpu: used as Percent * 100.0 / elapsed as Percent,
+                })
+            })
+            .collect::<Vec<_>>();
+        top.sort_unstable_by(|a, b| b.cpu.total_cmp(&a.cpu).then(a.pid.cmp(&b.pid)));
+        top.truncate(MAX_PROCESSES);
+
+        Ok(Processes { top })
+    }
+}
+
+/// Name and user plus system jiffies of the process
+fn read_process(proc: &Path, pid: u32) -> Result<(String, u64)> {
+    let stat = fs::read_to_string(proc.join(pid.to_string()).join("stat"))?;
+    // The name may contain spaces and parentheses, it ends at the last `)`
+    let (name, fields) = stat
+        .split_once('(')
+        .and_then(|(_, rest)| rest.rsplit_once(')'))
+        .ok_or(Error::ParseCommand(format!("unexpected /proc/{pid}/stat")))?;
+
+    let fields = fields.split_ascii_whitespace().collect::<Vec<_>>();
+    let field = |index: usize| -> Result<u64> {
+        Ok(fields
+            .get(index)
+            .ok_or(Error::ParseCommand(format!("unexpected /proc/{pid}/stat")))?
+            .parse()?)
+    };
+
+    Ok((name.to_owned(), field(UTIME_FIELD)? + field(STIME_FIELD)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::fixture;
+
+    #[test]
+    fn reads_the_process_times() {
+        let root = fixture(
+            "processes",
+            &[
+                (
+                    "1/stat",
+                    "1 (systemd) S 0 1 1 0 -1 4194560 50000 0 100 0 120 80 0 0 20 0 1 0 5 0 0\n",
+                ),
+                (
+                    "42/stat",
+                    "42 (tmux: server) (1)) R 1 42 42 0 -1 0 0 0 0 0 7 3 0 0 20 0 1 0 9 0 0\n",
+                ),
+                ("43/stat", "43 (broken R 1 43\n"),
+                ("44/stat", "44 (short) R 1 44 44\n"),
+            ],
+        );
+        let systemd = read_process(&root, 1);
+        let tmux = read_process(&root, 42);
+        let broken = read_process(&root, 43);
+        let short = read_process(&root, 44);
+        let missing = read_process(&root, 45);
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(systemd.unwrap(), (String::from("systemd"), 200));
+        assert_eq!(tmux.unwrap(), (String::from("tmux: server) (1)"), 10));
+        assert!(matches!(broken, Err(Error::ParseCommand(_))));
+        assert!(matches!(short, Err(Error::ParseCommand(_))));
+        assert!(matches!(missing, Err(Error::Io(_))));
+    }
+}
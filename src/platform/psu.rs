@@ -0,0 +1,240 @@
+//! Power supply adequacy from the `EXT5V` input rail and the under-voltage flags
+//!
+//! | Verdict    | Condition                                                                        |
+//! | ---------- | -------------------------------------------------------------------------------- |
+//! | Inadequate | Under-voltage now or since boot, or the input has dropped below 4.63 V           |
+//! | Marginal   | Input below 4.75 V (USB tolerance), sag above 0.25 V or current near the profile |
+//! | Adequate   | None of the above                                                                |
+//!
+//! The PMIC does not measure the input current, it is estimated from the total power
+//! of the PMIC rails and the input voltage, so it is a lower bound (USB devices,
+//! conversion losses and HATs are missing). The supply profile (3 A or 5 A) is the
+//! current negotiated by the firmware, read from the device tree.
+
+use std::fs;
+
+use serde::{Deserialize, Serialize};
+
+use crate::platform::power::{Amp, PowerStatus, Volt};
+
+pub const INPUT_RAIL: &str = "EXT5V";
+/// Current negotiated with the USB-C supply in mA, big endian, Raspberry Pi 5 only
+const MAX_CURRENT_PATH: &str = "/proc/device-tree/chosen/power/max_current";
+const DEFAULT_PROFILE: Amp = 3.0;
+/// Input voltage below which the firmware flags under-voltage
+const UNDER_VOLTAGE: Volt = 4.63;
+/// Lower limit of the 5 V ±5% USB tolerance
+const LOW_VOLTAGE: Volt = 4.75;
+const MAX_SAG: Volt = 0.25;
+/// Share of the supply profile current the draw may reach without a warning
+const CURRENT_MARGIN: f32 = 0.9;
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub enum PsuVerdict {
+    #[default]
+    Unknown,
+    Adequate,
+    Marginal(String),
+    Inadequate(String),
+}
+
+impl std::fmt::Display for PsuVerdict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PsuVerdict::Unknown => write!(f, "UNKNOWN"),
+            PsuVerdict::Adequate => write!(f, "ADEQUATE"),
+            PsuVerdict::Marginal(reason) => write!(f, "MARGINAL ({reason})"),
+            PsuVerdict::Inadequate(reason) => write!(f, "INADEQUATE ({reason})"),
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PsuAnalysis {
+    /// Current the supply is rated for, 3 A unless a 5 A supply has been negotiated
+    pub profile: Amp,
+    pub volts: Option<Volt>,
+    pub min_volts: Option<Volt>,
+    /// Highest input voltage seen, the reference for the sag
+    pub max_volts: Option<Volt>,
+    /// Estimated input current
+    pub amps: Option<Amp>,
+    pub peak_amps: Option<Amp>,
+    pub under_voltage: bool,
+    pub under_voltage_seen: bool,
+    pub verdict: PsuVerdict,
+}
+
+impl PsuAnalysis {
+    pub fn new() -> Self {
+        Self {
+            profile: read_profile().unwrap_or(DEFAULT_PROFILE),
+            ..Default::default()
+        }
+    }
+
+    /// Largest drop of the input voltage below the highest one seen
+    pub fn sag(&self) -> Option<Volt> {
+        Some(self.max_volts? - self.min_volts?)
+    }
+
+    pub fn update(&mut self, power: &PowerStatus) {
+        if let Some(throttle) = power.throttle.current() {
+            self.under_voltage = throttle.current.under_voltage;
+            self.under_voltage_seen |=
+                throttle.current.under_voltage || throttle.happened.under_voltage;
+        }
+
+        let input = power.power.current().and_then(|power| {
+            let rail = power
+                .power_map
+                .iter()
+                .find(|rail| rail.measure == INPUT_RAIL)?;
+            Some((rail.volts, power.total_power))
+        });
+        self.volts = input.map(|(volts, _)| volts);
+        self.amps = input.and_then(|(volts, watts)| (volts > 0.0).then(|| watts / volts));
+        if let Some((volts, _)) = input {
+            self.min_volts = Some(self.min_volts.map_or(volts, |min| min.min(volts)));
+            self.max_volts = Some(self.max_volts.map_or(volts, |max| max.max(volts)));
+        }
+        if let Some(amps) = self.amps {
+            self.peak_amps = Some(self.peak_amps.map_or(amps, |peak| peak.max(amps)));
+        }
+
+        self.verdict = self.verdict(power);
+    }
+
+    fn verdict(&self, power: &PowerStatus) -> PsuVerdict {
+        if self.under_voltage {
+            return PsuVerdict::Inadequate(String::from("under-voltage now"));
+        }
+        if self.under_voltage_seen {
+            return PsuVerdict::Inadequate(String::from("under-voltage since boot"));
+        }
+        if let Some(min) = self.min_volts.filter(|&min| min < UNDER_VOLTAGE) {
+            return PsuVerdict::Inadequate(format!("input dropped to {min:.2}V"));
+        }
+        if let Some(min) = self.min_volts.filter(|&min| min < LOW_VOLTAGE) {
+            return PsuVerdict::Marginal(format!("input dropped to {min:.2}V"));
+        }
+        if let Some(sag) = self.sag().filter(|&sag| sag > MAX_SAG) {
+            return PsuVerdict::Marginal(format!("{sag:.2}V sag under load"));
+        }
+        if let Some(peak) = self
+            .peak_amps
+            .filter(|&peak| peak > self.profile * CURRENT_MARGIN)
+        {
+            return PsuVerdict::Marginal(format!("{peak:.2}A of the {}A profile", self.profile));
+        }
+
+        match (power.throttle.value(), self.volts) {
+            (Some(_), Some(_)) => PsuVerdict::Adequate,
+            _ => PsuVerdict::Unknown,
+        }
+    }
+}
+
+/// Supply profile in amps from the device tree, a 3 A supply reports 3000 mA
+fn read_profile() -> Option<Amp> {
+    let value = fs::read(MAX_CURRENT_PATH).ok()?;
+    let milliamps = u32::from_be_bytes(value.get(..4)?.try_into().ok()?);
+    Some(milliamps as Amp / 1_000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::power::{Power, PowerMeasure, ThrottleStatus, Watt};
+
+    fn power(volts: Volt, watts: Watt, throttled: &str) -> PowerStatus {
+        let mut power = PowerStatus::default();
+        power
+            .throttle
+            .update(Ok(throttled.parse::<ThrottleStatus>().unwrap()));
+        power.power.update(Ok(Power {
+            power_map: vec![PowerMeasure {
+                measure: String::from(INPUT_RAIL),
+                volts,
+                amps: 0.0,
+            }],
+            total_power: watts,
+        }));
+        power
+    }
+
+    fn analyze(profile: Amp, readings: &[(Volt, Watt, &str)]) -> PsuVerdict {
+        let mut psu = PsuAnalysis {
+            profile,
+            ..Default::default()
+        };
+        for &(volts, watts, throttled) in readings {
+            psu.update(&power(volts, watts, throttled));
+        }
+        psu.verdict
+    }
+
+    fn is_marginal(verdict: &PsuVerdict, reason: &str) -> bool {
+        matches!(verdict, PsuVerdict::Marginal(r) if r.contains(reason))
+    }
+
+    fn is_inadequate(verdict: &PsuVerdict, reason: &str) -> bool {
+        matches!(verdict, PsuVerdict::Inadequate(r) if r.contains(reason))
+    }
+
+    #[test]
+    fn unknown_without_readings() {
+        let mut psu = PsuAnalysis::default();
+        psu.update(&PowerStatus::default());
+        assert_eq!(psu.verdict, PsuVerdict::Unknown);
+    }
+
+    #[test]
+    fn adequate_within_the_limits() {
+        assert_eq!(analyze(3.0, &[(5.1, 5.0, "0x0")]), PsuVerdict::Adequate);
+        // Above the USB tolerance with a small sag
+        assert_eq!(
+            analyze(3.0, &[(5.05, 5.0, "0x0"), (4.9, 10.0, "0x0")]),
+            PsuVerdict::Adequate
+        );
+    }
+
+    #[test]
+    fn judges_the_minimum_voltage() {
+        let verdict = analyze(3.0, &[(4.7, 5.0, "0x0")]);
+        assert!(is_marginal(&verdict, "4.70V"), "{verdict}");
+        let verdict = analyze(3.0, &[(4.6, 5.0, "0x0")]);
+        assert!(is_inadequate(&verdict, "4.60V"), "{verdict}");
+        // The minimum stays after the input recovers
+        let verdict = analyze(3.0, &[(4.6, 5.0, "0x0"), (5.1, 5.0, "0x0")]);
+        assert!(is_inadequate(&verdict, "4.60V"), "{verdict}");
+    }
+
+    #[test]
+    fn judges_the_sag_under_load() {
+        let verdict = analyze(3.0, &[(5.2, 3.0, "0x0"), (4.9, 12.0, "0x0")]);
+        assert!(is_marginal(&verdict, "sag"), "{verdict}");
+    }
+
+    #[test]
+    fn judges_the_draw_against_the_profile() {
+        // 14 W at 5 V is 2.8 A, above 90% of a 3 A supply and well within a 5 A one
+        let verdict = analyze(3.0, &[(5.0, 14.0, "0x0")]);
+        assert!(is_marginal(&verdict, "of the 3A profile"), "{verdict}");
+        assert_eq!(analyze(5.0, &[(5.0, 14.0, "0x0")]), PsuVerdict::Adequate);
+        let verdict = analyze(5.0, &[(5.0, 23.0, "0x0")]);
+        assert!(is_marginal(&verdict, "of the 5A profile"), "{verdict}");
+    }
+
+    #[test]
+    fn under_voltage_flags_are_inadequate() {
+        let verdict = analyze(5.0, &[(5.1, 5.0, "0x1")]);
+        assert!(is_inadequate(&verdict, "now"), "{verdict}");
+        let verdict = analyze(5.0, &[(5.1, 5.0, "0x10000")]);
+        assert!(is_inadequate(&verdict, "since boot"), "{verdict}");
+        // A past under-voltage is remembered once the flag clears
+        let verdict = analyze(5.0, &[(5.1, 5.0, "0x50005"), (5.1, 5.0, "0x0")]);
+        assert!(is_inadequate(&verdict, "since boot"), "{verdict}");
+    }
+}
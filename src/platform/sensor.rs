@@ -0,0 +1,119 @@
+//! Stateful wrapper around a single data source.
+//!
+//! Every source (a `vcgencmd` call, a sysfs file, ...) can fail on its own
+//! without taking down the rest of the [`Rpi`](crate::Rpi) status. A failed
+//! update keeps the last good value around together with its age, so the
+//! UI can show it as stale instead of dropping it.
+
+use std::time::{Duration, Instant};
+
+use serde::{ser::SerializeStruct, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{Error, Result};
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SensorState {
+    Ok,
+    /// Source is not present on this board (no fan, no `vcgencmd`, ...)
+    #[default]
+    Unavailable,
+    /// Last update failed with the given reason
+    Error(String),
+}
+
+#[derive(Debug)]
+pub struct Sensor<T> {
+    pub state: SensorState,
+    pub errors: u64,
+    value: Option<T>,
+    updated: Option<Instant>,
+}
+
+impl<T> Default for Sensor<T> {
+    fn default() -> Self {
+        Self {
+            state: SensorState::default(),
+            errors: 0,
+            value: None,
+            updated: None,
+        }
+    }
+}
+
+impl<T> Sensor<T> {
+    pub fn update(&mut self, result: Result<T>) {
+        match result {
+            Ok(value) => {
+                self.state = SensorState::Ok;
+                self.value = Some(value);
+                self.updated = Some(Instant::now());
+            }
+            Err(Error::Unavailable(_)) => self.state = SensorState::Unavailable,
+            Err(e) => {
+                self.state = SensorState::Error(e.to_string());
+                self.errors += 1;
+            }
+        }
+    }
+
+    /// Value of the latest update, `None` if it failed
+    pub fn current(&self) -> Option<&T> {
+        match self.state {
+            SensorState::Ok => self.value.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// Last good value, which may be stale - see [`Sensor::is_stale`]
+    pub fn value(&self) -> Option<&T> {
+        self.value.as_ref()
+    }
+
+    /// Time passed since the last successful update
+    pub fn age(&self) -> Option<Duration> {
+        self.updated.map(|updated| updated.elapsed())
+    }
+
+    pub fn is_stale(&self) -> bool {
+        self.state != SensorState::Ok && self.value.is_some()
+    }
+}
+
+/// State, error counter, last good value and its age in seconds
+impl<T: Serialize> Serialize for Sensor<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut sensor = serializer.serialize_struct("Sensor", 4)?;
+        sensor.serialize_field("state", &self.state)?;
+        sensor.serialize_field("errors", &self.errors)?;
+        sensor.serialize_field("value", &self.value)?;
+        sensor.serialize_field("age_s", &self.age().map(|age| age.as_secs_f64()))?;
+        sensor.end()
+    }
+}
+
+/// Counterpart of the serialization, the age is counted from the time of deserialization
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Sensor<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        struct Fields<T> {
+            #[serde(default)]
+            state: SensorState,
+            #[serde(default)]
+            errors: u64,
+            value: Option<T>,
+            age_s: Option<f64>,
+        }
+
+        let fields = Fields::deserialize(deserializer)?;
+        Ok(Self {
+            state: fields.state,
+            errors: fields.errors,
+            value: fields.value,
+            updated: fields
+                .age_s
+                .and_then(|age| Duration::try_from_secs_f64(age).ok())
+                .and_then(|age| Instant::now().checked_sub(age)),
+        })
+    }
+}
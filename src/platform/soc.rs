@@ -0,0 +1,264 @@
+//! SoC measurements from the firmware via `vcgencmd`
+//!
+//! | Command                    | Output                                    |
+//! | -------------------------- | ----------------------------------------- |
+//! | `measure_clock <clock>`    | `frequency(28)=200000000`                 |
+//! | `measure_volts <rail>`     | `volt=0.7200V`                            |
+//! | `get_mem arm`, `gpu`       | `arm=1020M`                               |
+//! | `read_ring_osc`            | `ring_osc(1)=8.812MHz (@0.8563V) (52.1'C)` |
+//!
+//! Clocks not present on a board are reported by the firmware as `0`.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    platform::{
+        command::{Arg, Vcgencmd},
+        cpu::{Clock, ClockMhz, TempValue},
+        power::Volt,
+        sensor::Sensor,
+    },
+    Error, Result,
+};
+
+pub type MiB = u32;
+
+/// Clocks measured besides the ARM and core clocks of [`CpuClock`](super::cpu::CpuClock)
+pub const CLOCKS: [Arg; 10] = [
+    Arg::H264,
+    Arg::Isp,
+    Arg::V3d,
+    Arg::Uart,
+    Arg::Pwm,
+    Arg::Emmc,
+    Arg::Pixel,
+    Arg::Vec,
+    Arg::Hdmi,
+    Arg::Dpi,
+];
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SocClocks {
+    pub clocks: Vec<(Arg, Clock)>,
+}
+
+impl SocClocks {
+    pub fn new() -> Result<Self> {
+        Ok(Self {
+            clocks: CLOCKS
+                .into_iter()
+                .map(|clock| Ok((clock, Clock::measure(clock)?)))
+                .collect::<Result<_>>()?,
+        })
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SocVolts {
+    pub core: Volt,
+    pub sdram_c: Volt,
+    pub sdram_i: Volt,
+    pub sdram_p: Volt,
+}
+
+impl SocVolts {
+    pub fn new() -> Result<Self> {
+        Ok(Self {
+            core: measure_volts(Arg::Core)?,
+            sdram_c: measure_volts(Arg::SdramC)?,
+            sdram_i: measure_volts(Arg::SdramI)?,
+            sdram_p: measure_volts(Arg::SdramP)?,
+        })
+    }
+
+    /// Rails with their `measure_volts` names
+    pub fn rails(&self) -> [(&'static str, Volt); 4] {
+        [
+            (Arg::Core.as_str(), self.core),
+            (Arg::SdramC.as_str(), self.sdram_c),
+            (Arg::SdramI.as_str(), self.sdram_i),
+            (Arg::SdramP.as_str(), self.sdram_p),
+        ]
+    }
+}
+
+fn measure_volts(rail: Arg) -> Result<Volt> {
+    parse_volts(
+        &Vcgencmd::run(&[Arg::MeasureVolts.as_str(), rail.as_str()])?,
+        rail,
+    )
+}
+
+/// Parses `vcgencmd measure_volts <rail>` printing `volt=0.7200V`
+fn parse_volts(output: &str, rail: Arg) -> Result<Volt> {
+    Ok(output
+        .trim()
+        .strip_prefix("volt=")
+        .and_then(|volts| volts.strip_suffix('V'))
+        .ok_or(Error::ParseCommand(format!(
+            "Failed to strip volt=..V of {} rail",
+            rail.as_str()
+        )))?
+        .parse()?)
+}
+
+/// Memory split between the ARM and the GPU
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MemorySplit {
+    pub arm: MiB,
+    pub gpu: MiB,
+}
+
+impl MemorySplit {
+    pub fn new() -> Result<Self> {
+        Ok(Self {
+            arm: get_mem(Arg::Arm)?,
+            gpu: get_mem(Arg::Gpu)?,
+        })
+    }
+}
+
+fn get_mem(memory: Arg) -> Result<MiB> {
+    parse_mem(
+        &Vcgencmd::run(&[Arg::GetMem.as_str(), memory.as_str()])?,
+        memory,
+    )
+}
+
+/// Parses `vcgencmd get_mem <arm|gpu>` printing `arm=1020M`
+fn parse_mem(output: &str, memory: Arg) -> Result<MiB> {
+    Ok(output
+        .trim()
+        .strip_prefix(memory.as_str())
+        .and_then(|output| output.strip_prefix('='))
+        .and_then(|output| output.strip_suffix('M'))
+        .ok_or(Error::ParseCommand(format!(
+            "unexpected get_mem output {output}"
+        )))?
+        .parse()?)
+}
+
+/// Ring oscillator of the SoC, its frequency drops with silicon ageing and lower voltage
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RingOscillator {
+    pub frequency: ClockMhz,
+    pub volts: Volt,
+    pub temp: TempValue,
+}
+
+impl RingOscillator {
+    pub fn new() -> Result<Self> {
+        Self::parse(&Vcgencmd::run(&[Arg::ReadRingOsc.as_str()])?)
+    }
+
+    /// Parses `ring_osc(1)=8.812MHz (@0.8563V) (52.1'C)`
+    fn parse(output: &str) -> Result<Self> {
+        let error = || Error::ParseCommand(format!("unexpected read_ring_osc output {output}"));
+
+        let (_, values) = output.trim().split_once('=').ok_or_else(error)?;
+        let mut it = values.split_ascii_whitespace();
+        let mut next = |prefix: &str, suffix: &str| {
+            it.next()
+                .and_then(|value| value.strip_prefix(prefix))
+                .and_then(|value| value.strip_suffix(suffix))
+                .ok_or_else(error)
+        };
+
+        Ok(Self {
+            frequency: next("", "MHz")?.parse()?,
+            volts: next("(@", "V)")?.parse()?,
+            temp: next("(", "'C)")?.parse()?,
+        })
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SocStatus {
+    pub clocks: Sensor<SocClocks>,
+    pub volts: Sensor<SocVolts>,
+    pub memory: Sensor<MemorySplit>,
+    pub ring_osc: Sensor<RingOscillator>,
+}
+
+impl SocStatus {
+    pub fn update(&mut self) {
+        self.clocks.update(SocClocks::new());
+        self.volts.update(SocVolts::new());
+        // The split is fixed at boot
+        if self.memory.value().is_none() {
+            self.memory.update(MemorySplit::new());
+        }
+        self.ring_osc.update(RingOscillator::new());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_volts() {
+        assert_eq!(parse_volts("volt=0.7200V\n", Arg::Core).unwrap(), 0.72);
+        assert_eq!(parse_volts("volt=1.1000V", Arg::SdramP).unwrap(), 1.1);
+        assert!(matches!(
+            parse_volts("volt=0.7200", Arg::Core),
+            Err(Error::ParseCommand(_))
+        ));
+        assert!(matches!(
+            parse_volts("error=1 error_msg=\"Invalid argument\"", Arg::SdramC),
+            Err(Error::ParseCommand(_))
+        ));
+        assert!(matches!(
+            parse_volts("volt=0.72.0V", Arg::Core),
+            Err(Error::ParseFloat(_))
+        ));
+    }
+
+    #[test]
+    fn parses_memory_split() {
+        assert_eq!(parse_mem("arm=1020M\n", Arg::Arm).unwrap(), 1020);
+        assert_eq!(parse_mem("gpu=4M", Arg::Gpu).unwrap(), 4);
+        // The output of the other memory is not taken for the asked one
+        assert!(matches!(
+            parse_mem("gpu=4M", Arg::Arm),
+            Err(Error::ParseCommand(_))
+        ));
+        assert!(matches!(
+            parse_mem("arm=1G", Arg::Arm),
+            Err(Error::ParseCommand(_))
+        ));
+        assert!(matches!(
+            parse_mem("arm=-1M", Arg::Arm),
+            Err(Error::ParseInt(_))
+        ));
+    }
+
+    #[test]
+    fn parses_ring_oscillator() {
+        let ring_osc = RingOscillator::parse("ring_osc(1)=8.812MHz (@0.8563V) (52.1'C)\n").unwrap();
+        assert_eq!(ring_osc.frequency, 8.812);
+        assert_eq!(ring_osc.volts, 0.8563);
+        assert_eq!(ring_osc.temp, 52.1);
+
+        for output in [
+            "ring_osc(1)=8.812MHz (@0.8563V)",
+            "ring_osc(1)=8.812MHz (0.8563V) (52.1'C)",
+            "8.812MHz (@0.8563V) (52.1'C)",
+            "",
+        ] {
+            assert!(
+                matches!(RingOscillator::parse(output), Err(Error::ParseCommand(_))),
+                "{output:?} accepted"
+            );
+        }
+        assert!(matches!(
+            RingOscillator::parse("ring_osc(1)=fastMHz (@0.8563V) (52.1'C)"),
+            Err(Error::ParseFloat(_))
+        ));
+    }
+}
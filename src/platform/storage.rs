@@ -0,0 +1,263 @@
+//! Storage temperatures and throughput
+//!
+//! - NVMe drive temperatures from the `nvme` hwmon devices, the drives throttle when hot
+//! - Read and write throughput of the whole block devices from `/proc/diskstats`
+
+use std::{collections::HashMap, fs, time::Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    platform::{
+        cpu::TempValue,
+        hwmon::{ChannelKind, Hwmon},
+        read_sysfs_dir,
+        sensor::Sensor,
+    },
+    Error, Result,
+};
+
+const NVME_DEVICE_NAME: &str = "nvme";
+const DISKSTATS_PATH: &str = "/proc/diskstats";
+/// Lists the whole disks only, partitions are skipped
+const BLOCK_PATH: &str = "/sys/block";
+/// Virtual devices without a physical disk behind
+const VIRTUAL_DEVICE_PREFIXES: [&str; 2] = ["loop", "ram"];
+/// `/proc/diskstats` counts in 512 byte sectors regardless of the device
+const SECTOR_SIZE: u64 = 512;
+
+pub type BytesPerSecond = f64;
+/// Sectors read and written per disk name
+type DiskSectors = HashMap<String, (u64, u64)>;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NvmeDrive {
+    /// Controller name like `nvme0`
+    pub name: String,
+    /// `Composite` first, then the individual sensors
+    pub temps: Vec<(String, TempValue)>,
+}
+
+impl NvmeDrive {
+    pub fn composite(&self) -> Option<TempValue> {
+        self.temps.first().map(|(_, temp)| *temp)
+    }
+}
+
+fn nvme_drives(hwmon: &Hwmon) -> Result<Vec<NvmeDrive>> {
+    let drives = hwmon
+        .find_all(NVME_DEVICE_NAME)
+        .map(|device| NvmeDrive {
+            name: device.device_name().unwrap_or_else(|| device.name.clone()),
+            temps: device
+                .channels
+                .iter()
+                .filter(|channel| channel.kind == ChannelKind::Temp)
+                .map(|channel| (channel.name(), channel.value as TempValue))
+                .collect(),
+        })
+        .collect::<Vec<_>>();
+
+    match drives.is_empty() {
+        true => Err(Error::Unavailable(String::from("no NVMe drives"))),
+        false => Ok(drives),
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DiskIo {
+    pub name: String,
+    pub read: BytesPerSecond,
+    pub write: BytesPerSecond,
+}
+
+/// Sectors read and written by every whole disk listed in `block`
+fn read_diskstats(block: &str, diskstats: &str) -> Result<DiskSectors> {
+    let disks = read_sysfs_dir(block)?
+        .map(|entry| Ok(entry?.file_name().to_string_lossy().into_owned()))
+        .filter(|name: &Result<String>| {
+            name.as_ref().map_or(true, |name| {
+                !VIRTUAL_DEVICE_PREFIXES
+                    .iter()
+                    .any(|prefix| name.starts_with(prefix))
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut sectors = HashMap::new();
+    for line in fs::read_to_string(diskstats)?.lines() {
+        // major minor name reads merged sectors_read ms writes merged sectors_written ...
+        let fields = line.split_ascii_whitespace().collect::<Vec<_>>();
+        let Some(name) = fields
+            .get(2)
+            .filter(|name| disks.iter().any(|disk| disk == *name))
+        else {
+            continue;
+        };
+        let field = |index: usize| -> Result<u64> {
+            Ok(fields
+                .get(index)
+                .ok_or(Error::ParseCommand(format!(
+                    "unexpected {diskstats} line {line}"
+                )))?
+                .parse()?)
+        };
+        sectors.insert(name.to_string(), (field(5)?, field(9)?));
+    }
+    Ok(sectors)
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StorageStatus {
+    pub nvme: Sensor<Vec<NvmeDrive>>,
+    pub io: Sensor<Vec<DiskIo>>,
+    #[serde(skip)]
+    previous: Option<(Instant, DiskSectors)>,
+}
+
+impl StorageStatus {
+    pub fn update(&mut self, hwmon: Option<&Hwmon>) {
+        self.nvme.update(match hwmon {
+            Some(hwmon) => nvme_drives(hwmon),
+            None => Err(Error::Unavailable(String::from("no hwmon devices"))),
+        });
+        let io = read_diskstats(BLOCK_PATH, DISKSTATS_PATH)
+            .and_then(|sectors| self.disk_io(Instant::now(), sectors));
+        self.io.update(io);
+    }
+
+    /// Throughput since the previous update, unavailable on the first one
+    fn disk_io(&mut self, now: Instant, sectors: DiskSectors) -> Result<Vec<DiskIo>> {
+        let previous = self.previous.replace((now, sectors));
+        let (Some((previous_time, previous)), Some((_, sectors))) = (previous, &self.previous)
+        else {
+            return Err(Error::Unavailable(String::from(
+                "no previous disk stats yet",
+            )));
+        };
+
+        let elapsed = now.duration_since(previous_time).as_secs_f64();
+        let rate = |current: u64, previous: u64| {
+            current.saturating_sub(previous) as f64 * SECTOR_SIZE as f64 / elapsed
+        };
+        let mut io = sectors
+            .iter()
+            .filter_map(|(name, (read, written))| {
+                let (previous_read, previous_written) = previous.get(name)?;
+                Some(DiskIo {
+                    name: name.clone(),
+                    read: rate(*read, *previous_read),
+                    write: rate(*written, *previous_written),
+                })
+            })
+            .collect::<Vec<_>>();
+        io.sort_unstable_by(|a, b| a.name.cmp(&b.name));
+        Ok(io)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::platform::fixture;
+
+    const DISKSTATS: &str = "\
+ 259       0 nvme0n1 5000 0 80000 900 2000 0 40000 700 0 1200 1600 0 0 0 0
+ 259       1 nvme0n1p1 100 0 2000 10 0 0 0 0 0 10 10 0 0 0 0
+   7       0 loop0 10 0 20 0 0 0 0 0 0 0 0 0 0 0 0
+ 179       0 mmcblk0 300 0 6000 90 30 0 600 20 0 100 110 0 0 0 0
+";
+
+    fn sectors(disks: &[(&str, u64, u64)]) -> DiskSectors {
+        disks
+            .iter()
+            .map(|&(name, read, written)| (name.to_owned(), (read, written)))
+            .collect()
+    }
+
+    #[test]
+    fn reads_whole_disks() {
+        let root = fixture(
+            "diskstats",
+            &[
+                ("block/nvme0n1/dev", "259:0\n"),
+                ("block/mmcblk0/dev", "179:0\n"),
+                ("block/loop0/dev", "7:0\n"),
+                ("diskstats", DISKSTATS),
+            ],
+        );
+        let block = root.join("block");
+        let diskstats = root.join("diskstats");
+        let result = read_diskstats(block.to_str().unwrap(), diskstats.to_str().unwrap());
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(
+            result.unwrap(),
+            sectors(&[("nvme0n1", 80000, 40000), ("mmcblk0", 6000, 600)])
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_diskstats() {
+        for (name, content) in [
+            ("diskstats-short", " 179 0 mmcblk0 300 0 6000\n"),
+            ("diskstats-number", " 179 0 mmcblk0 300 0 6k 90 30 0 600\n"),
+        ] {
+            let root = fixture(
+                name,
+                &[("block/mmcblk0/dev", "179:0\n"), ("diskstats", content)],
+            );
+            let block = root.join("block");
+            let diskstats = root.join("diskstats");
+            let result = read_diskstats(block.to_str().unwrap(), diskstats.to_str().unwrap());
+            fs::remove_dir_all(&root).unwrap();
+            assert!(result.is_err(), "{content:?} accepted");
+        }
+        assert!(matches!(
+            read_diskstats("/nonexistent/block", DISKSTATS_PATH),
+            Err(Error::Unavailable(_))
+        ));
+    }
+
+    #[test]
+    fn rates_from_the_previous_update() {
+        let start = Instant::now();
+        let mut storage = StorageStatus::default();
+        let first = storage.disk_io(start, sectors(&[("mmcblk0", 1000, 1000), ("sda", 10, 10)]));
+        assert!(matches!(first, Err(Error::Unavailable(_))));
+
+        // The card read 2000 sectors in 2 s, its write counter was reset, the USB disk
+        // is gone and the NVMe drive appeared
+        let io = storage
+            .disk_io(
+                start + Duration::from_secs(2),
+                sectors(&[("mmcblk0", 3000, 200), ("nvme0n1", 500, 500)]),
+            )
+            .unwrap();
+        assert_eq!(io.len(), 1);
+        assert_eq!(io[0].name, "mmcblk0");
+        assert_eq!(io[0].read, 2000.0 * 512.0 / 2.0);
+        assert_eq!(io[0].write, 0.0);
+
+        let io = storage
+            .disk_io(
+                start + Duration::from_secs(3),
+                sectors(&[("mmcblk0", 3000, 400), ("nvme0n1", 2500, 500)]),
+            )
+            .unwrap();
+        let rates = io
+            .iter()
+            .map(|disk| (disk.name.as_str(), disk.read, disk.write))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            rates,
+            [
+                ("mmcblk0", 0.0, 200.0 * 512.0),
+                ("nvme0n1", 2000.0 * 512.0, 0.0)
+            ]
+        );
+    }
+}
@@ -0,0 +1,384 @@
+//! System load from `/proc`, to correlate temperature and power with the workload
+//!
+//! - `/proc/stat` total and per core CPU utilisation between two updates
+//! - `/proc/loadavg` load average
+//! - `/proc/meminfo` memory and swap usage
+//! - `/proc/pressure/{cpu,memory,io}` pressure stall information, needs a kernel with PSI enabled
+
+use std::{fs, io};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    platform::{read_trimmed, sensor::Sensor},
+    Error, Result,
+};
+
+const STAT_PATH: &str = "/proc/stat";
+const LOADAVG_PATH: &str = "/proc/loadavg";
+const MEMINFO_PATH: &str = "/proc/meminfo";
+const PRESSURE_PATH: &str = "/proc/pressure";
+/// Returned by the pressure files while PSI is disabled
+const EOPNOTSUPP: i32 = 95;
+
+pub type Percent = f32;
+pub type KiB = u64;
+
+/// Jiffies spent by a CPU, busy and idle (including iowait)
+#[derive(Debug, Default, Clone, Copy)]
+struct CpuTimes {
+    busy: u64,
+    idle: u64,
+}
+
+impl CpuTimes {
+    fn parse(line: &str) -> Result<Self> {
+        let values = line
+            .split_ascii_whitespace()
+            .skip(1)
+            .map(|value| value.parse::<u64>())
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        if values.len() < 5 {
+            return Err(Error::ParseCommand(format!(
+                "unexpected {STAT_PATH} line {line}"
+            )));
+        }
+
+        // user nice system idle iowait irq softirq steal, guest times are included in user
+        let idle = values[3] + values[4];
+        let busy = values.iter().take(8).sum::<u64>() - idle;
+        Ok(Self { busy, idle })
+    }
+
+    fn usage_since(&self, previous: &Self) -> Percent {
+        let busy = self.busy.saturating_sub(previous.busy);
+        let total = busy + self.idle.saturating_sub(previous.idle);
+        match total {
+            0 => 0.0,
+            total => busy as Percent * 100.0 / total as Percent,
+        }
+    }
+}
+
+/// Jiffies spent by all CPUs since boot
+pub fn read_total_jiffies() -> Result<u64> {
+    read_cpu_times()?
+        .first()
+        .map(|times| times.busy + times.idle)
+        .ok_or(Error::ParseCommand(format!("no cpu line in {STAT_PATH}")))
+}
+
+/// Total and per core times, the first entry is the total
+fn read_cpu_times() -> Result<Vec<CpuTimes>> {
+    parse_cpu_times(&fs::read_to_string(STAT_PATH)?)
+}
+
+fn parse_cpu_times(stat: &str) -> Result<Vec<CpuTimes>> {
+    stat.lines()
+        .filter(|line| line.starts_with("cpu"))
+        .map(CpuTimes::parse)
+        .collect()
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CpuUsage {
+    pub total: Percent,
+    pub cores: Vec<Percent>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LoadAverage {
+    pub one: f32,
+    pub five: f32,
+    pub fifteen: f32,
+}
+
+impl LoadAverage {
+    pub fn new() -> Result<Self> {
+        Self::parse(&read_trimmed(LOADAVG_PATH.as_ref())?)
+    }
+
+    fn parse(loadavg: &str) -> Result<Self> {
+        let mut it = loadavg.split_ascii_whitespace();
+        let mut next = || {
+            it.next().ok_or(Error::ParseCommand(format!(
+                "unexpected {LOADAVG_PATH} {loadavg}"
+            )))
+        };
+
+        Ok(Self {
+            one: next()?.parse()?,
+            five: next()?.parse()?,
+            fifteen: next()?.parse()?,
+        })
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Memory {
+    pub total: KiB,
+    pub available: KiB,
+    pub swap_total: KiB,
+    pub swap_free: KiB,
+}
+
+impl Memory {
+    pub fn new() -> Result<Self> {
+        Self::parse(&fs::read_to_string(MEMINFO_PATH)?)
+    }
+
+    fn parse(meminfo: &str) -> Result<Self> {
+        let mut memory = Self::default();
+        for line in meminfo.lines() {
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let field = match key {
+                "MemTotal" => &mut memory.total,
+                "MemAvailable" => &mut memory.available,
+                "SwapTotal" => &mut memory.swap_total,
+                "SwapFree" => &mut memory.swap_free,
+                _ => continue,
+            };
+            *field = value.trim().trim_end_matches("kB").trim().parse()?;
+        }
+        Ok(memory)
+    }
+
+    pub fn used(&self) -> KiB {
+        self.total.saturating_sub(self.available)
+    }
+
+    pub fn swap_used(&self) -> KiB {
+        self.swap_total.saturating_sub(self.swap_free)
+    }
+}
+
+/// Share of time in percent some (or all, for `full`) tasks were stalled
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PressureAverage {
+    pub avg10: Percent,
+    pub avg60: Percent,
+    pub avg300: Percent,
+}
+
+impl PressureAverage {
+    /// Parses `some avg10=0.00 avg60=0.00 avg300=0.00 total=0`
+    fn parse(line: &str) -> Result<Self> {
+        let mut average = Self::default();
+        for pair in line.split_ascii_whitespace().skip(1) {
+            let Some((key, value)) = pair.split_once('=') else {
+                continue;
+            };
+            match key {
+                "avg10" => average.avg10 = value.parse()?,
+                "avg60" => average.avg60 = value.parse()?,
+                "avg300" => average.avg300 = value.parse()?,
+                _ => {}
+            }
+        }
+        Ok(average)
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ResourcePressure {
+    pub some: PressureAverage,
+    /// Not reported for the CPU on older kernels
+    pub full: Option<PressureAverage>,
+}
+
+impl ResourcePressure {
+    fn new(resource: &str) -> Result<Self> {
+        let content = match fs::read_to_string(format!("{PRESSURE_PATH}/{resource}")) {
+            Ok(content) => content,
+            // PSI is compiled in, but disabled without `psi=1` on the kernel command line
+            Err(e)
+                if e.kind() == io::ErrorKind::NotFound || e.raw_os_error() == Some(EOPNOTSUPP) =>
+            {
+                return Err(Error::Unavailable(String::from(
+                    "pressure stall information",
+                )));
+            }
+            Err(e) => return Err(e.into()),
+        };
+        Self::parse(&content)
+    }
+
+    fn parse(content: &str) -> Result<Self> {
+        let mut pressure = Self::default();
+        for line in content.lines() {
+            match line.split_ascii_whitespace().next() {
+                Some("some") => pressure.some = PressureAverage::parse(line)?,
+                Some("full") => pressure.full = Some(PressureAverage::parse(line)?),
+                _ => {}
+            }
+        }
+        Ok(pressure)
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Pressure {
+    pub cpu: ResourcePressure,
+    pub memory: ResourcePressure,
+    pub io: ResourcePressure,
+}
+
+impl Pressure {
+    pub fn new() -> Result<Self> {
+        Ok(Self {
+            cpu: ResourcePressure::new("cpu")?,
+            memory: ResourcePressure::new("memory")?,
+            io: ResourcePressure::new("io")?,
+        })
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SystemStatus {
+    pub usage: Sensor<CpuUsage>,
+    pub load: Sensor<LoadAverage>,
+    pub memory: Sensor<Memory>,
+    pub pressure: Sensor<Pressure>,
+    #[serde(skip)]
+    previous_times: Option<Vec<CpuTimes>>,
+}
+
+impl SystemStatus {
+    pub fn update(&mut self) {
+        let usage = self.cpu_usage();
+        self.usage.update(usage);
+        self.load.update(LoadAverage::new());
+        self.memory.update(Memory::new());
+        self.pressure.update(Pressure::new());
+    }
+
+    /// Utilisation since the previous update, unavailable on the first one
+    fn cpu_usage(&mut self) -> Result<CpuUsage> {
+        let times = read_cpu_times()?;
+        let Some(previous) = self.previous_times.replace(times.clone()) else {
+            return Err(Error::Unavailable(String::from(
+                "no previous CPU times yet",
+            )));
+        };
+
+        let mut usage = times
+            .iter()
+            .zip(&previous)
+            .map(|(times, previous)| times.usage_since(previous));
+        Ok(CpuUsage {
+            total: usage.next().unwrap_or_default(),
+            cores: usage.collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STAT: &str = "\
+cpu  4705 356 584 3699 23 23 0 0 0 0
+cpu0 1393 280 230 837 7 11 0 0 0 0
+cpu1 3312 76 354 2862 16 12 0 0 0 0
+intr 114930548 113199788 3 0 5 263 0 4
+ctxt 1990473
+btime 1062191376
+processes 2915
+";
+
+    #[test]
+    fn parses_cpu_times() {
+        let times = parse_cpu_times(STAT).unwrap();
+        assert_eq!(times.len(), 3);
+        // user + nice + system + irq + softirq + steal
+        assert_eq!(times[0].busy, 4705 + 356 + 584 + 23);
+        assert_eq!(times[0].idle, 3699 + 23);
+        assert_eq!((times[1].busy, times[1].idle), (1914, 844));
+    }
+
+    #[test]
+    fn rejects_short_cpu_line() {
+        assert!(parse_cpu_times("cpu  1 2 3 4\n").is_err());
+        assert!(parse_cpu_times("cpu  1 2 x 4 5\n").is_err());
+    }
+
+    #[test]
+    fn cpu_usage_between_samples() {
+        let previous = CpuTimes {
+            busy: 100,
+            idle: 300,
+        };
+        let current = CpuTimes {
+            busy: 175,
+            idle: 325,
+        };
+        assert_eq!(current.usage_since(&previous), 75.0);
+        assert_eq!(previous.usage_since(&previous), 0.0);
+        // Counters going backwards (CPU hotplug) do not underflow
+        assert_eq!(previous.usage_since(&current), 0.0);
+    }
+
+    #[test]
+    fn parses_loadavg() {
+        let load = LoadAverage::parse("0.52 1.03 0.99 2/312 4242").unwrap();
+        assert_eq!((load.one, load.five, load.fifteen), (0.52, 1.03, 0.99));
+        assert!(LoadAverage::parse("0.52 1.03").is_err());
+    }
+
+    #[test]
+    fn parses_meminfo() {
+        let memory = Memory::parse(
+            "MemTotal:        8245616 kB\n\
+             MemFree:         6703776 kB\n\
+             MemAvailable:    7572480 kB\n\
+             Buffers:           42256 kB\n\
+             SwapCached:            0 kB\n\
+             SwapTotal:        524272 kB\n\
+             SwapFree:         524016 kB\n\
+             HugePages_Total:       0\n",
+        )
+        .unwrap();
+        assert_eq!(memory.total, 8245616);
+        assert_eq!(memory.available, 7572480);
+        assert_eq!(memory.used(), 8245616 - 7572480);
+        assert_eq!(memory.swap_used(), 256);
+        assert!(Memory::parse("MemTotal: lots kB\n").is_err());
+    }
+
+    #[test]
+    fn parses_pressure() {
+        let pressure = ResourcePressure::parse(
+            "some avg10=1.53 avg60=0.87 avg300=0.21 total=5831234\n\
+             full avg10=0.00 avg60=0.12 avg300=0.05 total=1234567\n",
+        )
+        .unwrap();
+        assert_eq!(
+            (
+                pressure.some.avg10,
+                pressure.some.avg60,
+                pressure.some.avg300
+            ),
+            (1.53, 0.87, 0.21)
+        );
+        let full = pressure.full.unwrap();
+        assert_eq!((full.avg10, full.avg60, full.avg300), (0.0, 0.12, 0.05));
+    }
+
+    #[test]
+    fn cpu_pressure_without_full_line() {
+        let pressure =
+            ResourcePressure::parse("some avg10=0.10 avg60=0.20 avg300=0.30 total=42\n").unwrap();
+        assert_eq!(pressure.some.avg300, 0.3);
+        assert!(pressure.full.is_none());
+        assert!(ResourcePressure::parse("some avg10=high\n").is_err());
+    }
+}
@@ -0,0 +1,252 @@
+//! Kernel thermal framework state under `/sys/class/thermal`
+//!
+//! - `thermal_zone*/{type,temp}` with `trip_point_*_{temp,type,hyst}`
+//! - `cooling_device*/{type,cur_state,max_state}`
+//!
+//! Trip points tell at which temperatures the kernel steps up the cooling devices,
+//! e.g. the fan on the Raspberry Pi 5, which explains its current speed.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    platform::{cpu::TempValue, read_sysfs_dir, read_trimmed},
+    Result,
+};
+
+const THERMAL_PATH: &str = "/sys/class/thermal";
+const ZONE_PREFIX: &str = "thermal_zone";
+const COOLING_DEVICE_PREFIX: &str = "cooling_device";
+const TRIP_POINT_PREFIX: &str = "trip_point_";
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TripPoint {
+    pub index: u32,
+    /// `active`, `passive`, `hot` or `critical`
+    pub kind: String,
+    pub temp: TempValue,
+    pub hyst: Option<TempValue>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ThermalZone {
+    pub name: String,
+    pub kind: String,
+    pub temp: Option<TempValue>,
+    pub trips: Vec<TripPoint>,
+}
+
+impl ThermalZone {
+    fn read(path: &Path, name: String) -> Result<Self> {
+        let mut trips = Vec::new();
+        for entry in fs::read_dir(path)? {
+            let file_name = entry?.file_name();
+            let Some(index) = file_name
+                .to_str()
+                .and_then(|name| name.strip_prefix(TRIP_POINT_PREFIX))
+                .and_then(|name| name.strip_suffix("_temp"))
+                .and_then(|index| index.parse::<u32>().ok())
+            else {
+                continue;
+            };
+
+            let trip = |suffix: &str| {
+                read_trimmed(&path.join(format!("{TRIP_POINT_PREFIX}{index}_{suffix}")))
+            };
+            let read_trip = || -> Result<TripPoint> {
+                Ok(TripPoint {
+                    index,
+                    kind: trip("type")?,
+                    temp: millidegree(&trip("temp")?)?,
+                    hyst: trip("hyst").ok().and_then(|hyst| millidegree(&hyst).ok()),
+                })
+            };
+            // An unreadable trip point is left out, the zone is still worth showing
+            if let Ok(trip) = read_trip() {
+                trips.push(trip);
+            }
+        }
+        trips.sort_unstable_by_key(|trip| trip.index);
+
+        Ok(Self {
+            name,
+            kind: read_trimmed(&path.join("type"))?,
+            // Reading the temperature fails on some zones while the sensor is suspended
+            temp: read_trimmed(&path.join("temp"))
+                .and_then(|temp| millidegree(&temp))
+                .ok(),
+            trips,
+        })
+    }
+
+    /// Trip points the zone temperature has reached
+    pub fn active_trips(&self) -> impl Iterator<Item = &TripPoint> {
+        self.trips
+            .iter()
+            .filter(|trip| self.temp.is_some_and(|temp| temp >= trip.temp))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CoolingDevice {
+    pub name: String,
+    pub kind: String,
+    pub cur_state: u32,
+    pub max_state: u32,
+}
+
+impl CoolingDevice {
+    fn read(path: &Path, name: String) -> Result<Self> {
+        Ok(Self {
+            name,
+            kind: read_trimmed(&path.join("type"))?,
+            cur_state: read_trimmed(&path.join("cur_state"))?.parse()?,
+            max_state: read_trimmed(&path.join("max_state"))?.parse()?,
+        })
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Thermal {
+    pub zones: Vec<ThermalZone>,
+    pub cooling_devices: Vec<CoolingDevice>,
+}
+
+impl Thermal {
+    /// Reads every zone and cooling device, an unreadable one is left out
+    /// and fails the source only when nothing can be read
+    pub fn read() -> Result<Self> {
+        Self::read_from(THERMAL_PATH)
+    }
+
+    fn read_from(root: &str) -> Result<Self> {
+        let mut thermal = Self::default();
+        let mut error = None;
+        for (path, name) in entries(root)? {
+            let result = if name.starts_with(ZONE_PREFIX) {
+                ThermalZone::read(&path, name).map(|zone| thermal.zones.push(zone))
+            } else if name.starts_with(COOLING_DEVICE_PREFIX) {
+                CoolingDevice::read(&path, name).map(|device| thermal.cooling_devices.push(device))
+            } else {
+                continue;
+            };
+            if let Err(e) = result {
+                error.get_or_insert(e);
+            }
+        }
+        match error {
+            Some(e) if thermal.zones.is_empty() && thermal.cooling_devices.is_empty() => Err(e),
+            _ => Ok(thermal),
+        }
+    }
+}
+
+/// Entries of the thermal class in the natural order, `thermal_zone2` before `thermal_zone10`
+fn entries(root: &str) -> Result<Vec<(PathBuf, String)>> {
+    let mut entries = read_sysfs_dir(root)?
+        .map(|entry| {
+            let entry = entry?;
+            Ok((
+                entry.path(),
+                entry.file_name().to_string_lossy().into_owned(),
+            ))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    entries.sort_unstable_by_key(|(_, name)| {
+        let prefix = name.trim_end_matches(|c: char| c.is_ascii_digit());
+        (
+            prefix.to_owned(),
+            name[prefix.len()..].parse().unwrap_or(u32::MAX),
+        )
+    });
+    Ok(entries)
+}
+
+fn millidegree(value: &str) -> Result<TempValue> {
+    Ok(value.parse::<i32>()? as TempValue / 1_000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::fixture;
+
+    #[test]
+    fn skips_unreadable_entries() {
+        let root = fixture(
+            "thermal-partial",
+            &[
+                ("thermal_zone0/type", "cpu-thermal\n"),
+                ("thermal_zone0/temp", "51250\n"),
+                ("thermal_zone0/trip_point_0_type", "active\n"),
+                ("thermal_zone0/trip_point_0_temp", "50000\n"),
+                ("thermal_zone0/trip_point_0_hyst", "5000\n"),
+                // Trip point without a type
+                ("thermal_zone0/trip_point_1_temp", "60000\n"),
+                ("thermal_zone0/trip_point_10_type", "critical\n"),
+                ("thermal_zone0/trip_point_10_temp", "110000\n"),
+                // Zone without a type
+                ("thermal_zone1/temp", "40000\n"),
+                ("cooling_device0/type", "pwm-fan\n"),
+                ("cooling_device0/cur_state", "2\n"),
+                ("cooling_device0/max_state", "4\n"),
+                ("cooling_device1/type", "broken\n"),
+                ("cooling_device1/cur_state", "n/a\n"),
+                ("cooling_device1/max_state", "4\n"),
+            ],
+        );
+        let thermal = Thermal::read_from(root.to_str().unwrap()).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+
+        let [zone] = &thermal.zones[..] else {
+            panic!("expected one zone, got {:?}", thermal.zones);
+        };
+        assert_eq!(zone.kind, "cpu-thermal");
+        assert_eq!(zone.temp, Some(51.25));
+        let trips = zone
+            .trips
+            .iter()
+            .map(|trip| (trip.index, trip.kind.as_str(), trip.temp, trip.hyst))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            trips,
+            [
+                (0, "active", 50.0, Some(5.0)),
+                (10, "critical", 110.0, None)
+            ]
+        );
+        assert_eq!(zone.active_trips().count(), 1);
+
+        let [device] = &thermal.cooling_devices[..] else {
+            panic!(
+                "expected one cooling device, got {:?}",
+                thermal.cooling_devices
+            );
+        };
+        assert_eq!(
+            (device.kind.as_str(), device.cur_state, device.max_state),
+            ("pwm-fan", 2, 4)
+        );
+    }
+
+    #[test]
+    fn fails_when_nothing_is_readable() {
+        let root = fixture("thermal-broken", &[("thermal_zone0/temp", "40000\n")]);
+        let result = Thermal::read_from(root.to_str().unwrap());
+        fs::remove_dir_all(&root).unwrap();
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn missing_class_is_unavailable() {
+        assert!(matches!(
+            Thermal::read_from("/nonexistent/thermal"),
+            Err(crate::Error::Unavailable(_))
+        ));
+    }
+}
@@ -0,0 +1,262 @@
+//! Recordings of the board state as CSV
+//!
+//! The file starts with `# key: value` comment lines carrying the board identity,
+//! so recordings of different boards can be told apart, followed by a header row
+//! and one row per sample. Unavailable values are left empty.
+//!
+//! ```text
+//! # pi-stats recording
+//! # model: Raspberry Pi 5 Model B Rev 1.0
+//! # serial: 1f2e3d4c5b6a7980
+//! elapsed_s,cpu_temp_c,arm_mhz,...,total_w,EXT5V_w,...
+//! 0.000,52.1,2400.00,...
+//! ```
+//!
+//! The power rail columns are taken from the first sample with a power reading, the rows
+//! before it are held back until then so the header names every rail.
+
+use std::{
+    fmt::Display,
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{platform::BoardInfo, Result, Rpi};
+
+const COLUMNS: [&str; 14] = [
+    "elapsed_s",
+    "cpu_temp_c",
+    "arm_mhz",
+    "gpu_mhz",
+    "core_v",
+    "fan_pwm",
+    "fan_rpm",
+    "cpu_usage_pct",
+    "under_voltage",
+    "frequency_capped",
+    "throttled",
+    "soft_temp_limit",
+    "total_w",
+    "total_wh",
+];
+
+/// Rows held back waiting for the first power reading, the header is written
+/// without rail columns after as many
+const MAX_PENDING: usize = 60;
+
+pub struct Recorder {
+    out: BufWriter<File>,
+    started: Instant,
+    /// Rail names of the power columns, `None` until the header is written
+    rails: Option<Vec<String>>,
+    /// Rows without the rail columns recorded before the header
+    pending: Vec<Vec<String>>,
+}
+
+impl Recorder {
+    /// Creates the file and writes the board identity
+    pub fn create(path: &Path, board: &BoardInfo) -> Result<Self> {
+        let mut out = BufWriter::new(File::create(path)?);
+        writeln!(out, "# pi-stats recording")?;
+        let identity = [
+            ("model", &board.model),
+            ("serial", &board.serial),
+            ("hostname", &board.hostname),
+            ("firmware", &board.firmware),
+            ("kernel", &board.kernel),
+        ];
+        for (key, value) in identity {
+            if let Some(value) = value {
+                writeln!(out, "# {key}: {value}")?;
+            }
+        }
+        for (key, value) in &board.config {
+            writeln!(out, "# {key}: {value}")?;
+        }
+        let started = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        writeln!(out, "# started: {}", started.as_secs())?;
+
+        Ok(Self {
+            out,
+            started: Instant::now(),
+            rails: None,
+            pending: Vec::new(),
+        })
+    }
+
+    pub fn record(&mut self, rpi: &Rpi) -> Result<()> {
+        let power = rpi.power.power.current();
+        let clock = rpi.cpu.clock.current();
+        let fan = rpi.fan.current();
+        let throttle = rpi.power.throttle.current().map(|status| &status.current);
+        let flag = |set: bool| u8::from(set);
+        let mut row = vec![
+            format!("{:.3}", self.started.elapsed().as_secs_f64()),
+            field(rpi.cpu.temp.current().map(|temp| format!("{:.1}", **temp))),
+            field(clock.map(|clock| format!("{:.2}", *clock.arm))),
+            field(clock.map(|clock| format!("{:.2}", *clock.gpu))),
+            field(
+                rpi.soc
+                    .volts
+                    .current()
+                    .map(|volts| format!("{:.4}", volts.core)),
+            ),
+            field(fan.map(|fan| fan.pwm)),
+            field(fan.map(|fan| fan.rpm)),
+            field(
+                rpi.system
+                    .usage
+                    .current()
+                    .map(|usage| format!("{:.1}", usage.total)),
+            ),
+            field(throttle.map(|status| flag(status.under_voltage))),
+            field(throttle.map(|status| flag(status.arm_frequency_capped))),
+            field(throttle.map(|status| flag(status.throttled))),
+            field(throttle.map(|status| flag(status.soft_temp_limit))),
+            field(power.map(|power| format!("{:.3}", power.total_power))),
+            format!("{:.6}", rpi.energy.total),
+        ];
+        if self.rails.is_none() {
+            match power {
+                Some(power) => {
+                    let rails = power
+                        .power_map
+                        .iter()
+                        .map(|rail| rail.measure.clone())
+                        .collect();
+                    self.write_header(rails)?;
+                }
+                None if self.pending.len() < MAX_PENDING => {
+                    self.pending.push(row);
+                    return Ok(());
+                }
+                None => self.write_header(Vec::new())?,
+            }
+        }
+        for rail in self.rails.iter().flatten() {
+            row.push(field(power.and_then(|power| {
+                let measure = power.power_map.iter().find(|m| &m.measure == rail)?;
+                Some(format!("{:.3}", measure.volts * measure.amps))
+            })));
+        }
+        writeln!(self.out, "{}", row.join(","))?;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<()> {
+        if self.rails.is_none() {
+            self.write_header(Vec::new())?;
+        }
+        Ok(self.out.flush()?)
+    }
+
+    /// Writes the header with the rail columns and the rows held back, their rails left empty
+    fn write_header(&mut self, rails: Vec<String>) -> Result<()> {
+        let header = COLUMNS
+            .iter()
+            .map(|column| column.to_string())
+            .chain(rails.iter().map(|rail| format!("{rail}_w")))
+            .collect::<Vec<_>>();
+        writeln!(self.out, "{}", header.join(","))?;
+        for row in std::mem::take(&mut self.pending) {
+            writeln!(self.out, "{}{}", row.join(","), ",".repeat(rails.len()))?;
+        }
+        self.rails = Some(rails);
+        Ok(())
+    }
+}
+
+fn field(value: Option<impl Display>) -> String {
+    value.map(|value| value.to_string()).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    use crate::platform::fixture;
+
+    /// Rail names, volts and amps of a power reading
+    type Rails = &'static [(&'static str, f32, f32)];
+
+    /// Header and rows of the recording, without the comments and the elapsed time
+    fn record(name: &str, samples: &[Option<Rails>]) -> Vec<String> {
+        let root = fixture(name, &[]);
+        let path = root.join("recording.csv");
+        let mut board = BoardInfo::default();
+        board.model = Some(String::from("Raspberry Pi 5 Model B Rev 1.0"));
+        let mut recorder = Recorder::create(&path, &board).unwrap();
+        let mut rpi = Rpi::default();
+        for sample in samples {
+            match sample {
+                Some(rails) => {
+                    let power_map = rails
+                        .iter()
+                        .map(|(measure, volts, amps)| {
+                            serde_json::json!({"measure": measure, "volts": volts, "amps": amps})
+                        })
+                        .collect::<Vec<_>>();
+                    let total_power: f32 = rails.iter().map(|(_, volts, amps)| volts * amps).sum();
+                    let power = serde_json::json!({
+                        "power_map": power_map,
+                        "total_power": total_power,
+                    });
+                    rpi.power
+                        .power
+                        .update(Ok(serde_json::from_value(power).unwrap()));
+                }
+                None => rpi.power.power = Default::default(),
+            }
+            recorder.record(&rpi).unwrap();
+        }
+        recorder.finish().unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+
+        assert!(content.starts_with("# pi-stats recording\n# model: Raspberry Pi 5"));
+        content
+            .lines()
+            .filter(|line| !line.starts_with('#'))
+            .map(|line| {
+                line.split_once(',')
+                    .map_or(line, |(_, rest)| rest)
+                    .to_owned()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn header_waits_for_the_first_power_reading() {
+        let lines = record(
+            "recording-deferred",
+            &[None, Some(&[("EXT5V", 5.0, 1.0), ("VDD_CORE", 0.8, 2.5)])],
+        );
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].ends_with(",total_w,total_wh,EXT5V_w,VDD_CORE_w"));
+        assert!(lines[1].ends_with(",,0.000000,,"));
+        assert!(lines[2].ends_with(",7.000,0.000000,5.000,2.000"));
+    }
+
+    #[test]
+    fn header_without_rails_when_never_read() {
+        let lines = record("recording-no-power", &[None, None]);
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].ends_with(",total_w,total_wh"));
+        assert!(lines[1..].iter().all(|row| row.ends_with(",,0.000000")));
+    }
+
+    #[test]
+    fn header_without_rails_after_max_pending() {
+        let mut samples: Vec<Option<Rails>> = vec![None; MAX_PENDING + 1];
+        samples.push(Some(&[("EXT5V", 5.0, 1.0)]));
+        let lines = record("recording-max-pending", &samples);
+        assert_eq!(lines.len(), MAX_PENDING + 3);
+        assert!(lines[0].ends_with(",total_w,total_wh"));
+        assert!(lines[MAX_PENDING + 2].ends_with(",5.000,0.000000"));
+    }
+}
@@ -0,0 +1,340 @@
+//! Board state of a remote `pi-stats` instance
+//!
+//! The snapshots are read from the server-sent event stream (`/api/v1/stream`) of
+//! `pi-stats serve`, or polled over SSH from `pi-stats snapshot --json`, and turned back
+//! into an [`Rpi`], so the TUI renders a remote board like the local one. The ages of the values
+//! are counted from the time the snapshot arrived. A lost connection is retried with a backoff
+//! while the last snapshot stays on display.
+
+use std::{
+    sync::{Arc, Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+
+use reqwest::Url;
+use tokio::{process, task::JoinHandle, time};
+
+use crate::{Error, Result, Rpi};
+
+const STREAM_PATH: &str = "api/v1/stream";
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+/// Longest silence of the stream, the server sends a keep-alive every 15 s
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+const MIN_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+/// Command run over SSH when none is configured
+pub const SSH_COMMAND: &str = "pi-stats snapshot --json";
+
+#[derive(Default)]
+struct Link {
+    /// Latest snapshot as JSON, checked to deserialize
+    snapshot: Option<Arc<str>>,
+    /// Whether the snapshot has been taken since it arrived
+    taken: bool,
+    received: Option<Instant>,
+    connected: bool,
+    error: Option<String>,
+}
+
+impl Link {
+    fn receive(&mut self, snapshot: String) -> Result<()> {
+        serde_json::from_str::<Rpi>(&snapshot)?;
+        self.snapshot = Some(snapshot.into());
+        self.taken = false;
+        self.received = Some(Instant::now());
+        self.connected = true;
+        self.error = None;
+        Ok(())
+    }
+
+    fn fail(&mut self, error: Error) {
+        self.connected = false;
+        self.error = Some(error.to_string());
+    }
+}
+
+/// Aborts the task once the last clone of the [`Remote`] is gone
+struct Task(JoinHandle<()>);
+
+impl Drop for Task {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// Connection to a remote board, clones share it
+#[derive(Clone)]
+pub struct Remote {
+    address: String,
+    link: Arc<Mutex<Link>>,
+    _task: Arc<Task>,
+}
+
+impl Remote {
+    /// Streams from the instance at `address`, a base URL like `http://pi5:9180` or just `pi5:9180`
+    pub fn connect(address: &str) -> Result<Self> {
+        let (url, stream) = stream_url(address)?;
+        let client = reqwest::Client::builder()
+            .connect_timeout(CONNECT_TIMEOUT)
+            .build()
+            .map_err(remote_error)?;
+        let link = Arc::new(Mutex::new(Link::default()));
+        let task = tokio::spawn(follow(client, stream, link.clone()));
+        Ok(Self::new(url.to_string(), link, task))
+    }
+
+    /// Runs `command` printing a snapshot as JSON on the SSH `destination` once per interval
+    pub fn ssh(destination: &str, command: &str, interval: Duration) -> Self {
+        let link = Arc::new(Mutex::new(Link::default()));
+        let task = tokio::spawn(poll(
+            destination.to_owned(),
+            command.to_owned(),
+            interval,
+            link.clone(),
+        ));
+        Self::new(format!("ssh://{destination}"), link, task)
+    }
+
+    fn new(address: String, link: Arc<Mutex<Link>>, task: JoinHandle<()>) -> Self {
+        Self {
+            address,
+            link,
+            _task: Arc::new(Task(task)),
+        }
+    }
+
+    /// URL of the stream or the SSH destination
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    /// Snapshot received since the previous call
+    pub fn take(&self) -> Option<Rpi> {
+        let mut link = self.link();
+        if link.taken {
+            return None;
+        }
+        link.taken = true;
+        deserialize(link.snapshot.as_deref())
+    }
+
+    /// Latest snapshot, whether taken or not
+    pub fn latest(&self) -> Option<Rpi> {
+        deserialize(self.link().snapshot.as_deref())
+    }
+
+    /// Time passed since the latest snapshot arrived
+    pub fn age(&self) -> Option<Duration> {
+        self.link().received.map(|received| received.elapsed())
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.link().connected
+    }
+
+    /// Why the remote is out of reach, cleared once it is back
+    pub fn error(&self) -> Option<String> {
+        self.link().error.clone()
+    }
+
+    fn link(&self) -> MutexGuard<'_, Link> {
+        lock(&self.link)
+    }
+}
+
+/// Base URL of the instance at `address` and the URL of its stream below it
+fn stream_url(address: &str) -> Result<(Url, Url)> {
+    let with_scheme = match address.contains("://") {
+        true => address.to_owned(),
+        false => format!("http://{address}"),
+    };
+    let mut url = Url::parse(&with_scheme)
+        .map_err(|e| Error::InvalidInput(format!("remote {address}: {e}")))?;
+    if !url.path().ends_with('/') {
+        url.set_path(&format!("{}/", url.path()));
+    }
+    let stream = url
+        .join(STREAM_PATH)
+        .map_err(|e| Error::InvalidInput(format!("remote {address}: {e}")))?;
+    Ok((url, stream))
+}
+
+fn deserialize(snapshot: Option<&str>) -> Option<Rpi> {
+    serde_json::from_str(snapshot?).ok()
+}
+
+fn lock(link: &Mutex<Link>) -> MutexGuard<'_, Link> {
+    link.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Reads the stream for as long as the remote runs, reconnecting after every failure
+async fn follow(client: reqwest::Client, url: Url, link: Arc<Mutex<Link>>) {
+    let mut backoff = MIN_BACKOFF;
+    loop {
+        let error = match read_stream(&client, &url, &link, &mut backoff).await {
+            Ok(()) => Error::Remote(String::from("stream closed by the remote")),
+            Err(e) => e,
+        };
+        lock(&link).fail(error);
+        time::sleep(backoff).await;
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+}
+
+async fn read_stream(
+    client: &reqwest::Client,
+    url: &Url,
+    link: &Mutex<Link>,
+    backoff: &mut Duration,
+) -> Result<()> {
+    let mut response = client
+        .get(url.clone())
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(remote_error)?;
+    let mut buffer = Vec::new();
+    loop {
+        let chunk = time::timeout(READ_TIMEOUT, response.chunk())
+            .await
+            .map_err(|_| Error::Remote(format!("no data for {READ_TIMEOUT:?}")))?
+            .map_err(remote_error)?;
+        let Some(chunk) = chunk else {
+            return Ok(());
+        };
+        buffer.extend_from_slice(&chunk);
+
+        // Events end with an empty line
+        while let Some(end) = buffer.windows(2).position(|end| end == b"\n\n") {
+            let event = String::from_utf8_lossy(&buffer[..end]).into_owned();
+            buffer.drain(..end + 2);
+            if let Some(data) = sample_data(&event) {
+                lock(link).receive(data)?;
+                *backoff = MIN_BACKOFF;
+            }
+        }
+    }
+}
+
+/// Samples the board over SSH once per interval
+async fn poll(destination: String, command: String, interval: Duration, link: Arc<Mutex<Link>>) {
+    let mut interval = time::interval(interval);
+    interval.set_missed_tick_behavior(time::MissedTickBehavior::Delay);
+    loop {
+        interval.tick().await;
+        let result = match ssh(&destination, &command).await {
+            Ok(snapshot) => lock(&link).receive(snapshot),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            lock(&link).fail(e);
+        }
+    }
+}
+
+async fn ssh(destination: &str, command: &str) -> Result<String> {
+    let output = process::Command::new("ssh")
+        .args([
+            "-o",
+            "BatchMode=yes",
+            "-o",
+            &format!("ConnectTimeout={}", CONNECT_TIMEOUT.as_secs()),
+            "--",
+            destination,
+            command,
+        ])
+        .kill_on_drop(true)
+        .output()
+        .await?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(Error::Remote(format!(
+            "ssh {destination} {}: {}",
+            output.status,
+            stderr.lines().last().unwrap_or_default()
+        )));
+    }
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+fn remote_error(e: reqwest::Error) -> Error {
+    Error::Remote(e.to_string())
+}
+
+/// Data of a `sample` event, `None` for the other events and the keep-alive comments
+fn sample_data(event: &str) -> Option<String> {
+    let mut name = None;
+    let mut data = Vec::new();
+    for line in event.lines() {
+        let (field, value) = line.split_once(':').unwrap_or((line, ""));
+        let value = value.strip_prefix(' ').unwrap_or(value);
+        match field {
+            "event" => name = Some(value),
+            "data" => data.push(value),
+            _ => {}
+        }
+    }
+    (name == Some("sample") && !data.is_empty()).then(|| data.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn joins_the_stream_path() {
+        for (address, base, stream) in [
+            (
+                "pi5:9180",
+                "http://pi5:9180/",
+                "http://pi5:9180/api/v1/stream",
+            ),
+            (
+                "http://pi5:9180",
+                "http://pi5:9180/",
+                "http://pi5:9180/api/v1/stream",
+            ),
+            (
+                "https://pi5/stats",
+                "https://pi5/stats/",
+                "https://pi5/stats/api/v1/stream",
+            ),
+            (
+                "https://pi5/stats/",
+                "https://pi5/stats/",
+                "https://pi5/stats/api/v1/stream",
+            ),
+        ] {
+            let (url, joined) = stream_url(address).unwrap();
+            assert_eq!(url.as_str(), base, "{address}");
+            assert_eq!(joined.as_str(), stream, "{address}");
+        }
+        assert!(stream_url("http://").is_err());
+    }
+
+    #[test]
+    fn reads_sample_events() {
+        assert_eq!(
+            sample_data("event: sample\ndata: {\"a\":\ndata: 1}").as_deref(),
+            Some("{\"a\":\n1}")
+        );
+        assert_eq!(sample_data("event:sample\ndata:{}").as_deref(), Some("{}"));
+        assert_eq!(sample_data(": keep-alive"), None);
+        assert_eq!(sample_data("event: error\ndata: {}"), None);
+        assert_eq!(sample_data("data: {}"), None);
+        assert_eq!(sample_data("event: sample"), None);
+    }
+
+    #[test]
+    fn receives_older_snapshots() {
+        let mut snapshot = serde_json::to_value(Rpi::default()).unwrap();
+        snapshot.as_object_mut().unwrap().remove("storage");
+        let mut link = Link::default();
+        link.receive(snapshot.to_string()).unwrap();
+        assert!(link.connected);
+        assert!(deserialize(link.snapshot.as_deref()).is_some());
+
+        assert!(link.receive(String::from("{\"cpu\": 1}")).is_err());
+        assert!(link.snapshot.is_some());
+    }
+}
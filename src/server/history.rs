@@ -0,0 +1,239 @@
+//! Time series of every metric over the last period, kept in memory for the history endpoint
+//!
+//! The series are named `<subsystem>.<name>` like `power.watts`, with one series per tag set
+//! (`{"rail": "VDD_CORE"}`). Points older than the period are dropped, as are the series
+//! without any point left, e.g. of a removed network interface.
+
+use std::{
+    collections::{BTreeMap, VecDeque},
+    time::{Duration, UNIX_EPOCH},
+};
+
+use serde::Serialize;
+
+use crate::sink::Sample;
+
+/// Unix time in seconds and the value
+type Point = (f64, f64);
+
+struct Series {
+    tags: BTreeMap<&'static str, String>,
+    points: VecDeque<Point>,
+}
+
+#[derive(Serialize)]
+pub struct SeriesPoints<'a> {
+    tags: &'a BTreeMap<&'static str, String>,
+    points: Vec<Point>,
+}
+
+pub struct History {
+    period: Duration,
+    series: BTreeMap<String, Vec<Series>>,
+}
+
+impl History {
+    pub fn new(period: Duration) -> Self {
+        Self {
+            period,
+            series: BTreeMap::new(),
+        }
+    }
+
+    pub fn push(&mut self, sample: &Sample) {
+        let time = sample
+            .time
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs_f64();
+        for metric in &sample.metrics {
+            let tags = metric.tags.iter().cloned().collect::<BTreeMap<_, _>>();
+            let series = self
+                .series
+                .entry(format!("{}.{}", metric.subsystem, metric.name))
+                .or_default();
+            let index = match series.iter().position(|series| series.tags == tags) {
+                Some(index) => index,
+                None => {
+                    series.push(Series {
+                        tags,
+                        points: VecDeque::new(),
+                    });
+                    series.len() - 1
+                }
+            };
+            series[index].points.push_back((time, metric.exact()));
+        }
+
+        let oldest = time - self.period.as_secs_f64();
+        for series in self.series.values_mut() {
+            for series in series.iter_mut() {
+                while series
+                    .points
+                    .front()
+                    .is_some_and(|(time, _)| *time < oldest)
+                {
+                    series.points.pop_front();
+                }
+            }
+            series.retain(|series| !series.points.is_empty());
+        }
+        self.series.retain(|_, series| !series.is_empty());
+    }
+
+    /// Names of the metrics with any point kept
+    pub fn metrics(&self) -> Vec<&str> {
+        self.series.keys().map(String::as_str).collect()
+    }
+
+    /// Points of every series of the metric since the Unix time, `None` for an unknown metric
+    pub fn query(&self, metric: &str, since: f64) -> Option<Vec<SeriesPoints<'_>>> {
+        let series = self.series.get(metric)?;
+        Some(
+            series
+                .iter()
+                .map(|series| SeriesPoints {
+                    tags: &series.tags,
+                    points: series
+                        .points
+                        .iter()
+                        .filter(|(time, _)| *time >= since)
+                        .copied()
+                        .collect(),
+                })
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sink::{Metric, MetricKind};
+
+    fn metric(name: &'static str, rail: Option<&str>, value: f64) -> Metric {
+        Metric {
+            subsystem: "power",
+            name,
+            tags: rail
+                .map(|rail| vec![("rail", rail.to_owned())])
+                .unwrap_or_default(),
+            value,
+            kind: MetricKind::Gauge,
+        }
+    }
+
+    fn sample(secs: u64, metrics: Vec<Metric>) -> Sample {
+        Sample {
+            time: UNIX_EPOCH + Duration::from_secs(secs),
+            hostname: None,
+            serial: None,
+            model: None,
+            energy_since: None,
+            boot_time: None,
+            metrics,
+        }
+    }
+
+    fn points(history: &History, metric: &str, since: f64) -> Vec<(Option<String>, Vec<Point>)> {
+        history
+            .query(metric, since)
+            .unwrap()
+            .into_iter()
+            .map(|series| (series.tags.get("rail").cloned(), series.points))
+            .collect()
+    }
+
+    #[test]
+    fn keeps_a_series_per_tag_set() {
+        let mut history = History::new(Duration::from_secs(60));
+        for secs in [100, 101] {
+            history.push(&sample(
+                secs,
+                vec![
+                    metric("watts", Some("VDD_CORE"), 1.5),
+                    metric("watts", Some("EXT5V"), 5.0),
+                    metric("total_watts", None, 6.5),
+                ],
+            ));
+        }
+
+        assert_eq!(history.metrics(), ["power.total_watts", "power.watts"]);
+        assert_eq!(
+            points(&history, "power.watts", f64::MIN),
+            [
+                (
+                    Some(String::from("VDD_CORE")),
+                    vec![(100.0, 1.5), (101.0, 1.5)]
+                ),
+                (
+                    Some(String::from("EXT5V")),
+                    vec![(100.0, 5.0), (101.0, 5.0)]
+                ),
+            ]
+        );
+        assert_eq!(
+            points(&history, "power.total_watts", f64::MIN),
+            [(None, vec![(100.0, 6.5), (101.0, 6.5)])]
+        );
+    }
+
+    #[test]
+    fn prunes_points_older_than_the_period() {
+        let mut history = History::new(Duration::from_secs(10));
+        for (secs, value) in [(100, 1.0), (105, 2.0), (111, 3.0)] {
+            history.push(&sample(
+                secs,
+                vec![metric("watts", Some("VDD_CORE"), value)],
+            ));
+        }
+        assert_eq!(
+            points(&history, "power.watts", f64::MIN),
+            [(
+                Some(String::from("VDD_CORE")),
+                vec![(105.0, 2.0), (111.0, 3.0)]
+            )]
+        );
+    }
+
+    #[test]
+    fn drops_series_without_points() {
+        let mut history = History::new(Duration::from_secs(10));
+        history.push(&sample(
+            100,
+            vec![
+                metric("watts", Some("VDD_CORE"), 1.0),
+                metric("watts", Some("EXT5V"), 5.0),
+                metric("total_watts", None, 6.0),
+            ],
+        ));
+        history.push(&sample(120, vec![metric("watts", Some("VDD_CORE"), 2.0)]));
+
+        assert_eq!(history.metrics(), ["power.watts"]);
+        assert_eq!(
+            points(&history, "power.watts", f64::MIN),
+            [(Some(String::from("VDD_CORE")), vec![(120.0, 2.0)])]
+        );
+        assert!(history.query("power.total_watts", f64::MIN).is_none());
+    }
+
+    #[test]
+    fn filters_points_since() {
+        let mut history = History::new(Duration::from_secs(60));
+        for secs in [100, 101, 102] {
+            history.push(&sample(
+                secs,
+                vec![metric("total_watts", None, secs as f64)],
+            ));
+        }
+        assert_eq!(
+            points(&history, "power.total_watts", 101.0),
+            [(None, vec![(101.0, 101.0), (102.0, 102.0)])]
+        );
+        assert_eq!(
+            points(&history, "power.total_watts", 103.0),
+            [(None, vec![])]
+        );
+        assert!(history.query("power.volts", f64::MIN).is_none());
+    }
+}
@@ -0,0 +1,246 @@
+//! HTTP JSON API of a running instance
+//!
+//! | Endpoint                                    | Response                                              |
+//! | ------------------------------------------- | ----------------------------------------------------- |
+//! | `GET /`                                     | Dashboard page rendering the stream in the browser    |
+//! | `GET /api/v1/snapshot`                      | Latest state of the board, the serialized [`Rpi`]     |
+//! | `GET /api/v1/metrics`                       | Names of the metrics in the history                   |
+//! | `GET /api/v1/history?metric=NAME&since=T`   | Points of the metric since the Unix time `T`          |
+//! | `GET /api/v1/stream`                        | Server-sent `sample` events with every new snapshot   |
+//!
+//! The sampling loop hands every update to [`ServerState::update`], the requests are served
+//! from the state it leaves behind and never touch the sources themselves.
+
+mod history;
+
+use std::{
+    convert::Infallible,
+    sync::{Arc, RwLock},
+    time::{Duration, UNIX_EPOCH},
+};
+
+use axum::{
+    extract::{Query, State},
+    http::{header, StatusCode},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        Html, IntoResponse, Response,
+    },
+    routing::get,
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+use tokio_stream::{wrappers::BroadcastStream, Stream, StreamExt};
+
+use crate::{sink::Sample, Rpi};
+
+use history::History;
+
+/// Self-contained page with the panels of the TUI, embedded in the binary
+const DASHBOARD: &str = include_str!("dashboard.html");
+
+/// Snapshots kept for the stream subscribers lagging behind
+const STREAM_BUFFER: usize = 16;
+
+/// Serialized state with the time it was sampled at, also printed by `pi-stats snapshot --json`
+#[derive(Serialize)]
+pub struct Snapshot<'a> {
+    /// Unix time in seconds
+    pub time: f64,
+    #[serde(flatten)]
+    pub rpi: &'a Rpi,
+}
+
+pub struct ServerState {
+    /// Latest snapshot as JSON
+    snapshot: RwLock<Arc<str>>,
+    history: RwLock<History>,
+    updates: broadcast::Sender<Arc<str>>,
+}
+
+impl ServerState {
+    /// State keeping the history of the given period
+    pub fn new(history: Duration) -> Arc<Self> {
+        Arc::new(Self {
+            snapshot: RwLock::new(Arc::from("{}")),
+            history: RwLock::new(History::new(history)),
+            updates: broadcast::channel(STREAM_BUFFER).0,
+        })
+    }
+
+    /// Publishes the state after an update of the board
+    pub fn update(&self, rpi: &Rpi) -> crate::Result<()> {
+        let sample = Sample::new(rpi);
+        let time = sample
+            .time
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs_f64();
+        let snapshot: Arc<str> = serde_json::to_string(&Snapshot { time, rpi })?.into();
+
+        *self.snapshot.write().unwrap_or_else(|e| e.into_inner()) = snapshot.clone();
+        self.history
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(&sample);
+        // No subscribers is not an error
+        let _ = self.updates.send(snapshot);
+        Ok(())
+    }
+}
+
+pub fn router(state: Arc<ServerState>) -> Router {
+    Router::new()
+        .route("/", get(Html(DASHBOARD)))
+        .route("/api/v1/snapshot", get(snapshot))
+        .route("/api/v1/metrics", get(metrics))
+        .route("/api/v1/history", get(history))
+        .route("/api/v1/stream", get(stream))
+        .with_state(state)
+}
+
+async fn snapshot(State(state): State<Arc<ServerState>>) -> Response {
+    let snapshot = state
+        .snapshot
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone();
+    (
+        [(header::CONTENT_TYPE, "application/json")],
+        snapshot.to_string(),
+    )
+        .into_response()
+}
+
+async fn metrics(State(state): State<Arc<ServerState>>) -> Response {
+    let history = state.history.read().unwrap_or_else(|e| e.into_inner());
+    Json(history.metrics()).into_response()
+}
+
+#[derive(Deserialize)]
+struct HistoryQuery {
+    metric: Option<String>,
+    /// Unix time in seconds, the whole history by default
+    since: Option<f64>,
+}
+
+#[derive(Serialize)]
+struct HistoryResponse<'a> {
+    metric: &'a str,
+    series: Vec<history::SeriesPoints<'a>>,
+}
+
+async fn history(
+    State(state): State<Arc<ServerState>>,
+    Query(query): Query<HistoryQuery>,
+) -> Response {
+    let Some(metric) = query.metric else {
+        return (
+            StatusCode::BAD_REQUEST,
+            "missing metric, see /api/v1/metrics\n",
+        )
+            .into_response();
+    };
+    let history = state.history.read().unwrap_or_else(|e| e.into_inner());
+    match history.query(&metric, query.since.unwrap_or(f64::MIN)) {
+        Some(series) => Json(HistoryResponse {
+            metric: &metric,
+            series,
+        })
+        .into_response(),
+        None => (
+            StatusCode::NOT_FOUND,
+            format!("no metric {metric}, see /api/v1/metrics\n"),
+        )
+            .into_response(),
+    }
+}
+
+async fn stream(
+    State(state): State<Arc<ServerState>>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    // Subscribers lagging behind skip the snapshots they missed
+    let updates = BroadcastStream::new(state.updates.subscribe()).filter_map(|snapshot| {
+        snapshot
+            .ok()
+            .map(|snapshot| Ok(Event::default().event("sample").data(snapshot.as_ref())))
+    });
+    Sse::new(updates).keep_alive(KeepAlive::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::Request};
+    use http_body_util::BodyExt;
+    use tower::ServiceExt;
+
+    async fn get(state: &Arc<ServerState>, uri: &str) -> (StatusCode, String) {
+        let response = router(state.clone())
+            .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    fn state_with_energy() -> Arc<ServerState> {
+        let state = ServerState::new(Duration::from_secs(60));
+        let mut rpi = Rpi::default();
+        rpi.energy.total = 1.5;
+        rpi.energy.rails.insert(String::from("VDD_CORE"), 0.5);
+        state.update(&rpi).unwrap();
+        state
+    }
+
+    #[tokio::test]
+    async fn update_publishes_the_snapshot() {
+        let state = ServerState::new(Duration::from_secs(60));
+        let mut updates = state.updates.subscribe();
+        let mut rpi = Rpi::default();
+        rpi.energy.total = 1.5;
+        state.update(&rpi).unwrap();
+
+        let (status, body) = get(&state, "/api/v1/snapshot").await;
+        assert_eq!(status, StatusCode::OK);
+        let snapshot: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert!(snapshot["time"].as_f64().is_some_and(|time| time > 0.0));
+        assert_eq!(snapshot["energy"]["total"], 1.5);
+        assert_eq!(updates.try_recv().unwrap().as_ref(), body);
+
+        let (status, body) = get(&state, "/api/v1/metrics").await;
+        assert_eq!(status, StatusCode::OK);
+        let metrics: Vec<String> = serde_json::from_str(&body).unwrap();
+        assert!(metrics.contains(&String::from("energy.total_wh")));
+    }
+
+    #[tokio::test]
+    async fn history_of_a_metric() {
+        let state = state_with_energy();
+        let (status, body) = get(&state, "/api/v1/history?metric=energy.wh").await;
+        assert_eq!(status, StatusCode::OK);
+        let history: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(history["metric"], "energy.wh");
+        assert_eq!(history["series"][0]["tags"]["rail"], "VDD_CORE");
+        assert_eq!(history["series"][0]["points"][0][1], 0.5);
+
+        let (status, body) = get(&state, "/api/v1/history?metric=energy.wh&since=1e12").await;
+        assert_eq!(status, StatusCode::OK);
+        let history: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(history["series"][0]["points"], serde_json::json!([]));
+    }
+
+    #[tokio::test]
+    async fn history_rejects_missing_and_unknown_metrics() {
+        let state = state_with_energy();
+        let (status, _) = get(&state, "/api/v1/history").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, body) = get(&state, "/api/v1/history?metric=energy.joules").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body, "no metric energy.joules, see /api/v1/metrics\n");
+        let (status, _) = get(&state, "/api/v1/history?metric=energy.wh&since=soon").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+}
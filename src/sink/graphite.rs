@@ -0,0 +1,194 @@
+//! Graphite plaintext output over TCP
+//!
+//! One `path value timestamp` line per metric, the path made of the prefix, the subsystem,
+//! the tag values and the metric name, the timestamp in seconds:
+//!
+//! ```text
+//! pi.pi5.power.VDD_CORE.watts 1.1 1760000000
+//! ```
+//!
+//! The connection is opened with the first sample and opened again after a failed write,
+//! samples taken while the server is unreachable are lost.
+
+use std::time::{Duration, UNIX_EPOCH};
+
+use tokio::{io::AsyncWriteExt, net::TcpStream};
+
+use crate::{
+    sink::{expand, Metric, Sample, Sink},
+    Error, Result,
+};
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub struct GraphiteSink {
+    address: String,
+    /// Path prefix template, see [`expand`]
+    template: String,
+    prefix: Option<String>,
+    stream: Option<TcpStream>,
+}
+
+impl GraphiteSink {
+    pub fn new(address: String, template: String) -> Self {
+        Self {
+            address,
+            template,
+            prefix: None,
+            stream: None,
+        }
+    }
+
+    async fn connect(&self) -> Result<TcpStream> {
+        match tokio::time::timeout(CONNECT_TIMEOUT, TcpStream::connect(&self.address)).await {
+            Ok(stream) => Ok(stream?),
+            Err(_) => Err(Error::Sink(format!(
+                "timed out connecting to {}",
+                self.address
+            ))),
+        }
+    }
+}
+
+impl Sink for GraphiteSink {
+    async fn send(&mut self, sample: &Sample) -> Result<()> {
+        let prefix = self
+            .prefix
+            .get_or_insert_with(|| expand(&self.template, sample, path_node));
+        let time = sample
+            .time
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let mut lines = String::new();
+        for metric in &sample.metrics {
+            lines.push_str(&format!(
+                "{} {} {time}\n",
+                metric_path(prefix, metric),
+                metric.formatted()
+            ));
+        }
+
+        let mut stream = match self.stream.take() {
+            Some(stream) => stream,
+            None => self.connect().await?,
+        };
+        stream.write_all(lines.as_bytes()).await?;
+        self.stream = Some(stream);
+        Ok(())
+    }
+
+    async fn close(&mut self) -> Result<()> {
+        if let Some(stream) = self.stream.as_mut() {
+            stream.shutdown().await?;
+        }
+        Ok(())
+    }
+}
+
+/// Dotted path of the metric under the prefix, `prefix.subsystem.tag values....name`
+pub(super) fn metric_path(prefix: &str, metric: &Metric) -> String {
+    let mut path = String::from(prefix.trim_end_matches('.'));
+    if !path.is_empty() {
+        path.push('.');
+    }
+    path.push_str(metric.subsystem);
+    for (_, value) in &metric.tags {
+        path.push('.');
+        path.push_str(&path_node(value));
+    }
+    path.push('.');
+    path.push_str(metric.name);
+    path
+}
+
+/// Value usable as one node of a path, without the separators of Graphite and StatsD
+pub(super) fn path_node(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| match c {
+            '.' | ':' | '|' | '@' | '/' => '_',
+            c if c.is_whitespace() => '_',
+            c => c,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::{io::AsyncReadExt, net::TcpListener};
+
+    use super::*;
+    use crate::sink::{MetricKind, Tags};
+
+    fn metric(subsystem: &'static str, name: &'static str, tags: Tags, value: f64) -> Metric {
+        Metric {
+            subsystem,
+            name,
+            tags,
+            value,
+            kind: MetricKind::Gauge,
+        }
+    }
+
+    fn sample(metrics: Vec<Metric>) -> Sample {
+        Sample {
+            time: UNIX_EPOCH + Duration::from_secs(1_760_000_000),
+            hostname: Some(String::from("pi5.lan")),
+            serial: None,
+            model: None,
+            energy_since: None,
+            boot_time: None,
+            metrics,
+        }
+    }
+
+    #[test]
+    fn escapes_path_nodes() {
+        assert_eq!(path_node("pi5.lan"), "pi5_lan");
+        assert_eq!(path_node("a:b|c@d/e f"), "a_b_c_d_e_f");
+        assert_eq!(path_node("VDD_CORE"), "VDD_CORE");
+    }
+
+    #[test]
+    fn builds_metric_paths() {
+        let rail = metric("power", "watts", vec![("rail", String::from("EXT5V"))], 1.0);
+        assert_eq!(metric_path("pi.pi5", &rail), "pi.pi5.power.EXT5V.watts");
+        assert_eq!(metric_path("pi.pi5.", &rail), "pi.pi5.power.EXT5V.watts");
+        assert_eq!(metric_path("", &rail), "power.EXT5V.watts");
+        let mount = metric(
+            "storage",
+            "used_bytes",
+            vec![("mount", String::from("/boot.fw"))],
+            1.0,
+        );
+        assert_eq!(metric_path("pi", &mount), "pi.storage._boot_fw.used_bytes");
+    }
+
+    #[tokio::test]
+    async fn writes_lines_over_tcp() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let mut sink = GraphiteSink::new(address.to_string(), String::from("pi.{hostname}"));
+        let sample = sample(vec![
+            metric("cpu", "temp_celsius", Vec::new(), 52.5),
+            metric(
+                "power",
+                "watts",
+                vec![("rail", String::from("VDD_CORE"))],
+                1.1,
+            ),
+        ]);
+        sink.send(&sample).await.unwrap();
+        sink.close().await.unwrap();
+
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut received = String::new();
+        stream.read_to_string(&mut received).await.unwrap();
+        assert_eq!(
+            received,
+            "pi.pi5_lan.cpu.temp_celsius 52.5 1760000000\n\
+             pi.pi5_lan.power.VDD_CORE.watts 1.1 1760000000\n"
+        );
+    }
+}
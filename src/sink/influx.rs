@@ -0,0 +1,572 @@
+//! InfluxDB line protocol output
+//!
+//! Every subsystem becomes a measurement, the metric tags (rail, core, zone, ...) and the
+//! board `host` and `serial` become tags, and the metrics sharing them become the fields
+//! of one line, timestamped in nanoseconds:
+//!
+//! ```text
+//! power,host=pi5,rail=VDD_CORE,serial=1f2e3d4c volts=0.72,amps=1.53,watts=1.1 1760000000000000000
+//! ```
+//!
+//! The lines are written to stdout, a file, a UDP socket or the HTTP `/api/v2/write`
+//! endpoint, in batches of several samples. Failed writes are retried with an exponential
+//! backoff, keeping at most [`MAX_PENDING`] bytes and dropping the oldest lines beyond it.
+//! Until a write succeeds again every sample reports the error of the last attempt.
+//! Batches rejected by the server (4xx besides 429) are dropped right away as retrying
+//! them would never succeed.
+
+use std::{
+    fmt::Write as _,
+    path::PathBuf,
+    str::FromStr,
+    time::{Duration, Instant, UNIX_EPOCH},
+};
+
+use reqwest::{StatusCode, Url};
+use tokio::{
+    fs::{File, OpenOptions},
+    io::{AsyncWriteExt, Stdout},
+    net::UdpSocket,
+};
+
+use crate::{
+    sink::{datagrams, Sample, Sink, Tags},
+    Error, Result,
+};
+
+const WRITE_PATH: &str = "/api/v2/write";
+const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+/// Lines kept while the target is unreachable
+const MAX_PENDING: usize = 1 << 20;
+const MIN_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Where the lines are written, parsed from `-`, a file path, `udp://HOST:PORT` or an HTTP URL
+#[derive(Debug, Clone)]
+pub enum InfluxTarget {
+    Stdout,
+    File(PathBuf),
+    Udp(String),
+    Http(Url),
+}
+
+impl FromStr for InfluxTarget {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if s == "-" {
+            return Ok(Self::Stdout);
+        }
+        if let Some(address) = s.strip_prefix("udp://") {
+            return Ok(Self::Udp(address.trim_end_matches('/').to_owned()));
+        }
+        if let Some(path) = s.strip_prefix("file://") {
+            return Ok(Self::File(PathBuf::from(path)));
+        }
+        if !s.starts_with("http://") && !s.starts_with("https://") {
+            return Ok(Self::File(PathBuf::from(s)));
+        }
+
+        let mut url =
+            Url::parse(s).map_err(|e| Error::InvalidInput(format!("influx URL {s}: {e}")))?;
+        if url.path() == "/" {
+            url.set_path(WRITE_PATH);
+        }
+        if !url.query_pairs().any(|(key, _)| key == "precision") {
+            url.query_pairs_mut().append_pair("precision", "ns");
+        }
+        Ok(Self::Http(url))
+    }
+}
+
+impl std::fmt::Display for InfluxTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InfluxTarget::Stdout => write!(f, "stdout"),
+            InfluxTarget::File(path) => write!(f, "{}", path.display()),
+            InfluxTarget::Udp(address) => write!(f, "udp://{address}"),
+            InfluxTarget::Http(url) => write!(f, "{url}"),
+        }
+    }
+}
+
+enum Output {
+    Stdout(Stdout),
+    File(File),
+    Udp(UdpSocket),
+    Http {
+        client: reqwest::Client,
+        url: Url,
+        token: Option<String>,
+    },
+}
+
+pub struct InfluxSink {
+    output: Output,
+    /// Samples per write
+    batch: usize,
+    /// Lines not written yet and the number of samples in them
+    pending: String,
+    pending_samples: usize,
+    /// Delay after the last failed write, when the next attempt is due and why it failed
+    backoff: Option<(Duration, Instant, String)>,
+}
+
+impl InfluxSink {
+    pub async fn new(target: InfluxTarget, token: Option<String>, batch: usize) -> Result<Self> {
+        let output = match target {
+            InfluxTarget::Stdout => Output::Stdout(tokio::io::stdout()),
+            InfluxTarget::File(path) => Output::File(
+                OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .await?,
+            ),
+            InfluxTarget::Udp(address) => {
+                let socket = UdpSocket::bind("0.0.0.0:0").await?;
+                socket.connect(&address).await?;
+                Output::Udp(socket)
+            }
+            InfluxTarget::Http(url) => Output::Http {
+                client: reqwest::Client::builder().timeout(HTTP_TIMEOUT).build()?,
+                url,
+                token,
+            },
+        };
+
+        Ok(Self {
+            output,
+            batch: batch.max(1),
+            pending: String::new(),
+            pending_samples: 0,
+            backoff: None,
+        })
+    }
+
+    async fn flush(&mut self) -> Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        match self.write().await {
+            Ok(()) => {
+                self.backoff = None;
+                self.clear();
+                Ok(())
+            }
+            Err(WriteError::Rejected(e)) => {
+                self.backoff = None;
+                self.clear();
+                Err(e)
+            }
+            Err(WriteError::Failed(e)) => {
+                let delay = self
+                    .backoff
+                    .as_ref()
+                    .map_or(MIN_BACKOFF, |(delay, _, _)| (*delay * 2).min(MAX_BACKOFF));
+                // Kept as the message of an output error, the same one until the next attempt
+                let error = match e {
+                    Error::Sink(error) => error,
+                    e => e.to_string(),
+                };
+                self.backoff = Some((delay, Instant::now() + delay, error.clone()));
+                self.trim();
+                Err(Error::Sink(error))
+            }
+        }
+    }
+
+    async fn write(&mut self) -> std::result::Result<(), WriteError> {
+        let body = self.pending.as_bytes();
+        match &mut self.output {
+            Output::Stdout(out) => {
+                out.write_all(body).await?;
+                out.flush().await?;
+            }
+            Output::File(file) => {
+                file.write_all(body).await?;
+                file.flush().await?;
+            }
+            Output::Udp(socket) => {
+                for datagram in datagrams(&self.pending) {
+                    socket.send(datagram.as_bytes()).await?;
+                }
+            }
+            Output::Http { client, url, token } => {
+                let mut request = client
+                    .post(url.clone())
+                    .header("Content-Type", "text/plain; charset=utf-8")
+                    .body(self.pending.clone());
+                if let Some(token) = token {
+                    request = request.header("Authorization", format!("Token {token}"));
+                }
+                let response = request.send().await.map_err(Error::from)?;
+                let status = response.status();
+                if !status.is_success() {
+                    let text = response.text().await.unwrap_or_default();
+                    let e = Error::Sink(format!("influx responded {status} {}", text.trim()));
+                    return Err(match status {
+                        StatusCode::TOO_MANY_REQUESTS => WriteError::Failed(e),
+                        status if status.is_client_error() => WriteError::Rejected(e),
+                        _ => WriteError::Failed(e),
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn clear(&mut self) {
+        self.pending.clear();
+        self.pending_samples = 0;
+    }
+
+    /// Drops the oldest lines above [`MAX_PENDING`]
+    fn trim(&mut self) {
+        if self.pending.len() <= MAX_PENDING {
+            return;
+        }
+        let cut = self.pending.len() - MAX_PENDING;
+        let start = self.pending[cut..]
+            .find('\n')
+            .map_or(self.pending.len(), |i| cut + i + 1);
+        self.pending.drain(..start);
+    }
+}
+
+impl Sink for InfluxSink {
+    async fn send(&mut self, sample: &Sample) -> Result<()> {
+        write_lines(&mut self.pending, sample);
+        self.pending_samples += 1;
+
+        let due = self
+            .backoff
+            .as_ref()
+            .is_none_or(|(_, at, _)| Instant::now() >= *at);
+        if self.pending_samples >= self.batch && due {
+            return self.flush().await;
+        }
+        self.trim();
+        match &self.backoff {
+            Some((_, _, error)) => Err(Error::Sink(error.clone())),
+            None => Ok(()),
+        }
+    }
+
+    async fn close(&mut self) -> Result<()> {
+        self.flush().await
+    }
+}
+
+enum WriteError {
+    /// Worth retrying, e.g. the target is unreachable
+    Failed(Error),
+    /// The lines themselves were refused
+    Rejected(Error),
+}
+
+impl From<Error> for WriteError {
+    fn from(e: Error) -> Self {
+        WriteError::Failed(e)
+    }
+}
+
+impl From<std::io::Error> for WriteError {
+    fn from(e: std::io::Error) -> Self {
+        WriteError::Failed(e.into())
+    }
+}
+
+/// Appends one line per subsystem and tag set of the sample
+fn write_lines(out: &mut String, sample: &Sample) {
+    let time = sample
+        .time
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    let board = [("host", &sample.hostname), ("serial", &sample.serial)];
+
+    let mut lines: Vec<(&str, &Tags, String)> = Vec::new();
+    for metric in &sample.metrics {
+        let mut field = escape(metric.name, ",= ");
+        let _ = write!(field, "={}", metric.formatted());
+        match lines
+            .iter_mut()
+            .find(|(subsystem, tags, _)| *subsystem == metric.subsystem && **tags == metric.tags)
+        {
+            Some((_, _, fields)) => {
+                fields.push(',');
+                fields.push_str(&field);
+            }
+            None => lines.push((metric.subsystem, &metric.tags, field)),
+        }
+    }
+
+    for (subsystem, tags, fields) in lines {
+        let mut tags = tags
+            .iter()
+            .map(|(key, value)| (*key, value.as_str()))
+            .chain(
+                board
+                    .iter()
+                    .filter_map(|(key, value)| Some((*key, value.as_deref()?))),
+            )
+            .filter(|(_, value)| !value.is_empty())
+            .collect::<Vec<_>>();
+        tags.sort_by_key(|(key, _)| *key);
+
+        out.push_str(&escape(subsystem, ", "));
+        for (key, value) in tags {
+            let _ = write!(out, ",{}={}", escape(key, ",= "), escape(value, ",= "));
+        }
+        let _ = writeln!(out, " {fields} {time}");
+    }
+}
+
+fn escape(value: &str, special: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if special.contains(c) || c == '\\' {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::VecDeque,
+        sync::{Arc, Mutex},
+        time::SystemTime,
+    };
+
+    use axum::{extract::State, http::HeaderMap, routing::post, Router};
+
+    use super::*;
+    use crate::sink::{datagrams, Metric, MetricKind, MAX_DATAGRAM};
+
+    fn metric(subsystem: &'static str, name: &'static str, tags: Tags, value: f64) -> Metric {
+        Metric {
+            subsystem,
+            name,
+            tags,
+            value,
+            kind: MetricKind::Gauge,
+        }
+    }
+
+    fn sample(metrics: Vec<Metric>) -> Sample {
+        Sample {
+            time: UNIX_EPOCH + Duration::from_secs(1_760_000_000),
+            hostname: Some(String::from("pi5")),
+            serial: Some(String::from("1f2e3d4c")),
+            model: None,
+            energy_since: None,
+            boot_time: None,
+            metrics,
+        }
+    }
+
+    fn rail(name: &str) -> Tags {
+        vec![("rail", name.to_owned())]
+    }
+
+    #[test]
+    fn groups_fields_by_subsystem_and_tags() {
+        let mut out = String::new();
+        write_lines(
+            &mut out,
+            &sample(vec![
+                metric("cpu", "temp_celsius", Vec::new(), 52.5),
+                metric("power", "volts", rail("VDD_CORE"), 0.72),
+                metric("power", "volts", rail("EXT5V"), 5.1),
+                metric("power", "amps", rail("VDD_CORE"), 1.5),
+                metric("cpu", "arm_clock_mhz", Vec::new(), 2400.0),
+            ]),
+        );
+        assert_eq!(
+            out,
+            "cpu,host=pi5,serial=1f2e3d4c temp_celsius=52.5,arm_clock_mhz=2400 1760000000000000000\n\
+             power,host=pi5,rail=VDD_CORE,serial=1f2e3d4c volts=0.72,amps=1.5 1760000000000000000\n\
+             power,host=pi5,rail=EXT5V,serial=1f2e3d4c volts=5.1 1760000000000000000\n"
+        );
+    }
+
+    #[test]
+    fn escapes_tags_and_fields() {
+        let mut sample = sample(vec![metric(
+            "storage",
+            "temp celsius",
+            vec![
+                ("device", String::from(r"nvme 0,n=1\x")),
+                ("empty", String::new()),
+            ],
+            40.0,
+        )]);
+        sample.hostname = Some(String::from("my pi"));
+        sample.serial = None;
+        let mut out = String::new();
+        write_lines(&mut out, &sample);
+        assert_eq!(
+            out,
+            "storage,device=nvme\\ 0\\,n\\=1\\\\x,host=my\\ pi temp\\ celsius=40 1760000000000000000\n"
+        );
+        assert_eq!(escape("a,b c=d", ", "), r"a\,b\ c=d");
+    }
+
+    #[test]
+    fn splits_lines_into_datagrams() {
+        let line = format!("cpu value={}\n", "1".repeat(90));
+        let lines = line.repeat(40);
+        let split = datagrams(&lines);
+        assert!(split.len() > 1);
+        assert!(split.iter().all(|datagram| datagram.len() <= MAX_DATAGRAM));
+        assert!(split.iter().all(|datagram| datagram.ends_with('\n')));
+        assert_eq!(split.concat(), lines);
+
+        // A line longer than a datagram is sent alone
+        let long = format!("cpu value={}\n", "1".repeat(MAX_DATAGRAM));
+        let lines = format!("a x=1\n{long}b x=2\n");
+        assert_eq!(datagrams(&lines), ["a x=1\n", long.as_str(), "b x=2\n"]);
+    }
+
+    #[tokio::test]
+    async fn sends_datagrams_over_udp() {
+        let server = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let address = server.local_addr().unwrap();
+        let target = InfluxTarget::from_str(&format!("udp://{address}")).unwrap();
+        let mut sink = InfluxSink::new(target, None, 1).await.unwrap();
+        let metrics = (0..64)
+            .map(|core| metric("cpu", "usage", vec![("core", core.to_string())], 99.5))
+            .collect();
+        let sample = sample(metrics);
+        sink.send(&sample).await.unwrap();
+
+        let mut expected = String::new();
+        write_lines(&mut expected, &sample);
+        let mut received = String::new();
+        let mut buf = [0; 2048];
+        while received.len() < expected.len() {
+            let len = server.recv(&mut buf).await.unwrap();
+            assert!(len <= MAX_DATAGRAM);
+            received.push_str(std::str::from_utf8(&buf[..len]).unwrap());
+        }
+        assert_eq!(received, expected);
+    }
+
+    #[tokio::test]
+    async fn trims_the_oldest_lines() {
+        let mut sink = InfluxSink::new(InfluxTarget::Stdout, None, 1)
+            .await
+            .unwrap();
+        let line = format!("cpu value={}\n", "1".repeat(1_000));
+        for i in 0..(MAX_PENDING / line.len() + 10) {
+            sink.pending.push_str(&format!("{i:08} {line}"));
+        }
+        let last = sink.pending.lines().last().unwrap().to_owned();
+        sink.trim();
+        assert!(sink.pending.len() <= MAX_PENDING);
+        assert!(sink.pending.len() > MAX_PENDING - line.len() - 10);
+        assert!(sink
+            .pending
+            .lines()
+            .all(|pending| pending.ends_with(line.trim_end())));
+        assert_eq!(sink.pending.lines().last().unwrap(), last);
+    }
+
+    /// Requests received by the stand-in, with their `Authorization` header
+    type Requests = Arc<Mutex<Vec<(Option<String>, String)>>>;
+
+    /// InfluxDB stand-in answering the writes with the given statuses, 204 after them
+    async fn stand_in(statuses: &[u16]) -> (InfluxTarget, Requests) {
+        let requests = Requests::default();
+        let statuses = Arc::new(Mutex::new(
+            statuses.iter().copied().collect::<VecDeque<_>>(),
+        ));
+        let write = {
+            let requests = requests.clone();
+            move |State(statuses): State<Arc<Mutex<VecDeque<u16>>>>,
+                  headers: HeaderMap,
+                  body: String| async move {
+                let authorization = headers
+                    .get("authorization")
+                    .map(|value| value.to_str().unwrap().to_owned());
+                requests.lock().unwrap().push((authorization, body));
+                let status = statuses.lock().unwrap().pop_front().unwrap_or(204);
+                (StatusCode::from_u16(status).unwrap(), "stand-in")
+            }
+        };
+        let router = Router::new()
+            .route(WRITE_PATH, post(write))
+            .with_state(statuses);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(axum::serve(listener, router).into_future());
+        let target = InfluxTarget::from_str(&format!("http://{address}")).unwrap();
+        (target, requests)
+    }
+
+    fn temp(value: f64) -> Sample {
+        let mut sample = sample(vec![metric("cpu", "temp_celsius", Vec::new(), value)]);
+        sample.time = SystemTime::now();
+        sample
+    }
+
+    #[tokio::test]
+    async fn retries_server_errors_with_backoff() {
+        let (target, requests) = stand_in(&[503]).await;
+        let token = Some(String::from("secret"));
+        let mut sink = InfluxSink::new(target, token, 1).await.unwrap();
+
+        let error = sink.send(&temp(50.0)).await.unwrap_err().to_string();
+        assert!(error.contains("503"), "{error}");
+        assert_eq!(sink.backoff.as_ref().unwrap().0, MIN_BACKOFF);
+
+        // Waiting for the backoff, still failing with the same error
+        let waiting = sink.send(&temp(51.0)).await.unwrap_err().to_string();
+        assert_eq!(waiting, error);
+        assert_eq!(requests.lock().unwrap().len(), 1);
+
+        if let Some((_, at, _)) = sink.backoff.as_mut() {
+            *at = Instant::now();
+        }
+        sink.send(&temp(52.0)).await.unwrap();
+        assert!(sink.backoff.is_none());
+        assert!(sink.pending.is_empty());
+        sink.send(&temp(53.0)).await.unwrap();
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 3);
+        assert!(requests
+            .iter()
+            .all(|(authorization, _)| authorization.as_deref() == Some("Token secret")));
+        let retried = &requests[1].1;
+        assert_eq!(retried.lines().count(), 3);
+        assert!(retried.contains("temp_celsius=50 "));
+        assert!(retried.contains("temp_celsius=52 "));
+        assert_eq!(requests[2].1.lines().count(), 1);
+    }
+
+    #[tokio::test]
+    async fn drops_rejected_batches() {
+        let (target, requests) = stand_in(&[400]).await;
+        let mut sink = InfluxSink::new(target, None, 1).await.unwrap();
+
+        let error = sink.send(&temp(50.0)).await.unwrap_err().to_string();
+        assert!(
+            error.contains("400") && error.contains("stand-in"),
+            "{error}"
+        );
+        assert!(sink.backoff.is_none());
+        assert!(sink.pending.is_empty());
+        sink.send(&temp(51.0)).await.unwrap();
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].0, None);
+        assert!(requests[1].1.contains("temp_celsius=51 "));
+        assert_eq!(requests[1].1.lines().count(), 1);
+    }
+}
@@ -0,0 +1,197 @@
+//! Outputs pushing every sample to external systems
+//!
+//! The sampling loop ([`App::on_tick`](crate::App)) turns the board state into one [`Sample`]
+//! per tick and hands it to every sink. Each sink runs in its own task behind a bounded queue,
+//! a slow or unreachable sink drops samples instead of stalling the sampling.
+//! The queues are drained and the sinks flushed on [`Sinks::shutdown`].
+
+mod graphite;
+mod influx;
+mod mqtt;
+mod otlp;
+mod sample;
+mod snmp;
+mod statsd;
+
+pub use graphite::GraphiteSink;
+pub use influx::{InfluxSink, InfluxTarget};
+pub use mqtt::{MqttBroker, MqttConfig, MqttSink};
+pub use otlp::OtlpSink;
+pub use sample::{Metric, MetricKind, Sample, Tags};
+pub use snmp::SnmpSink;
+pub use statsd::StatsdSink;
+
+use std::{
+    future::Future,
+    sync::{Arc, Mutex},
+};
+
+use tokio::{sync::mpsc, task::JoinHandle};
+
+use crate::{Result, Rpi};
+
+/// Samples waiting for a sink before the new ones are dropped
+const QUEUE_SIZE: usize = 64;
+/// Largest UDP datagram, keeps the lines within a common MTU
+const MAX_DATAGRAM: usize = 1_400;
+
+pub trait Sink: Send + 'static {
+    fn send(&mut self, sample: &Sample) -> impl Future<Output = Result<()>> + Send;
+
+    /// Writes out everything still buffered, called once when the sampling stops
+    fn close(&mut self) -> impl Future<Output = Result<()>> + Send {
+        async { Ok(()) }
+    }
+}
+
+struct SinkHandle {
+    name: &'static str,
+    queue: mpsc::Sender<Arc<Sample>>,
+    /// Latest error of the sink, cleared by the next successful send
+    error: Arc<Mutex<Option<String>>>,
+    dropped: u64,
+    task: JoinHandle<()>,
+}
+
+#[derive(Default)]
+pub struct Sinks {
+    sinks: Vec<SinkHandle>,
+}
+
+impl Sinks {
+    pub fn is_empty(&self) -> bool {
+        self.sinks.is_empty()
+    }
+
+    /// Runs the sink in its own task
+    pub fn spawn(&mut self, name: &'static str, mut sink: impl Sink) {
+        let (queue, mut samples) = mpsc::channel::<Arc<Sample>>(QUEUE_SIZE);
+        let error = Arc::new(Mutex::new(None));
+        let task = tokio::spawn({
+            let error = error.clone();
+            async move {
+                while let Some(sample) = samples.recv().await {
+                    let result = sink.send(&sample).await;
+                    set_error(&error, result);
+                }
+                let result = sink.close().await;
+                set_error(&error, result);
+            }
+        });
+
+        self.sinks.push(SinkHandle {
+            name,
+            queue,
+            error,
+            dropped: 0,
+            task,
+        });
+    }
+
+    pub fn publish(&mut self, rpi: &Rpi) {
+        if self.sinks.is_empty() {
+            return;
+        }
+        let sample = Arc::new(Sample::new(rpi));
+        for sink in &mut self.sinks {
+            if sink.queue.try_send(sample.clone()).is_err() {
+                sink.dropped += 1;
+            }
+        }
+    }
+
+    /// Name, latest error and number of the dropped samples of every sink
+    pub fn status(&self) -> Vec<(&'static str, Option<String>, u64)> {
+        self.sinks
+            .iter()
+            .map(|sink| {
+                let error = sink.error.lock().unwrap_or_else(|e| e.into_inner()).clone();
+                (sink.name, error, sink.dropped)
+            })
+            .collect()
+    }
+
+    /// Closes the queues and waits for the sinks to flush
+    pub async fn shutdown(self) {
+        for sink in self.sinks {
+            drop(sink.queue);
+            let _ = sink.task.await;
+        }
+    }
+}
+
+/// Replaces `{hostname}`, `{serial}` and `{model}` in the template with the identity of the board,
+/// escaped for the output, e.g. without the dots of a domain name in a Graphite path
+pub fn expand(template: &str, sample: &Sample, escape: impl Fn(&str) -> String) -> String {
+    let value = |value: &Option<String>| escape(value.as_deref().unwrap_or("unknown"));
+    template
+        .replace("{hostname}", &value(&sample.hostname))
+        .replace("{serial}", &value(&sample.serial))
+        .replace("{model}", &value(&sample.model))
+}
+
+/// Splits the lines into datagrams of at most [`MAX_DATAGRAM`] bytes, longer lines are sent alone
+fn datagrams(lines: &str) -> Vec<&str> {
+    let mut datagrams = Vec::new();
+    let mut start = 0;
+    let mut end = 0;
+    for line in lines.split_inclusive('\n') {
+        if end > start && end - start + line.len() > MAX_DATAGRAM {
+            datagrams.push(&lines[start..end]);
+            start = end;
+        }
+        end += line.len();
+    }
+    if end > start {
+        datagrams.push(&lines[start..end]);
+    }
+    datagrams
+}
+
+fn set_error(error: &Mutex<Option<String>>, result: Result<()>) {
+    *error.lock().unwrap_or_else(|e| e.into_inner()) = result.err().map(|e| e.to_string());
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::UNIX_EPOCH;
+
+    use super::*;
+
+    #[test]
+    fn expands_the_identity() {
+        let sample = Sample {
+            time: UNIX_EPOCH,
+            hostname: Some(String::from("pi5.lan")),
+            serial: None,
+            model: Some(String::from("Raspberry Pi 5")),
+            energy_since: None,
+            boot_time: None,
+            metrics: Vec::new(),
+        };
+        assert_eq!(
+            expand("pi.{hostname}.{serial}.{model}", &sample, |value| value
+                .replace(['.', ' '], "_")),
+            "pi.pi5_lan.unknown.Raspberry_Pi_5"
+        );
+        assert_eq!(expand("pi", &sample, str::to_owned), "pi");
+    }
+
+    #[test]
+    fn fills_datagrams_up_to_the_limit() {
+        let line = format!("{}\n", "x".repeat(MAX_DATAGRAM / 2 - 1));
+        let two = line.repeat(2);
+        assert_eq!(datagrams(&two), [two.as_str()]);
+        let three = line.repeat(3);
+        assert_eq!(datagrams(&three), [two.as_str(), line.as_str()]);
+        assert!(datagrams("").is_empty());
+    }
+
+    #[test]
+    fn sends_long_lines_alone() {
+        let long = format!("{}\n", "x".repeat(MAX_DATAGRAM + 1));
+        assert_eq!(datagrams(&long), [long.as_str()]);
+        let lines = format!("{long}a 1\nb 2\n");
+        assert_eq!(datagrams(&lines), [long.as_str(), "a 1\nb 2\n"]);
+    }
+}
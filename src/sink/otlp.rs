@@ -0,0 +1,572 @@
+//! OpenTelemetry metrics export over OTLP/HTTP with protobuf encoding
+//!
+//! Every metric is exported as `pi_stats.<subsystem>.<name>`, a gauge with one data point per
+//! tag set (the tags become the point attributes) or a cumulative monotonic sum for the
+//! counters (energy, network errors). The energy sums start when the energy counting started,
+//! the others at the boot of the board, both fall back to the start of the export. The board is described by the resource attributes `host.name`, `rpi.model`
+//! and `rpi.serial`.
+//!
+//! The latest sample is exported once per interval. Gauges hold the current values and sums
+//! the totals, so a failed export is not retried, the next one carries the newer state.
+
+use std::{
+    collections::BTreeMap,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use prost::Message;
+use reqwest::{StatusCode, Url};
+
+use crate::{
+    sink::{Metric, MetricKind, Sample, Sink},
+    Error, Result,
+};
+
+use proto::{
+    any_value, metric, number_data_point, AnyValue, ExportMetricsServiceRequest, Gauge,
+    InstrumentationScope, KeyValue, NumberDataPoint, Resource, ResourceMetrics, ScopeMetrics, Sum,
+};
+
+const METRICS_PATH: &str = "/v1/metrics";
+const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+const SERVICE_NAME: &str = "pi-stats";
+const METRIC_PREFIX: &str = "pi_stats";
+const CUMULATIVE: i32 = 2;
+/// Metric name suffix and its UCUM unit
+const UNITS: [(&str, &str); 10] = [
+    ("celsius", "Cel"),
+    ("volts", "V"),
+    ("amps", "A"),
+    ("watts", "W"),
+    ("wh", "W.h"),
+    ("mhz", "MHz"),
+    ("percent", "%"),
+    ("kib", "KiBy"),
+    ("bytes_per_second", "By/s"),
+    ("rpm", "{rpm}"),
+];
+
+pub struct OtlpSink {
+    client: reqwest::Client,
+    url: Url,
+    headers: Vec<(String, String)>,
+    interval: Duration,
+    /// Start of the cumulative sums
+    started: SystemTime,
+    exported: Option<SystemTime>,
+    latest: Option<Sample>,
+}
+
+impl OtlpSink {
+    /// Exports to the collector at `endpoint`, `/v1/metrics` is appended when it has no path
+    pub fn new(endpoint: &str, headers: Vec<(String, String)>, interval: Duration) -> Result<Self> {
+        let mut url = Url::parse(endpoint)
+            .map_err(|e| Error::InvalidInput(format!("OTLP endpoint {endpoint}: {e}")))?;
+        if url.path() == "/" {
+            url.set_path(METRICS_PATH);
+        }
+
+        Ok(Self {
+            client: reqwest::Client::builder().timeout(HTTP_TIMEOUT).build()?,
+            url,
+            headers,
+            interval,
+            started: SystemTime::now(),
+            exported: None,
+            latest: None,
+        })
+    }
+
+    async fn export(&mut self) -> Result<()> {
+        let Some(sample) = self.latest.take() else {
+            return Ok(());
+        };
+        self.exported = Some(sample.time);
+
+        let body = request(&sample, self.started).encode_to_vec();
+        let mut request = self
+            .client
+            .post(self.url.clone())
+            .header("Content-Type", "application/x-protobuf")
+            .body(body);
+        for (key, value) in &self.headers {
+            request = request.header(key, value);
+        }
+        let response = request.send().await?;
+        match response.status() {
+            status if status.is_success() => Ok(()),
+            StatusCode::NOT_FOUND => Err(Error::Sink(format!(
+                "{} not found, is it the OTLP/HTTP receiver?",
+                self.url
+            ))),
+            status => Err(Error::Sink(format!("collector responded {status}"))),
+        }
+    }
+}
+
+impl Sink for OtlpSink {
+    async fn send(&mut self, sample: &Sample) -> Result<()> {
+        let due = self.exported.is_none_or(|exported| {
+            sample
+                .time
+                .duration_since(exported)
+                .is_ok_and(|elapsed| elapsed >= self.interval)
+        });
+        self.latest = Some(sample.clone());
+        if due {
+            self.export().await
+        } else {
+            Ok(())
+        }
+    }
+
+    async fn close(&mut self) -> Result<()> {
+        self.export().await
+    }
+}
+
+fn request(sample: &Sample, started: SystemTime) -> ExportMetricsServiceRequest {
+    let nanos = |time: SystemTime| {
+        time.duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos() as u64
+    };
+    let time = nanos(sample.time);
+    let start = |subsystem: &str| match subsystem {
+        "energy" => nanos(sample.energy_since.unwrap_or(started)),
+        _ => nanos(sample.boot_time.unwrap_or(started)),
+    };
+
+    // Data points grouped by the metric they belong to
+    let mut metrics: BTreeMap<(&str, &str), Vec<&Metric>> = BTreeMap::new();
+    for metric in &sample.metrics {
+        metrics
+            .entry((metric.subsystem, metric.name))
+            .or_default()
+            .push(metric);
+    }
+
+    let metrics = metrics
+        .into_iter()
+        .map(|((subsystem, name), points)| {
+            let kind = points[0].kind;
+            let data_points = points
+                .into_iter()
+                .map(|metric| NumberDataPoint {
+                    attributes: metric
+                        .tags
+                        .iter()
+                        .map(|(key, value)| attribute(key, value))
+                        .collect(),
+                    start_time_unix_nano: match kind {
+                        MetricKind::Gauge => 0,
+                        MetricKind::Counter => start(subsystem),
+                    },
+                    time_unix_nano: time,
+                    value: Some(number_data_point::Value::AsDouble(metric.exact())),
+                    flags: 0,
+                })
+                .collect();
+            proto::Metric {
+                name: format!("{METRIC_PREFIX}.{subsystem}.{name}"),
+                description: String::new(),
+                unit: UNITS
+                    .iter()
+                    .find(|(suffix, _)| name.ends_with(suffix))
+                    .map(|(_, unit)| unit.to_string())
+                    .unwrap_or_default(),
+                data: Some(match kind {
+                    MetricKind::Gauge => metric::Data::Gauge(Gauge { data_points }),
+                    MetricKind::Counter => metric::Data::Sum(Sum {
+                        data_points,
+                        aggregation_temporality: CUMULATIVE,
+                        is_monotonic: true,
+                    }),
+                }),
+            }
+        })
+        .collect();
+
+    let identity = [
+        ("host.name", &sample.hostname),
+        ("rpi.model", &sample.model),
+        ("rpi.serial", &sample.serial),
+    ];
+    let attributes = [
+        attribute("service.name", SERVICE_NAME),
+        attribute("service.version", env!("CARGO_PKG_VERSION")),
+    ]
+    .into_iter()
+    .chain(
+        identity
+            .into_iter()
+            .filter_map(|(key, value)| Some(attribute(key, value.as_ref()?))),
+    )
+    .collect();
+
+    ExportMetricsServiceRequest {
+        resource_metrics: vec![ResourceMetrics {
+            resource: Some(Resource { attributes }),
+            scope_metrics: vec![ScopeMetrics {
+                scope: Some(InstrumentationScope {
+                    name: SERVICE_NAME.to_owned(),
+                    version: env!("CARGO_PKG_VERSION").to_owned(),
+                }),
+                metrics,
+            }],
+        }],
+    }
+}
+
+fn attribute(key: &str, value: &str) -> KeyValue {
+    KeyValue {
+        key: key.to_owned(),
+        value: Some(AnyValue {
+            value: Some(any_value::Value::StringValue(value.to_owned())),
+        }),
+    }
+}
+
+/// The part of the OTLP metrics protocol used by the exporter,
+/// field numbers from `opentelemetry/proto/metrics/v1/metrics.proto`
+mod proto {
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct ExportMetricsServiceRequest {
+        #[prost(message, repeated, tag = "1")]
+        pub resource_metrics: Vec<ResourceMetrics>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct ResourceMetrics {
+        #[prost(message, optional, tag = "1")]
+        pub resource: Option<Resource>,
+        #[prost(message, repeated, tag = "2")]
+        pub scope_metrics: Vec<ScopeMetrics>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Resource {
+        #[prost(message, repeated, tag = "1")]
+        pub attributes: Vec<KeyValue>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct ScopeMetrics {
+        #[prost(message, optional, tag = "1")]
+        pub scope: Option<InstrumentationScope>,
+        #[prost(message, repeated, tag = "2")]
+        pub metrics: Vec<Metric>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct InstrumentationScope {
+        #[prost(string, tag = "1")]
+        pub name: String,
+        #[prost(string, tag = "2")]
+        pub version: String,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct KeyValue {
+        #[prost(string, tag = "1")]
+        pub key: String,
+        #[prost(message, optional, tag = "2")]
+        pub value: Option<AnyValue>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct AnyValue {
+        #[prost(oneof = "any_value::Value", tags = "1")]
+        pub value: Option<any_value::Value>,
+    }
+
+    pub mod any_value {
+        #[derive(Clone, PartialEq, prost::Oneof)]
+        pub enum Value {
+            #[prost(string, tag = "1")]
+            StringValue(String),
+        }
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Metric {
+        #[prost(string, tag = "1")]
+        pub name: String,
+        #[prost(string, tag = "2")]
+        pub description: String,
+        #[prost(string, tag = "3")]
+        pub unit: String,
+        #[prost(oneof = "metric::Data", tags = "5, 7")]
+        pub data: Option<metric::Data>,
+    }
+
+    pub mod metric {
+        #[derive(Clone, PartialEq, prost::Oneof)]
+        pub enum Data {
+            #[prost(message, tag = "5")]
+            Gauge(super::Gauge),
+            #[prost(message, tag = "7")]
+            Sum(super::Sum),
+        }
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Gauge {
+        #[prost(message, repeated, tag = "1")]
+        pub data_points: Vec<NumberDataPoint>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Sum {
+        #[prost(message, repeated, tag = "1")]
+        pub data_points: Vec<NumberDataPoint>,
+        /// `AggregationTemporality`, 2 is cumulative
+        #[prost(int32, tag = "2")]
+        pub aggregation_temporality: i32,
+        #[prost(bool, tag = "3")]
+        pub is_monotonic: bool,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct NumberDataPoint {
+        #[prost(message, repeated, tag = "7")]
+        pub attributes: Vec<KeyValue>,
+        #[prost(fixed64, tag = "2")]
+        pub start_time_unix_nano: u64,
+        #[prost(fixed64, tag = "3")]
+        pub time_unix_nano: u64,
+        #[prost(oneof = "number_data_point::Value", tags = "4")]
+        pub value: Option<number_data_point::Value>,
+        #[prost(uint32, tag = "8")]
+        pub flags: u32,
+    }
+
+    pub mod number_data_point {
+        #[derive(Clone, PartialEq, prost::Oneof)]
+        pub enum Value {
+            #[prost(double, tag = "4")]
+            AsDouble(f64),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use axum::{body::Bytes, http::HeaderMap, routing::post, Router};
+
+    use super::*;
+
+    /// Requests received by the stand-in, with their `x-api-key` header
+    type Requests = Arc<Mutex<Vec<(Option<String>, Bytes)>>>;
+
+    /// OTLP/HTTP receiver stand-in accepting every export, returns its endpoint
+    async fn collector() -> (String, Requests) {
+        let requests = Requests::default();
+        let export = {
+            let requests = requests.clone();
+            move |headers: HeaderMap, body: Bytes| async move {
+                let key = headers
+                    .get("x-api-key")
+                    .map(|value| value.to_str().unwrap().to_owned());
+                requests.lock().unwrap().push((key, body));
+            }
+        };
+        let router = Router::new().route(METRICS_PATH, post(export));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(axum::serve(listener, router).into_future());
+        (format!("http://{address}"), requests)
+    }
+
+    fn metric(
+        subsystem: &'static str,
+        name: &'static str,
+        rail: Option<&str>,
+        kind: MetricKind,
+    ) -> Metric {
+        Metric {
+            subsystem,
+            name,
+            tags: rail
+                .map(|rail| vec![("rail", rail.to_owned())])
+                .unwrap_or_default(),
+            value: 2.5,
+            kind,
+        }
+    }
+
+    fn seconds(secs: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    fn find<'a>(request: &'a ExportMetricsServiceRequest, name: &str) -> &'a proto::Metric {
+        request.resource_metrics[0].scope_metrics[0]
+            .metrics
+            .iter()
+            .find(|metric| metric.name == name)
+            .unwrap_or_else(|| panic!("no metric {name}"))
+    }
+
+    fn string(attribute: &KeyValue) -> (&str, &str) {
+        let Some(AnyValue {
+            value: Some(any_value::Value::StringValue(value)),
+        }) = &attribute.value
+        else {
+            panic!("no string value of {}", attribute.key);
+        };
+        (attribute.key.as_str(), value.as_str())
+    }
+
+    #[tokio::test]
+    async fn exports_gauges_and_sums() {
+        let (endpoint, requests) = collector().await;
+        let headers = vec![(String::from("x-api-key"), String::from("secret"))];
+        let mut sink = OtlpSink::new(&endpoint, headers, Duration::from_secs(60)).unwrap();
+        sink.started = seconds(1_760_000_000);
+        let sample = Sample {
+            time: seconds(1_760_000_100),
+            hostname: Some(String::from("pi5")),
+            serial: Some(String::from("1f2e3d4c")),
+            model: Some(String::from("Raspberry Pi 5 Model B Rev 1.0")),
+            energy_since: Some(seconds(1_750_000_000)),
+            boot_time: Some(seconds(1_759_990_000)),
+            metrics: vec![
+                metric("cpu", "temp_celsius", None, MetricKind::Gauge),
+                metric("power", "watts", Some("VDD_CORE"), MetricKind::Gauge),
+                metric("power", "watts", Some("EXT5V"), MetricKind::Gauge),
+                metric("energy", "wh", Some("VDD_CORE"), MetricKind::Counter),
+                metric("network", "rx_errors", None, MetricKind::Counter),
+            ],
+        };
+        sink.send(&sample).await.unwrap();
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 1);
+        let (key, body) = &requests[0];
+        assert_eq!(key.as_deref(), Some("secret"));
+        let request = ExportMetricsServiceRequest::decode(body.as_ref()).unwrap();
+
+        let resource = request.resource_metrics[0].resource.as_ref().unwrap();
+        let attributes = resource.attributes.iter().map(string).collect::<Vec<_>>();
+        assert_eq!(
+            attributes,
+            [
+                ("service.name", "pi-stats"),
+                ("service.version", env!("CARGO_PKG_VERSION")),
+                ("host.name", "pi5"),
+                ("rpi.model", "Raspberry Pi 5 Model B Rev 1.0"),
+                ("rpi.serial", "1f2e3d4c"),
+            ]
+        );
+
+        let temp = find(&request, "pi_stats.cpu.temp_celsius");
+        assert_eq!(temp.unit, "Cel");
+        let Some(metric::Data::Gauge(gauge)) = &temp.data else {
+            panic!("temperature is not a gauge");
+        };
+        assert_eq!(gauge.data_points.len(), 1);
+        let point = &gauge.data_points[0];
+        assert_eq!(point.start_time_unix_nano, 0);
+        assert_eq!(point.time_unix_nano, 1_760_000_100_000_000_000);
+        assert_eq!(point.value, Some(number_data_point::Value::AsDouble(2.5)));
+
+        let Some(metric::Data::Gauge(watts)) = &find(&request, "pi_stats.power.watts").data else {
+            panic!("power is not a gauge");
+        };
+        let rails = watts
+            .data_points
+            .iter()
+            .map(|point| string(&point.attributes[0]))
+            .collect::<Vec<_>>();
+        assert_eq!(rails, [("rail", "VDD_CORE"), ("rail", "EXT5V")]);
+
+        let energy = find(&request, "pi_stats.energy.wh");
+        assert_eq!(energy.unit, "W.h");
+        let Some(metric::Data::Sum(sum)) = &energy.data else {
+            panic!("energy is not a sum");
+        };
+        assert!(sum.is_monotonic);
+        assert_eq!(sum.aggregation_temporality, CUMULATIVE);
+        assert_eq!(
+            sum.data_points[0].start_time_unix_nano,
+            1_750_000_000_000_000_000
+        );
+        assert_eq!(
+            string(&sum.data_points[0].attributes[0]),
+            ("rail", "VDD_CORE")
+        );
+
+        let Some(metric::Data::Sum(errors)) = &find(&request, "pi_stats.network.rx_errors").data
+        else {
+            panic!("network errors are not a sum");
+        };
+        assert_eq!(
+            errors.data_points[0].start_time_unix_nano,
+            1_759_990_000_000_000_000
+        );
+    }
+
+    #[test]
+    fn sums_start_with_the_export_without_times() {
+        let sample = Sample {
+            time: seconds(1_760_000_100),
+            hostname: None,
+            serial: None,
+            model: None,
+            energy_since: None,
+            boot_time: None,
+            metrics: vec![
+                metric("energy", "wh", Some("VDD_CORE"), MetricKind::Counter),
+                metric("network", "rx_errors", None, MetricKind::Counter),
+            ],
+        };
+        let request = request(&sample, seconds(1_760_000_000));
+        for name in ["pi_stats.energy.wh", "pi_stats.network.rx_errors"] {
+            let Some(metric::Data::Sum(sum)) = &find(&request, name).data else {
+                panic!("{name} is not a sum");
+            };
+            assert_eq!(
+                sum.data_points[0].start_time_unix_nano,
+                1_760_000_000_000_000_000
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn exports_once_per_interval() {
+        let (endpoint, requests) = collector().await;
+        let mut sink = OtlpSink::new(&endpoint, Vec::new(), Duration::from_secs(10)).unwrap();
+        let sample = |secs| Sample {
+            time: seconds(secs),
+            hostname: None,
+            serial: None,
+            model: None,
+            energy_since: None,
+            boot_time: None,
+            metrics: vec![metric("cpu", "temp_celsius", None, MetricKind::Gauge)],
+        };
+        for secs in [100, 105, 110, 111] {
+            sink.send(&sample(secs)).await.unwrap();
+        }
+        // The latest sample is exported on close
+        sink.close().await.unwrap();
+
+        let requests = requests.lock().unwrap();
+        let times = requests
+            .iter()
+            .map(|(key, body)| {
+                assert_eq!(*key, None);
+                let request = ExportMetricsServiceRequest::decode(body.as_ref()).unwrap();
+                let Some(metric::Data::Gauge(gauge)) =
+                    &find(&request, "pi_stats.cpu.temp_celsius").data
+                else {
+                    panic!("temperature is not a gauge");
+                };
+                gauge.data_points[0].time_unix_nano / 1_000_000_000
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(times, [100, 110, 111]);
+    }
+}
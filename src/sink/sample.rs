@@ -0,0 +1,259 @@
+//! Board state flattened into named metrics, shared by every sink
+//!
+//! Each metric belongs to a subsystem (`cpu`, `power`, ...) and may carry tags
+//! telling apart the rails, cores, zones or devices. Sources without a current
+//! reading are left out instead of repeating their stale values.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::Rpi;
+
+/// Tag names and values telling apart the metrics of the same name
+pub type Tags = Vec<(&'static str, String)>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetricKind {
+    Gauge,
+    /// Monotonic, e.g. the energy counters
+    Counter,
+}
+
+#[derive(Debug, Clone)]
+pub struct Metric {
+    pub subsystem: &'static str,
+    pub name: &'static str,
+    pub tags: Tags,
+    pub value: f64,
+    pub kind: MetricKind,
+}
+
+#[derive(Debug, Clone)]
+pub struct Sample {
+    pub time: SystemTime,
+    pub hostname: Option<String>,
+    pub serial: Option<String>,
+    pub model: Option<String>,
+    /// Since when the energy counters are counted
+    pub energy_since: Option<SystemTime>,
+    /// Boot of the board, the start of the other counters
+    pub boot_time: Option<SystemTime>,
+    pub metrics: Vec<Metric>,
+}
+
+impl Metric {
+    /// Value in its shortest form, readings taken as `f32` are not widened to `0.6499999761581421`
+    pub fn formatted(&self) -> String {
+        let narrow = self.value as f32;
+        if narrow as f64 == self.value {
+            narrow.to_string()
+        } else {
+            self.value.to_string()
+        }
+    }
+
+    /// Value as read, `0.41` rather than the widened `0.4099999964237213` of an `f32` reading
+    pub fn exact(&self) -> f64 {
+        self.formatted().parse().unwrap_or(self.value)
+    }
+}
+
+/// Collects the metrics of one subsystem
+struct Subsystem<'a> {
+    name: &'static str,
+    metrics: &'a mut Vec<Metric>,
+}
+
+impl<'a> Subsystem<'a> {
+    fn new(name: &'static str, metrics: &'a mut Vec<Metric>) -> Self {
+        Self { name, metrics }
+    }
+
+    fn gauge(&mut self, name: &'static str, value: impl Into<f64>) {
+        self.tagged(name, Vec::new(), value);
+    }
+
+    fn tagged(&mut self, name: &'static str, tags: Tags, value: impl Into<f64>) {
+        self.push(name, tags, value.into(), MetricKind::Gauge);
+    }
+
+    fn counter(&mut self, name: &'static str, tags: Tags, value: impl Into<f64>) {
+        self.push(name, tags, value.into(), MetricKind::Counter);
+    }
+
+    fn push(&mut self, name: &'static str, tags: Tags, value: f64, kind: MetricKind) {
+        self.metrics.push(Metric {
+            subsystem: self.name,
+            name,
+            tags,
+            value,
+            kind,
+        });
+    }
+}
+
+impl Sample {
+    pub fn new(rpi: &Rpi) -> Self {
+        let mut metrics = Vec::new();
+        let flag = |set: bool| u8::from(set);
+
+        let mut cpu = Subsystem::new("cpu", &mut metrics);
+        if let Some(temp) = rpi.cpu.temp.current() {
+            cpu.gauge("temp_celsius", **temp);
+        }
+        if let Some(clock) = rpi.cpu.clock.current() {
+            cpu.gauge("arm_clock_mhz", *clock.arm);
+            cpu.gauge("gpu_clock_mhz", *clock.gpu);
+        }
+        if let Some(usage) = rpi.system.usage.current() {
+            cpu.gauge("usage_percent", usage.total);
+            for (core, value) in usage.cores.iter().enumerate() {
+                cpu.tagged(
+                    "core_usage_percent",
+                    vec![("core", core.to_string())],
+                    *value,
+                );
+            }
+        }
+
+        let mut soc = Subsystem::new("soc", &mut metrics);
+        if let Some(volts) = rpi.soc.volts.current() {
+            for (rail, value) in volts.rails() {
+                soc.tagged("volts", vec![("rail", rail.to_owned())], value);
+            }
+        }
+        if let Some(clocks) = rpi.soc.clocks.current() {
+            for (clock, value) in &clocks.clocks {
+                soc.tagged(
+                    "clock_mhz",
+                    vec![("clock", clock.as_str().to_owned())],
+                    **value,
+                );
+            }
+        }
+        if let Some(osc) = rpi.soc.ring_osc.current() {
+            soc.gauge("ring_osc_mhz", osc.frequency);
+        }
+
+        let mut fan = Subsystem::new("fan", &mut metrics);
+        if let Some(status) = rpi.fan.current() {
+            fan.gauge("pwm", status.pwm);
+            fan.gauge("rpm", status.rpm);
+        }
+
+        let mut power = Subsystem::new("power", &mut metrics);
+        if let Some(status) = rpi.power.power.current() {
+            power.gauge("total_watts", status.total_power);
+            for rail in &status.power_map {
+                let tags = || vec![("rail", rail.measure.clone())];
+                power.tagged("volts", tags(), rail.volts);
+                power.tagged("amps", tags(), rail.amps);
+                power.tagged("watts", tags(), rail.volts * rail.amps);
+            }
+        }
+
+        let mut throttle = Subsystem::new("throttle", &mut metrics);
+        if let Some(status) = rpi.power.throttle.current() {
+            let (current, happened) = (&status.current, &status.happened);
+            throttle.gauge("under_voltage", flag(current.under_voltage));
+            throttle.gauge("frequency_capped", flag(current.arm_frequency_capped));
+            throttle.gauge("throttled", flag(current.throttled));
+            throttle.gauge("soft_temp_limit", flag(current.soft_temp_limit));
+            throttle.gauge("under_voltage_occurred", flag(happened.under_voltage));
+            throttle.gauge(
+                "frequency_capped_occurred",
+                flag(happened.arm_frequency_capped),
+            );
+            throttle.gauge("throttled_occurred", flag(happened.throttled));
+            throttle.gauge("soft_temp_limit_occurred", flag(happened.soft_temp_limit));
+        }
+
+        let mut psu = Subsystem::new("psu", &mut metrics);
+        if let Some(volts) = rpi.psu.volts {
+            psu.gauge("input_volts", volts);
+        }
+        if let Some(amps) = rpi.psu.amps {
+            psu.gauge("input_amps", amps);
+        }
+        if let Some(min) = rpi.psu.min_volts {
+            psu.gauge("min_input_volts", min);
+        }
+        psu.gauge("profile_amps", rpi.psu.profile);
+
+        let mut energy = Subsystem::new("energy", &mut metrics);
+        energy.counter("total_wh", Vec::new(), rpi.energy.total);
+        for (rail, value) in &rpi.energy.rails {
+            energy.counter("wh", vec![("rail", rail.clone())], *value);
+        }
+
+        let mut system = Subsystem::new("system", &mut metrics);
+        if let Some(load) = rpi.system.load.current() {
+            system.gauge("load1", load.one);
+            system.gauge("load5", load.five);
+            system.gauge("load15", load.fifteen);
+        }
+        if let Some(memory) = rpi.system.memory.current() {
+            system.gauge("memory_total_kib", memory.total as f64);
+            system.gauge("memory_used_kib", memory.used() as f64);
+            system.gauge("swap_used_kib", memory.swap_used() as f64);
+        }
+
+        let mut thermal = Subsystem::new("thermal", &mut metrics);
+        for zone in rpi
+            .thermal
+            .current()
+            .iter()
+            .flat_map(|thermal| &thermal.zones)
+        {
+            if let Some(temp) = zone.temp {
+                thermal.tagged("temp_celsius", vec![("zone", zone.kind.clone())], temp);
+            }
+        }
+
+        let mut storage = Subsystem::new("storage", &mut metrics);
+        for disk in rpi.storage.io.current().into_iter().flatten() {
+            let tags = || vec![("device", disk.name.clone())];
+            storage.tagged("read_bytes_per_second", tags(), disk.read);
+            storage.tagged("write_bytes_per_second", tags(), disk.write);
+        }
+        for drive in rpi.storage.nvme.current().into_iter().flatten() {
+            if let Some(temp) = drive.composite() {
+                storage.tagged(
+                    "nvme_temp_celsius",
+                    vec![("device", drive.name.clone())],
+                    temp,
+                );
+            }
+        }
+
+        let mut network = Subsystem::new("network", &mut metrics);
+        for interface in rpi.network.interfaces.current().into_iter().flatten() {
+            let tags = || vec![("interface", interface.name.clone())];
+            network.tagged("rx_bytes_per_second", tags(), interface.rx);
+            network.tagged("tx_bytes_per_second", tags(), interface.tx);
+            network.counter("rx_errors", tags(), interface.rx_errors as f64);
+            network.counter("tx_errors", tags(), interface.tx_errors as f64);
+            network.counter("rx_dropped", tags(), interface.rx_dropped as f64);
+            network.counter("tx_dropped", tags(), interface.tx_dropped as f64);
+        }
+
+        let time = SystemTime::now();
+        // Whole seconds, so the start does not shift between the samples
+        let boot_time = rpi
+            .board
+            .uptime()
+            .and_then(|uptime| time.checked_sub(uptime))
+            .and_then(|boot| boot.duration_since(UNIX_EPOCH).ok())
+            .map(|boot| UNIX_EPOCH + Duration::from_secs(boot.as_secs()));
+
+        Self {
+            time,
+            hostname: rpi.board.hostname.clone(),
+            serial: rpi.board.serial.clone(),
+            model: rpi.board.model.clone(),
+            energy_since: (rpi.energy.since > 0)
+                .then(|| UNIX_EPOCH + Duration::from_secs(rpi.energy.since)),
+            boot_time,
+            metrics,
+        }
+    }
+}
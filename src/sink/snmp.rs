@@ -0,0 +1,762 @@
+//! SNMP v2c agent answering from the latest sample
+//!
+//! The objects are defined by `PI-STATS-MIB` (`mib/PI-STATS-MIB.txt`) under
+//! `netSnmpPlaypen.314`, the experimental arc of Net-SNMP, as the project has no enterprise number:
+//!
+//! ```text
+//! 1.3.6.1.4.1.8072.9999.9999.314.1
+//!   .1  board      .1 model  .2 serial  .3 hostname
+//!   .2  cpu        .1 temperature (0.1 °C)  .2 ARM clock (MHz)  .3 GPU clock (MHz)  .4 usage (%)
+//!   .3  fan        .1 PWM (0-255)  .2 speed (RPM)
+//!   .4  power      .1 total (mW)  .2.1.<column>.<rail> table: .2 name .3 mV .4 mA .5 mW
+//!   .5  throttle   .1 `get_throttled` bits  .2-.5 active flags  .6-.9 occurred since boot
+//! ```
+//!
+//! SNMP has no floating point type, the values are scaled to integers. Sources without a current
+//! reading answer `noSuchInstance`. `GetRequest`, `GetNextRequest` and `GetBulkRequest` are served,
+//! a `SetRequest` is refused with `notWritable` and requests with another community are ignored.
+
+use std::{
+    collections::BTreeMap,
+    ops::Bound,
+    sync::{Arc, RwLock},
+};
+
+use tokio::{net::UdpSocket, task::JoinHandle};
+
+use crate::{
+    sink::{Sample, Sink},
+    Result,
+};
+
+/// `netSnmpPlaypen.314.1`, the objects of `PI-STATS-MIB`
+const OBJECTS: [u32; 11] = [1, 3, 6, 1, 4, 1, 8072, 9999, 9999, 314, 1];
+/// Responses are kept within one unfragmented UDP datagram on Ethernet
+const MAX_MESSAGE: usize = 1_472;
+const MAX_REQUEST: usize = 65_535;
+const VERSION_2C: i64 = 1;
+
+const TRUE: i64 = 1;
+const FALSE: i64 = 2;
+/// `piThrottle` objects of the throttle metrics and their bit in `vcgencmd get_throttled`
+const THROTTLE: [(&str, u32, u32); 8] = [
+    ("under_voltage", 2, 0),
+    ("frequency_capped", 3, 1),
+    ("throttled", 4, 2),
+    ("soft_temp_limit", 5, 3),
+    ("under_voltage_occurred", 6, 16),
+    ("frequency_capped_occurred", 7, 17),
+    ("throttled_occurred", 8, 18),
+    ("soft_temp_limit_occurred", 9, 19),
+];
+
+mod tag {
+    pub const INTEGER: u8 = 0x02;
+    pub const OCTET_STRING: u8 = 0x04;
+    pub const NULL: u8 = 0x05;
+    pub const OID: u8 = 0x06;
+    pub const SEQUENCE: u8 = 0x30;
+    pub const GAUGE32: u8 = 0x42;
+    pub const GET: u8 = 0xa0;
+    pub const GET_NEXT: u8 = 0xa1;
+    pub const RESPONSE: u8 = 0xa2;
+    pub const SET: u8 = 0xa3;
+    pub const GET_BULK: u8 = 0xa5;
+    pub const NO_SUCH_OBJECT: u8 = 0x80;
+    pub const NO_SUCH_INSTANCE: u8 = 0x81;
+    pub const END_OF_MIB_VIEW: u8 = 0x82;
+}
+
+mod error_status {
+    pub const NO_ERROR: i64 = 0;
+    pub const TOO_BIG: i64 = 1;
+    pub const NOT_WRITABLE: i64 = 17;
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Integer(i64),
+    Gauge(u32),
+    String(String),
+    /// Tag of `noSuchObject`, `noSuchInstance` or `endOfMibView`
+    Exception(u8),
+}
+
+type Oid = Vec<u32>;
+type View = BTreeMap<Oid, Value>;
+
+pub struct SnmpSink {
+    view: Arc<RwLock<View>>,
+    task: JoinHandle<()>,
+}
+
+impl SnmpSink {
+    /// Listens on `address`, e.g. `0.0.0.0:161`, for the requests with the `community`
+    pub async fn new(address: &str, community: String) -> Result<Self> {
+        let socket = UdpSocket::bind(address).await?;
+        let view = Arc::new(RwLock::new(View::new()));
+        let task = tokio::spawn(serve(socket, community, view.clone()));
+        Ok(Self { view, task })
+    }
+}
+
+impl Sink for SnmpSink {
+    async fn send(&mut self, sample: &Sample) -> Result<()> {
+        *self.view.write().unwrap_or_else(|e| e.into_inner()) = view(sample);
+        Ok(())
+    }
+
+    async fn close(&mut self) -> Result<()> {
+        self.task.abort();
+        Ok(())
+    }
+}
+
+async fn serve(socket: UdpSocket, community: String, view: Arc<RwLock<View>>) {
+    let mut buffer = vec![0; MAX_REQUEST];
+    loop {
+        let Ok((len, peer)) = socket.recv_from(&mut buffer).await else {
+            continue;
+        };
+        let response = {
+            let view = view.read().unwrap_or_else(|e| e.into_inner());
+            respond(&buffer[..len], &community, &view)
+        };
+        if let Some(response) = response {
+            // The manager retries a lost response
+            let _ = socket.send_to(&response, peer).await;
+        }
+    }
+}
+
+/// Objects of the sample by their OID
+fn view(sample: &Sample) -> View {
+    let mut view = View::new();
+    let mut insert = |suffix: &[u32], value: Value| {
+        view.insert([&OBJECTS[..], suffix].concat(), value);
+    };
+
+    for (object, value) in [
+        (1, &sample.model),
+        (2, &sample.serial),
+        (3, &sample.hostname),
+    ] {
+        if let Some(value) = value {
+            insert(&[1, object, 0], Value::String(value.clone()));
+        }
+    }
+
+    let mut rails: Vec<&str> = Vec::new();
+    let mut throttle = None;
+    for metric in &sample.metrics {
+        let value = metric.exact();
+        let gauge =
+            |scale: f64| Value::Gauge((value * scale).round().clamp(0.0, u32::MAX as f64) as u32);
+        match (metric.subsystem, metric.name) {
+            ("cpu", "temp_celsius") => {
+                insert(&[2, 1, 0], Value::Integer((value * 10.0).round() as i64))
+            }
+            ("cpu", "arm_clock_mhz") => insert(&[2, 2, 0], gauge(1.0)),
+            ("cpu", "gpu_clock_mhz") => insert(&[2, 3, 0], gauge(1.0)),
+            ("cpu", "usage_percent") => insert(&[2, 4, 0], gauge(1.0)),
+            ("fan", "pwm") => insert(&[3, 1, 0], gauge(1.0)),
+            ("fan", "rpm") => insert(&[3, 2, 0], gauge(1.0)),
+            ("power", "total_watts") => insert(&[4, 1, 0], gauge(1000.0)),
+            ("power", name @ ("volts" | "amps" | "watts")) => {
+                let Some((_, rail)) = metric.tags.iter().find(|(tag, _)| *tag == "rail") else {
+                    continue;
+                };
+                let index = match rails.iter().position(|known| known == rail) {
+                    Some(index) => index,
+                    None => {
+                        rails.push(rail);
+                        rails.len() - 1
+                    }
+                } as u32
+                    + 1;
+                let column = match name {
+                    "volts" => 3,
+                    "amps" => 4,
+                    _ => 5,
+                };
+                insert(&[4, 2, 1, 2, index], Value::String(rail.clone()));
+                insert(&[4, 2, 1, column, index], gauge(1000.0));
+            }
+            ("throttle", name) => {
+                if let Some((_, object, bit)) = THROTTLE.iter().find(|(flag, ..)| *flag == name) {
+                    let set = value != 0.0;
+                    insert(
+                        &[5, *object, 0],
+                        Value::Integer(if set { TRUE } else { FALSE }),
+                    );
+                    *throttle.get_or_insert(0) |= i64::from(set) << bit;
+                }
+            }
+            _ => {}
+        }
+    }
+    if let Some(bits) = throttle {
+        insert(&[5, 1, 0], Value::Integer(bits));
+    }
+    view
+}
+
+/// Response to the request, `None` for the requests not answered
+fn respond(request: &[u8], community: &str, view: &View) -> Option<Vec<u8>> {
+    let mut message = ber::Reader::new(request).sequence()?;
+    if message.integer()? != VERSION_2C || message.octet_string()? != community.as_bytes() {
+        return None;
+    }
+    let (pdu_type, pdu) = message.tlv()?;
+    let mut pdu = ber::Reader::new(pdu);
+    let request_id = pdu.integer()?;
+    let (first, second) = (pdu.integer()?, pdu.integer()?);
+    let mut bindings = pdu.sequence()?;
+    let mut oids = Vec::new();
+    while !bindings.is_empty() {
+        let mut binding = bindings.sequence()?;
+        oids.push(binding.oid()?);
+    }
+
+    let (error_status, error_index, bindings) = match pdu_type {
+        tag::GET => (error_status::NO_ERROR, 0, get(view, &oids)),
+        tag::GET_NEXT => (error_status::NO_ERROR, 0, get_next(view, &oids)),
+        tag::GET_BULK => {
+            let non_repeaters = first.clamp(0, oids.len() as i64) as usize;
+            let max_repetitions = second.max(0) as usize;
+            let bindings = get_bulk(view, &oids, non_repeaters, max_repetitions);
+            return Some(bulk_response(request_id, community, bindings));
+        }
+        tag::SET => {
+            let bindings = oids.into_iter().map(|oid| (oid, None)).collect();
+            (error_status::NOT_WRITABLE, 1, bindings)
+        }
+        _ => return None,
+    };
+
+    let encoded = response(request_id, community, error_status, error_index, &bindings);
+    if encoded.len() <= MAX_MESSAGE {
+        return Some(encoded);
+    }
+    Some(response(
+        request_id,
+        community,
+        error_status::TOO_BIG,
+        0,
+        &[],
+    ))
+}
+
+/// Variable bindings of a response, a `None` value is sent as `NULL`
+type Bindings = Vec<(Oid, Option<Value>)>;
+
+fn get(view: &View, oids: &[Oid]) -> Bindings {
+    oids.iter()
+        .map(|oid| {
+            let value = view.get(oid).cloned().unwrap_or_else(|| {
+                // Objects of the MIB without a current reading
+                Value::Exception(match oid.starts_with(&OBJECTS) {
+                    true => tag::NO_SUCH_INSTANCE,
+                    false => tag::NO_SUCH_OBJECT,
+                })
+            });
+            (oid.clone(), Some(value))
+        })
+        .collect()
+}
+
+fn next(view: &View, oid: &Oid) -> (Oid, Option<Value>) {
+    match view
+        .range::<Oid, _>((Bound::Excluded(oid), Bound::Unbounded))
+        .next()
+    {
+        Some((oid, value)) => (oid.clone(), Some(value.clone())),
+        None => (oid.clone(), Some(Value::Exception(tag::END_OF_MIB_VIEW))),
+    }
+}
+
+fn get_next(view: &View, oids: &[Oid]) -> Bindings {
+    oids.iter().map(|oid| next(view, oid)).collect()
+}
+
+fn get_bulk(view: &View, oids: &[Oid], non_repeaters: usize, max_repetitions: usize) -> Bindings {
+    let (singles, repeaters) = oids.split_at(non_repeaters);
+    let mut bindings = get_next(view, singles);
+    let mut last = repeaters.to_vec();
+    for _ in 0..max_repetitions {
+        if last.is_empty() {
+            break;
+        }
+        let row = get_next(view, &last);
+        let ended = row
+            .iter()
+            .all(|(_, value)| *value == Some(Value::Exception(tag::END_OF_MIB_VIEW)));
+        last = row.iter().map(|(oid, _)| oid.clone()).collect();
+        bindings.extend(row);
+        if ended {
+            break;
+        }
+    }
+    bindings
+}
+
+/// Response with as many of the bindings as fit, the manager continues from the last one
+fn bulk_response(request_id: i64, community: &str, mut bindings: Bindings) -> Vec<u8> {
+    loop {
+        let encoded = response(request_id, community, error_status::NO_ERROR, 0, &bindings);
+        if encoded.len() <= MAX_MESSAGE || bindings.is_empty() {
+            return encoded;
+        }
+        bindings.truncate(bindings.len() / 2);
+    }
+}
+
+fn response(
+    request_id: i64,
+    community: &str,
+    error_status: i64,
+    error_index: i64,
+    bindings: &[(Oid, Option<Value>)],
+) -> Vec<u8> {
+    let bindings = bindings
+        .iter()
+        .map(|(oid, value)| {
+            let value = match value {
+                None => ber::tlv(tag::NULL, &[]),
+                Some(Value::Integer(value)) => ber::integer(*value),
+                Some(Value::Gauge(value)) => ber::tlv(tag::GAUGE32, &ber::unsigned(*value)),
+                Some(Value::String(value)) => ber::tlv(tag::OCTET_STRING, value.as_bytes()),
+                Some(Value::Exception(tag)) => ber::tlv(*tag, &[]),
+            };
+            ber::tlv(tag::SEQUENCE, &[ber::oid(oid), value].concat())
+        })
+        .collect::<Vec<_>>()
+        .concat();
+    let pdu = [
+        ber::integer(request_id),
+        ber::integer(error_status),
+        ber::integer(error_index),
+        ber::tlv(tag::SEQUENCE, &bindings),
+    ]
+    .concat();
+    ber::tlv(
+        tag::SEQUENCE,
+        &[
+            ber::integer(VERSION_2C),
+            ber::tlv(tag::OCTET_STRING, community.as_bytes()),
+            ber::tlv(tag::RESPONSE, &pdu),
+        ]
+        .concat(),
+    )
+}
+
+/// The part of the Basic Encoding Rules used by SNMP
+mod ber {
+    use super::{tag, Oid};
+
+    pub struct Reader<'a> {
+        data: &'a [u8],
+    }
+
+    impl<'a> Reader<'a> {
+        pub fn new(data: &'a [u8]) -> Self {
+            Self { data }
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.data.is_empty()
+        }
+
+        /// Tag and content of the next element
+        pub fn tlv(&mut self) -> Option<(u8, &'a [u8])> {
+            let (&tag, rest) = self.data.split_first()?;
+            let (&first, rest) = rest.split_first()?;
+            let (len, rest) = match first {
+                0..=0x7f => (first as usize, rest),
+                0x81..=0x84 => {
+                    let (bytes, rest) = rest.split_at_checked((first & 0x7f) as usize)?;
+                    let len = bytes
+                        .iter()
+                        .fold(0usize, |len, &byte| len << 8 | byte as usize);
+                    (len, rest)
+                }
+                _ => return None,
+            };
+            let (content, rest) = rest.split_at_checked(len)?;
+            self.data = rest;
+            Some((tag, content))
+        }
+
+        fn expect(&mut self, expected: u8) -> Option<&'a [u8]> {
+            let (tag, content) = self.tlv()?;
+            (tag == expected).then_some(content)
+        }
+
+        pub fn sequence(&mut self) -> Option<Reader<'a>> {
+            self.expect(tag::SEQUENCE).map(Reader::new)
+        }
+
+        pub fn integer(&mut self) -> Option<i64> {
+            let content = self.expect(tag::INTEGER)?;
+            if content.is_empty() || content.len() > 8 {
+                return None;
+            }
+            // Sign extended from the first byte
+            let first = content[0] as i8 as i64;
+            Some(
+                content[1..]
+                    .iter()
+                    .fold(first, |value, &byte| value << 8 | byte as i64),
+            )
+        }
+
+        pub fn octet_string(&mut self) -> Option<&'a [u8]> {
+            self.expect(tag::OCTET_STRING)
+        }
+
+        pub fn oid(&mut self) -> Option<Oid> {
+            let content = self.expect(tag::OID)?;
+            let mut arcs = Vec::new();
+            let mut arc = 0u32;
+            for &byte in content {
+                arc = arc.checked_mul(128)? | (byte & 0x7f) as u32;
+                if byte & 0x80 == 0 {
+                    arcs.push(arc);
+                    arc = 0;
+                }
+            }
+            let (&first, rest) = arcs.split_first()?;
+            let (root, second) = match first {
+                0..40 => (0, first),
+                40..80 => (1, first - 40),
+                _ => (2, first - 80),
+            };
+            Some([&[root, second], rest].concat())
+        }
+    }
+
+    pub fn tlv(tag: u8, content: &[u8]) -> Vec<u8> {
+        let len = content.len();
+        let mut out = vec![tag];
+        if len < 0x80 {
+            out.push(len as u8);
+        } else {
+            let bytes = len.to_be_bytes();
+            let skip = bytes.iter().take_while(|&&byte| byte == 0).count();
+            out.push(0x80 | (bytes.len() - skip) as u8);
+            out.extend_from_slice(&bytes[skip..]);
+        }
+        out.extend_from_slice(content);
+        out
+    }
+
+    /// Two's complement in the fewest bytes
+    pub fn integer(value: i64) -> Vec<u8> {
+        let bytes = value.to_be_bytes();
+        let mut start = 0;
+        while start < bytes.len() - 1
+            && ((bytes[start] == 0x00 && bytes[start + 1] & 0x80 == 0)
+                || (bytes[start] == 0xff && bytes[start + 1] & 0x80 != 0))
+        {
+            start += 1;
+        }
+        tlv(tag::INTEGER, &bytes[start..])
+    }
+
+    /// Content of an unsigned application type like `Gauge32`
+    pub fn unsigned(value: u32) -> Vec<u8> {
+        let bytes = u64::from(value).to_be_bytes();
+        let mut start = 0;
+        while start < bytes.len() - 1 && bytes[start] == 0 && bytes[start + 1] & 0x80 == 0 {
+            start += 1;
+        }
+        bytes[start..].to_vec()
+    }
+
+    pub fn oid(oid: &[u32]) -> Vec<u8> {
+        let mut content = Vec::new();
+        let (first, rest) = match oid {
+            [root, second, rest @ ..] => (root * 40 + second, rest),
+            [root] => (root * 40, &[][..]),
+            [] => (0, &[][..]),
+        };
+        for &arc in std::iter::once(&first).chain(rest) {
+            let mut bytes = vec![(arc & 0x7f) as u8];
+            let mut arc = arc >> 7;
+            while arc > 0 {
+                bytes.push((arc & 0x7f) as u8 | 0x80);
+                arc >>= 7;
+            }
+            content.extend(bytes.iter().rev());
+        }
+        tlv(tag::OID, &content)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COMMUNITY: &str = "public";
+
+    fn object(suffix: &[u32]) -> Oid {
+        [&OBJECTS[..], suffix].concat()
+    }
+
+    fn request(pdu_type: u8, community: &str, first: i64, second: i64, oids: &[Oid]) -> Vec<u8> {
+        let bindings = oids
+            .iter()
+            .map(|oid| {
+                ber::tlv(
+                    tag::SEQUENCE,
+                    &[ber::oid(oid), ber::tlv(tag::NULL, &[])].concat(),
+                )
+            })
+            .collect::<Vec<_>>()
+            .concat();
+        let pdu = [
+            ber::integer(42),
+            ber::integer(first),
+            ber::integer(second),
+            ber::tlv(tag::SEQUENCE, &bindings),
+        ]
+        .concat();
+        ber::tlv(
+            tag::SEQUENCE,
+            &[
+                ber::integer(VERSION_2C),
+                ber::tlv(tag::OCTET_STRING, community.as_bytes()),
+                ber::tlv(pdu_type, &pdu),
+            ]
+            .concat(),
+        )
+    }
+
+    /// Error status, error index and the bindings with the tag of their value
+    type Response = (i64, i64, Vec<(Oid, u8)>);
+
+    fn parse(response: &[u8]) -> Response {
+        let mut message = ber::Reader::new(response).sequence().unwrap();
+        assert_eq!(message.integer(), Some(VERSION_2C));
+        assert_eq!(message.octet_string(), Some(COMMUNITY.as_bytes()));
+        let (pdu_type, pdu) = message.tlv().unwrap();
+        assert_eq!(pdu_type, tag::RESPONSE);
+        let mut pdu = ber::Reader::new(pdu);
+        assert_eq!(pdu.integer(), Some(42));
+        let (status, index) = (pdu.integer().unwrap(), pdu.integer().unwrap());
+        let mut bindings = pdu.sequence().unwrap();
+        let mut parsed = Vec::new();
+        while !bindings.is_empty() {
+            let mut binding = bindings.sequence().unwrap();
+            let oid = binding.oid().unwrap();
+            let (value, _) = binding.tlv().unwrap();
+            parsed.push((oid, value));
+        }
+        (status, index, parsed)
+    }
+
+    fn send(pdu_type: u8, first: i64, second: i64, oids: &[Oid], view: &View) -> Response {
+        let request = request(pdu_type, COMMUNITY, first, second, oids);
+        parse(&respond(&request, COMMUNITY, view).unwrap())
+    }
+
+    fn board() -> View {
+        View::from([
+            (
+                object(&[1, 1, 0]),
+                Value::String(String::from("Raspberry Pi 5")),
+            ),
+            (object(&[2, 1, 0]), Value::Integer(521)),
+            (object(&[3, 2, 0]), Value::Gauge(3_000)),
+        ])
+    }
+
+    #[test]
+    fn integers_at_the_sign_bit() {
+        for (value, content) in [
+            (0, &[0x00][..]),
+            (127, &[0x7f]),
+            (128, &[0x00, 0x80]),
+            (255, &[0x00, 0xff]),
+            (256, &[0x01, 0x00]),
+            (-1, &[0xff]),
+            (-128, &[0x80]),
+            (-129, &[0xff, 0x7f]),
+            (i32::MAX as i64, &[0x7f, 0xff, 0xff, 0xff]),
+            (i32::MIN as i64, &[0x80, 0x00, 0x00, 0x00]),
+        ] {
+            let encoded = ber::integer(value);
+            assert_eq!(encoded, ber::tlv(tag::INTEGER, content), "{value}");
+            assert_eq!(ber::Reader::new(&encoded).integer(), Some(value));
+        }
+        for value in [i64::MIN, i64::MAX] {
+            assert_eq!(
+                ber::Reader::new(&ber::integer(value)).integer(),
+                Some(value)
+            );
+        }
+        assert_eq!(ber::Reader::new(&[tag::INTEGER, 0]).integer(), None);
+    }
+
+    #[test]
+    fn unsigned_at_the_sign_bit() {
+        for (value, content) in [
+            (0, &[0x00][..]),
+            (127, &[0x7f]),
+            (128, &[0x00, 0x80]),
+            (0x7fff_ffff, &[0x7f, 0xff, 0xff, 0xff]),
+            (0x8000_0000, &[0x00, 0x80, 0x00, 0x00, 0x00]),
+            (u32::MAX, &[0x00, 0xff, 0xff, 0xff, 0xff]),
+        ] {
+            assert_eq!(ber::unsigned(value), content, "{value}");
+        }
+    }
+
+    #[test]
+    fn oids_with_multi_byte_arcs() {
+        for (oid, content) in [
+            (
+                vec![1, 3, 6, 1, 4, 1, 8072],
+                &[0x2b, 0x06, 0x01, 0x04, 0x01, 0xbf, 0x08][..],
+            ),
+            // The first two arcs share a byte, from 2.48 on they need more
+            (vec![2, 40], &[0x78]),
+            (vec![2, 999, 3], &[0x88, 0x37, 0x03]),
+            (vec![1, 3, u32::MAX], &[0x2b, 0x8f, 0xff, 0xff, 0xff, 0x7f]),
+        ] {
+            let encoded = ber::oid(&oid);
+            assert_eq!(encoded, ber::tlv(tag::OID, content), "{oid:?}");
+            assert_eq!(ber::Reader::new(&encoded).oid(), Some(oid));
+        }
+        assert_eq!(
+            ber::Reader::new(&ber::oid(&OBJECTS)).oid(),
+            Some(OBJECTS.to_vec())
+        );
+        // Arc above 32 bits
+        let too_large = ber::tlv(tag::OID, &[0x2b, 0x90, 0x80, 0x80, 0x80, 0x00]);
+        assert_eq!(ber::Reader::new(&too_large).oid(), None);
+    }
+
+    #[test]
+    fn long_form_lengths() {
+        for (len, header) in [
+            (127, &[tag::OCTET_STRING, 0x7f][..]),
+            (128, &[tag::OCTET_STRING, 0x81, 0x80]),
+            (300, &[tag::OCTET_STRING, 0x82, 0x01, 0x2c]),
+            (70_000, &[tag::OCTET_STRING, 0x83, 0x01, 0x11, 0x70]),
+        ] {
+            let content = vec![b'x'; len];
+            let encoded = ber::tlv(tag::OCTET_STRING, &content);
+            assert_eq!(&encoded[..header.len()], header, "{len}");
+            let mut reader = ber::Reader::new(&encoded);
+            assert_eq!(reader.octet_string(), Some(&content[..]));
+            assert!(reader.is_empty());
+        }
+
+        // Truncated content, indefinite and too long lengths
+        for invalid in [
+            &[tag::OCTET_STRING, 0x82, 0x01][..],
+            &[tag::OCTET_STRING, 0x81, 0x05, b'x'],
+            &[tag::OCTET_STRING, 0x80, b'x', 0x00, 0x00],
+            &[tag::OCTET_STRING, 0x85, 0x00, 0x00, 0x00, 0x00, 0x01, b'x'],
+        ] {
+            assert_eq!(ber::Reader::new(invalid).tlv(), None, "{invalid:?}");
+        }
+    }
+
+    #[test]
+    fn ignores_wrong_community_and_version() {
+        let view = board();
+        let oids = [object(&[2, 1, 0])];
+        let request = request(tag::GET, "private", 0, 0, &oids);
+        assert_eq!(respond(&request, COMMUNITY, &view), None);
+
+        let mut v1 = request.clone();
+        // Version INTEGER right after the message header, 0 is v1
+        let version = v1.iter().position(|&byte| byte == tag::INTEGER).unwrap();
+        v1[version + 2] = 0;
+        assert_eq!(respond(&v1, "private", &view), None);
+        assert_eq!(respond(&[0x30, 0x03, 0x02], COMMUNITY, &view), None);
+    }
+
+    #[test]
+    fn get_answers_missing_objects() {
+        let view = board();
+        let oids = [
+            object(&[2, 1, 0]),
+            object(&[3, 1, 0]),
+            vec![1, 3, 6, 1, 2, 1, 1, 5, 0],
+        ];
+        let (status, index, bindings) = send(tag::GET, 0, 0, &oids, &view);
+        assert_eq!((status, index), (error_status::NO_ERROR, 0));
+        assert_eq!(
+            bindings,
+            [
+                (oids[0].clone(), tag::INTEGER),
+                (oids[1].clone(), tag::NO_SUCH_INSTANCE),
+                (oids[2].clone(), tag::NO_SUCH_OBJECT),
+            ]
+        );
+    }
+
+    #[test]
+    fn get_next_walks_to_the_end_of_the_view() {
+        let view = board();
+        let (_, _, bindings) = send(tag::GET_NEXT, 0, 0, &[OBJECTS.to_vec()], &view);
+        assert_eq!(bindings, [(object(&[1, 1, 0]), tag::OCTET_STRING)]);
+        let (_, _, bindings) = send(tag::GET_NEXT, 0, 0, &[object(&[2, 1, 0])], &view);
+        assert_eq!(bindings, [(object(&[3, 2, 0]), tag::GAUGE32)]);
+
+        let last = [object(&[3, 2, 0])];
+        let (status, _, bindings) = send(tag::GET_NEXT, 0, 0, &last, &view);
+        assert_eq!(status, error_status::NO_ERROR);
+        assert_eq!(bindings, [(last[0].clone(), tag::END_OF_MIB_VIEW)]);
+    }
+
+    #[test]
+    fn get_bulk_stops_at_the_end_of_the_view() {
+        let view = board();
+        let oids = [object(&[1, 1, 0]), OBJECTS.to_vec()];
+        let (_, _, bindings) = send(tag::GET_BULK, 1, 10, &oids, &view);
+        assert_eq!(
+            bindings,
+            [
+                (object(&[2, 1, 0]), tag::INTEGER),
+                (object(&[1, 1, 0]), tag::OCTET_STRING),
+                (object(&[2, 1, 0]), tag::INTEGER),
+                (object(&[3, 2, 0]), tag::GAUGE32),
+                (object(&[3, 2, 0]), tag::END_OF_MIB_VIEW),
+            ]
+        );
+    }
+
+    #[test]
+    fn get_bulk_is_truncated_to_a_message() {
+        let view = (1..=200)
+            .map(|index| (object(&[4, 2, 1, 2, index]), Value::String("x".repeat(40))))
+            .collect::<View>();
+        let request = request(tag::GET_BULK, COMMUNITY, 0, 200, &[OBJECTS.to_vec()]);
+        let response = respond(&request, COMMUNITY, &view).unwrap();
+        assert!(response.len() <= MAX_MESSAGE, "{} bytes", response.len());
+
+        let (status, _, bindings) = parse(&response);
+        assert_eq!(status, error_status::NO_ERROR);
+        assert!(!bindings.is_empty() && bindings.len() < 200);
+        // The first rows, the manager continues from the last one
+        let expected = view
+            .keys()
+            .take(bindings.len())
+            .cloned()
+            .collect::<Vec<_>>();
+        let oids = bindings.into_iter().map(|(oid, _)| oid).collect::<Vec<_>>();
+        assert_eq!(oids, expected);
+    }
+
+    #[test]
+    fn set_is_not_writable() {
+        let oids = [object(&[2, 1, 0])];
+        let (status, index, bindings) = send(tag::SET, 0, 0, &oids, &board());
+        assert_eq!((status, index), (error_status::NOT_WRITABLE, 1));
+        assert_eq!(bindings, [(oids[0].clone(), tag::NULL)]);
+    }
+}
@@ -0,0 +1,105 @@
+//! StatsD gauges over UDP
+//!
+//! Every metric is sent as a gauge named like the Graphite path, several per datagram:
+//!
+//! ```text
+//! pi.pi5.cpu.temp_celsius:52.1|g
+//! pi.pi5.power.VDD_CORE.watts:1.1|g
+//! ```
+//!
+//! StatsD reads a signed gauge value as a change of the gauge, a negative value is
+//! therefore sent after resetting the gauge to zero.
+
+use tokio::net::UdpSocket;
+
+use crate::{
+    sink::{
+        datagrams, expand,
+        graphite::{metric_path, path_node},
+        Sample, Sink,
+    },
+    Result,
+};
+
+pub struct StatsdSink {
+    socket: UdpSocket,
+    /// Metric prefix template, see [`expand`]
+    template: String,
+    prefix: Option<String>,
+}
+
+impl StatsdSink {
+    pub async fn new(address: &str, template: String) -> Result<Self> {
+        let socket = UdpSocket::bind("0.0.0.0:0").await?;
+        socket.connect(address).await?;
+        Ok(Self {
+            socket,
+            template,
+            prefix: None,
+        })
+    }
+}
+
+impl Sink for StatsdSink {
+    async fn send(&mut self, sample: &Sample) -> Result<()> {
+        let prefix = self
+            .prefix
+            .get_or_insert_with(|| expand(&self.template, sample, path_node));
+        let mut lines = String::new();
+        for metric in &sample.metrics {
+            let path = metric_path(prefix, metric);
+            if metric.value < 0.0 {
+                lines.push_str(&format!("{path}:0|g\n"));
+            }
+            lines.push_str(&format!("{path}:{}|g\n", metric.formatted()));
+        }
+
+        for datagram in datagrams(&lines) {
+            self.socket.send(datagram.as_bytes()).await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::UNIX_EPOCH;
+
+    use super::*;
+    use crate::sink::{Metric, MetricKind};
+
+    #[tokio::test]
+    async fn sends_gauges_over_udp() {
+        let server = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let address = server.local_addr().unwrap();
+        let mut sink = StatsdSink::new(&address.to_string(), String::from("pi.{hostname}"))
+            .await
+            .unwrap();
+        let metric = |name, value| Metric {
+            subsystem: "power",
+            name,
+            tags: vec![("rail", String::from("VDD_CORE"))],
+            value,
+            kind: MetricKind::Gauge,
+        };
+        let sample = Sample {
+            time: UNIX_EPOCH,
+            hostname: Some(String::from("pi5.lan")),
+            serial: None,
+            model: None,
+            energy_since: None,
+            boot_time: None,
+            metrics: vec![metric("watts", 1.5), metric("offset", -0.25)],
+        };
+        sink.send(&sample).await.unwrap();
+
+        let mut buf = [0; 2048];
+        let len = server.recv(&mut buf).await.unwrap();
+        assert_eq!(
+            std::str::from_utf8(&buf[..len]).unwrap(),
+            "pi.pi5_lan.power.VDD_CORE.watts:1.5|g\n\
+             pi.pi5_lan.power.VDD_CORE.offset:0|g\n\
+             pi.pi5_lan.power.VDD_CORE.offset:-0.25|g\n"
+        );
+    }
+}
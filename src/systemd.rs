@@ -0,0 +1,145 @@
+//! Integration with systemd for the headless modes
+//!
+//! The service manager is told over `NOTIFY_SOCKET` when the sampling starts (`Type=notify`)
+//! and gets a watchdog ping after every sample (`WatchdogSec=`), so a sample stuck in a
+//! hanging `vcgencmd` gets the service restarted. The logs go to the journal with their
+//! fields (`SOURCE`, `ERRORS`, ...) when stderr is connected to it, and to stderr otherwise,
+//! each failure once until it clears or changes. Outside of systemd the notifications are no-ops.
+
+use std::{io::IsTerminal, time::Duration};
+
+use sd_notify::NotifyState;
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+
+use crate::{platform::SensorState, Rpi};
+
+/// Sends the logs to the journal under systemd, prints them on stderr otherwise
+pub fn init_logging() {
+    // Set by systemd for the services with the output connected to the journal
+    let journald = std::env::var_os("JOURNAL_STREAM")
+        .and_then(|_| tracing_journald::layer().ok())
+        .map(|layer| layer.with_field_prefix(None));
+    match journald {
+        Some(journald) => tracing_subscriber::registry().with(journald).init(),
+        None => tracing_subscriber::fmt()
+            .with_writer(std::io::stderr)
+            .with_ansi(std::io::stderr().is_terminal())
+            .with_target(false)
+            .init(),
+    }
+}
+
+/// Notifications to the service manager of a sampling loop
+pub struct Service {
+    interval: Duration,
+    watchdog: bool,
+    ready: bool,
+}
+
+impl Service {
+    /// Reads the watchdog timeout, `interval` is the time between two samples
+    pub fn new(interval: Duration) -> Self {
+        let mut usec = 0;
+        let watchdog = sd_notify::watchdog_enabled(false, &mut usec);
+        let timeout = Duration::from_micros(usec);
+        if watchdog && interval >= timeout / 2 {
+            tracing::warn!(
+                watchdog_usec = usec,
+                "watchdog timeout {timeout:?} is too short for the interval {interval:?}, \
+                 WatchdogSec= should be more than twice the interval"
+            );
+        }
+        Self {
+            interval,
+            watchdog,
+            ready: false,
+        }
+    }
+
+    /// Reports the startup as finished after the first sample and pings the watchdog after every one
+    pub fn sampled(&mut self) {
+        if !self.ready {
+            self.ready = true;
+            let status = format!("Sampling every {:?}", self.interval);
+            notify(&[NotifyState::Ready, NotifyState::Status(&status)]);
+        }
+        if self.watchdog {
+            notify(&[NotifyState::Watchdog]);
+        }
+    }
+
+    pub fn stopping(&self) {
+        notify(&[NotifyState::Stopping]);
+    }
+}
+
+fn notify(state: &[NotifyState]) {
+    // Only fails with a broken NOTIFY_SOCKET, the service manager then acts on its timeouts
+    if let Err(e) = sd_notify::notify(false, state) {
+        tracing::warn!(error = %e, "sd_notify failed");
+    }
+}
+
+/// Failure in the logs, a failed sample carries the error counter of its source
+#[derive(Debug)]
+struct Failure {
+    source: String,
+    error: String,
+    errors: Option<u64>,
+}
+
+/// Failures of a sampling loop already logged
+#[derive(Default)]
+pub struct ErrorLog {
+    reported: Vec<Failure>,
+}
+
+impl ErrorLog {
+    /// Logs the failed sources of the board and the `(source, error)` failures of the rest
+    /// not reported yet, each one once until it clears or changes
+    pub fn report(&mut self, rpi: &Rpi, others: impl IntoIterator<Item = (String, String)>) {
+        let sensors = rpi
+            .sources()
+            .into_iter()
+            .filter_map(|(source, state, errors)| match state {
+                SensorState::Error(error) => Some(Failure {
+                    source: source.to_owned(),
+                    error: error.clone(),
+                    errors: Some(errors),
+                }),
+                _ => None,
+            });
+        let others = others.into_iter().map(|(source, error)| Failure {
+            source,
+            error,
+            errors: None,
+        });
+        let failures = sensors.chain(others).collect::<Vec<_>>();
+
+        for failure in failures.iter().filter(|f| {
+            !self
+                .reported
+                .iter()
+                .any(|r| r.source == f.source && r.error == f.error)
+        }) {
+            let Failure {
+                source,
+                error,
+                errors,
+            } = failure;
+            match errors {
+                // A failed sample, the last good value of the source is kept
+                Some(errors) => tracing::warn!(source, errors, "{source}: {error}"),
+                None => tracing::error!(source, "{source}: {error}"),
+            }
+        }
+        for Failure { source, .. } in self
+            .reported
+            .iter()
+            .filter(|r| !failures.iter().any(|f| f.source == r.source))
+        {
+            tracing::info!(source, "{source}: recovered");
+        }
+        self.reported = failures;
+    }
+}